@@ -0,0 +1,10 @@
+use std::env;
+
+fn main() {
+    println!("cargo:rerun-if-env-changed=CARGO_CFG_CORTEXM_CORE");
+    // ARMv8-M Mainline cores share the PMSAv8 memory protection unit, the
+    // security extension, and the stack limit registers.
+    if env::var("CARGO_CFG_CORTEXM_CORE").map_or(false, |core| core.starts_with("cortexm33")) {
+        println!("cargo:rustc-cfg=armv8m");
+    }
+}
@@ -10,6 +10,8 @@
 extern crate proc_macro;
 
 mod sv;
+mod swo_log;
+mod test;
 mod thr;
 
 use proc_macro::TokenStream;
@@ -19,6 +21,16 @@ pub fn sv(input: TokenStream) -> TokenStream {
     sv::proc_macro(input)
 }
 
+#[proc_macro]
+pub fn swo_log(input: TokenStream) -> TokenStream {
+    swo_log::proc_macro(input)
+}
+
+#[proc_macro_attribute]
+pub fn test(args: TokenStream, input: TokenStream) -> TokenStream {
+    test::proc_macro(args, input)
+}
+
 #[proc_macro]
 pub fn thr(input: TokenStream) -> TokenStream {
     thr::proc_macro(input)
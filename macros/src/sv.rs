@@ -128,6 +128,10 @@ pub fn proc_macro(input: TokenStream) -> TokenStream {
                     ::drone_cortexm::sv::sv_call(service, #index);
                 }
             }
+
+            impl ::drone_cortexm::sv::SvCallNum<#ident> for #sv_ident {
+                const NUM: u8 = #index;
+            }
         });
     }
     let expanded = quote! {
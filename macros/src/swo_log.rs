@@ -0,0 +1,104 @@
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::{format_ident, quote};
+use syn::{
+    parse::{Parse, ParseStream, Result},
+    parse_macro_input,
+    punctuated::Punctuated,
+    Expr, LitStr, Token,
+};
+
+const SPECS: &[&str] = &["", ":?", ":x", ":X", ":b"];
+
+struct Input {
+    port: Expr,
+    format: LitStr,
+    args: Punctuated<Expr, Token![,]>,
+}
+
+impl Parse for Input {
+    fn parse(input: ParseStream<'_>) -> Result<Self> {
+        let port = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let format = input.parse()?;
+        let args = if input.is_empty() {
+            Punctuated::new()
+        } else {
+            input.parse::<Token![,]>()?;
+            Punctuated::parse_terminated(input)?
+        };
+        Ok(Self { port, format, args })
+    }
+}
+
+pub fn proc_macro(input: TokenStream) -> TokenStream {
+    let Input { port, format, args } = parse_macro_input!(input as Input);
+    let value = format.value();
+    let placeholders = match count_placeholders(&value) {
+        Ok(placeholders) => placeholders,
+        Err(message) => return syn::Error::new(format.span(), message).to_compile_error().into(),
+    };
+    if placeholders != args.len() {
+        let message = format!(
+            "format string has {} placeholders, but {} arguments were given",
+            placeholders,
+            args.len()
+        );
+        return syn::Error::new(format.span(), message).to_compile_error().into();
+    }
+    let mut bytes = value.into_bytes();
+    bytes.push(0);
+    let len = bytes.len();
+    let args = args.into_iter().collect::<Vec<_>>();
+    let idents = (0..args.len())
+        .map(|i| format_ident!("arg{}", i, span = Span::mixed_site()))
+        .collect::<Vec<_>>();
+    let expanded = quote! {{
+        #[cfg_attr(target_os = "none", link_section = ".drone_log")]
+        static FORMAT: [u8; #len] = [#(#bytes),*];
+        match (#(&(#args),)*) {
+            (#(#idents,)*) => {
+                ::drone_cortexm::swo::deferred::write(#port, &FORMAT, |port| {
+                    #(::drone_cortexm::swo::deferred::Arg::write(#idents, port);)*
+                });
+            }
+        }
+    }};
+    expanded.into()
+}
+
+fn count_placeholders(format: &str) -> std::result::Result<usize, String> {
+    if format.contains('\0') {
+        return Err("format string must not contain NUL characters".into());
+    }
+    let mut count = 0;
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => {
+                let mut spec = String::new();
+                loop {
+                    match chars.next() {
+                        Some('{') if spec.is_empty() => break,
+                        Some('}') => {
+                            if !SPECS.contains(&spec.as_str()) {
+                                return Err(format!("unsupported format spec `{{{}}}`", spec));
+                            }
+                            count += 1;
+                            break;
+                        }
+                        Some(c) => spec.push(c),
+                        None => return Err("unterminated placeholder".into()),
+                    }
+                }
+            }
+            '}' => {
+                if chars.next() != Some('}') {
+                    return Err("unmatched `}` in format string".into());
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(count)
+}
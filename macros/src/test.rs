@@ -0,0 +1,101 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    parse_macro_input, parse_quote, spanned::Spanned, Attribute, Error, Item, ItemFn, ItemMod,
+    Result, ReturnType, Type,
+};
+
+pub fn proc_macro(args: TokenStream, input: TokenStream) -> TokenStream {
+    let context = if args.is_empty() { None } else { Some(parse_macro_input!(args as Type)) };
+    let mut module = parse_macro_input!(input as ItemMod);
+    match expand(&mut module, context.as_ref()) {
+        Ok(()) => quote!(#module).into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn expand(module: &mut ItemMod, context: Option<&Type>) -> Result<()> {
+    let span = module.span();
+    let (_, items) = module
+        .content
+        .as_mut()
+        .ok_or_else(|| Error::new(span, "test suite must be an inline module"))?;
+    let mut cases = Vec::new();
+    for item in items.iter_mut() {
+        if let Item::Fn(item_fn) = item {
+            if take_attr(&mut item_fn.attrs, "test") {
+                cases.push(test_case(item_fn, context)?);
+            }
+        }
+    }
+    let context_ty = context.map_or_else(|| quote!(()), |ty| quote!(#ty));
+    items.push(parse_quote! {
+        /// The test cases of the suite.
+        pub static TESTS: &[::drone_cortexm::test::TestCase<#context_ty>] = &[#(#cases),*];
+    });
+    items.push(if context.is_some() {
+        parse_quote! {
+            /// Runs the test suite with `context` and exits with the result.
+            pub fn run(context: #context_ty) -> ! {
+                ::drone_cortexm::test::run(TESTS, context)
+            }
+        }
+    } else {
+        parse_quote! {
+            /// Runs the test suite and exits with the result.
+            pub fn run() -> ! {
+                ::drone_cortexm::test::run(TESTS, ())
+            }
+        }
+    });
+    Ok(())
+}
+
+fn test_case(item_fn: &mut ItemFn, context: Option<&Type>) -> Result<TokenStream2> {
+    let ignore = take_attr(&mut item_fn.attrs, "ignore");
+    if let Some(attr) = item_fn.attrs.iter().find(|attr| attr.path.is_ident("should_panic")) {
+        return Err(Error::new(
+            attr.span(),
+            "`#[should_panic]` is not supported, a panic terminates the test image",
+        ));
+    }
+    let sig = &item_fn.sig;
+    if sig.asyncness.is_some()
+        || !sig.generics.params.is_empty()
+        || !matches!(sig.output, ReturnType::Default)
+    {
+        return Err(Error::new(
+            sig.span(),
+            "test function must be a non-async function without generic parameters returning `()`",
+        ));
+    }
+    let ident = &sig.ident;
+    let run = match (sig.inputs.len(), context) {
+        (0, _) => quote!(|_| #ident()),
+        (1, Some(_)) => quote!(#ident),
+        (_, Some(ty)) => {
+            return Err(Error::new(
+                sig.inputs.span(),
+                format!("test function must take no arguments or `&{}`", quote!(#ty)),
+            ));
+        }
+        (_, None) => {
+            return Err(Error::new(sig.inputs.span(), "test function must take no arguments"));
+        }
+    };
+    let name = ident.to_string();
+    Ok(quote! {
+        ::drone_cortexm::test::TestCase {
+            name: ::core::concat!(::core::module_path!(), "::", #name),
+            ignore: #ignore,
+            run: #run,
+        }
+    })
+}
+
+fn take_attr(attrs: &mut Vec<Attribute>, name: &str) -> bool {
+    let len = attrs.len();
+    attrs.retain(|attr| !attr.path.is_ident(name));
+    attrs.len() != len
+}
@@ -15,6 +15,7 @@ struct Input {
     vtable: Vtable,
     index: Index,
     init: Init,
+    profiler: Option<Profiler>,
     sv: Option<Sv>,
     threads: Threads,
 }
@@ -51,6 +52,12 @@ struct Init {
     ident: Ident,
 }
 
+struct Profiler {
+    attrs: Vec<Attribute>,
+    vis: Visibility,
+    ident: Ident,
+}
+
 struct Sv {
     path: ExprPath,
 }
@@ -70,6 +77,7 @@ struct ThreadSpec {
     vis: Visibility,
     kind: ThreadKind,
     ident: Ident,
+    priority: Option<u8>,
 }
 
 enum ThreadKind {
@@ -85,6 +93,7 @@ impl Parse for Input {
         let mut vtable = None;
         let mut index = None;
         let mut init = None;
+        let mut profiler = None;
         let mut sv = None;
         let mut threads = None;
         while !input.is_empty() {
@@ -121,6 +130,12 @@ impl Parse for Input {
                 } else {
                     return Err(input.error("multiple `init` specifications"));
                 }
+            } else if ident == "profiler" {
+                if profiler.is_none() {
+                    profiler = Some(Profiler::parse(input, attrs)?);
+                } else {
+                    return Err(input.error("multiple `profiler` specifications"));
+                }
             } else if attrs.is_empty() && ident == "supervisor" {
                 if sv.is_none() {
                     sv = Some(input.parse()?);
@@ -146,6 +161,7 @@ impl Parse for Input {
             vtable: vtable.ok_or_else(|| input.error("missing `vtable` specification"))?,
             index: index.ok_or_else(|| input.error("missing `index` specification"))?,
             init: init.ok_or_else(|| input.error("missing `init` specification"))?,
+            profiler,
             sv,
             threads: threads.ok_or_else(|| input.error("missing `threads` specification"))?,
         })
@@ -198,6 +214,14 @@ impl Init {
     }
 }
 
+impl Profiler {
+    fn parse(input: ParseStream<'_>, attrs: Vec<Attribute>) -> Result<Self> {
+        let vis = input.parse()?;
+        let ident = input.parse()?;
+        Ok(Self { attrs, vis, ident })
+    }
+}
+
 impl Parse for Sv {
     fn parse(input: ParseStream<'_>) -> Result<Self> {
         let path = input.parse()?;
@@ -221,8 +245,21 @@ impl Parse for Threads {
                     let attrs = input3.call(Attribute::parse_outer)?;
                     let vis = input3.parse()?;
                     let kind = input3.parse()?;
-                    let ident = input3.parse()?;
-                    threads.push(Thread::Exception(ThreadSpec { attrs, vis, kind, ident }));
+                    let ident = input3.parse::<Ident>()?;
+                    let priority = parse_priority(&input3)?;
+                    if priority.is_some() && sys_exception(&ident).is_none() {
+                        return Err(syn::Error::new(
+                            ident.span(),
+                            format!("`{}` has a fixed priority", ident),
+                        ));
+                    }
+                    threads.push(Thread::Exception(ThreadSpec {
+                        attrs,
+                        vis,
+                        kind,
+                        ident,
+                        priority,
+                    }));
                     if !input3.is_empty() {
                         input3.parse::<Token![;]>()?;
                     }
@@ -237,7 +274,14 @@ impl Parse for Threads {
                     let vis = input3.parse()?;
                     let kind = input3.parse()?;
                     let ident = input3.parse()?;
-                    threads.push(Thread::Interrupt(num, ThreadSpec { attrs, vis, kind, ident }));
+                    let priority = parse_priority(&input3)?;
+                    threads.push(Thread::Interrupt(num, ThreadSpec {
+                        attrs,
+                        vis,
+                        kind,
+                        ident,
+                        priority,
+                    }));
                     if !input3.is_empty() {
                         input3.parse::<Token![;]>()?;
                     }
@@ -266,6 +310,7 @@ impl Thread {
             vis: Visibility::Public(VisPublic { pub_token: Token![pub](Span::call_site()) }),
             kind: ThreadKind::Inner,
             ident: format_ident!("reset"),
+            priority: None,
         })
     }
 }
@@ -292,22 +337,51 @@ impl Parse for ThreadKind {
     }
 }
 
+fn parse_priority(input: ParseStream<'_>) -> Result<Option<u8>> {
+    match input.fork().parse::<Ident>() {
+        Ok(ident) if ident == "priority" => {
+            input.parse::<Ident>()?;
+            input.parse::<Token![=]>()?;
+            Ok(Some(input.parse::<LitInt>()?.base10_parse()?))
+        }
+        _ => Ok(None),
+    }
+}
+
+fn sys_exception(ident: &Ident) -> Option<Ident> {
+    let variant = match ident.to_string().as_str() {
+        "mem_manage" => "MemManage",
+        "bus_fault" => "BusFault",
+        "usage_fault" => "UsageFault",
+        "secure_fault" => "SecureFault",
+        "sv_call" => "SvCall",
+        "debug" => "DebugMonitor",
+        "pend_sv" => "PendSv",
+        "sys_tick" => "SysTick",
+        _ => return None,
+    };
+    Some(format_ident!("{}", variant))
+}
+
 pub fn proc_macro(input: TokenStream) -> TokenStream {
-    let Input { thr, local, vtable, index, init, sv, threads } = parse_macro_input!(input as Input);
+    let Input { thr, local, vtable, index, init, profiler, sv, threads } =
+        parse_macro_input!(input as Input);
     let Threads { mut threads } = threads;
     threads.insert(0, Thread::reset());
     let threads = enumerate_threads(threads);
     let def_core_thr = def_core_thr(&thr, &local);
-    let def_vtable = def_vtable(&thr, &vtable, &threads);
-    let def_array = def_array(&thr, &threads);
+    let def_vtable = def_vtable(&thr, &vtable, &profiler, &threads);
+    let def_array = def_array(&thr, &profiler, &threads);
     let def_index = def_index(&thr, &index, &sv, &threads);
-    let def_init = def_init(&index, &init);
+    let def_init = def_init(&index, &init, &threads);
+    let def_profiler = profiler.as_ref().map(def_profiler);
     let expanded = quote! {
         #def_core_thr
         #def_vtable
         #def_array
         #def_index
         #def_init
+        #def_profiler
         ::drone_cortexm::reg::assert_taken!("scb_ccr");
         ::drone_cortexm::reg::assert_taken!("mpu_type");
         ::drone_cortexm::reg::assert_taken!("mpu_ctrl");
@@ -339,7 +413,12 @@ fn enumerate_threads(threads: Vec<Thread>) -> Vec<(Option<usize>, Thread)> {
 }
 
 #[allow(clippy::too_many_lines)]
-fn def_vtable(thr: &Thr, vtable: &Vtable, threads: &[(Option<usize>, Thread)]) -> TokenStream2 {
+fn def_vtable(
+    thr: &Thr,
+    vtable: &Vtable,
+    profiler: &Option<Profiler>,
+    threads: &[(Option<usize>, Thread)],
+) -> TokenStream2 {
     let Vtable { attrs: vtable_attrs, vis: vtable_vis, ident: vtable_ident } = vtable;
     let mut tokens = Vec::new();
     let mut vtable_tokens = Vec::new();
@@ -351,7 +430,7 @@ fn def_vtable(thr: &Thr, vtable: &Vtable, threads: &[(Option<usize>, Thread)]) -
             Thread::Exception(spec) | Thread::Interrupt(_, spec) => {
                 let ThreadSpec { kind, ident, .. } = spec;
                 let field_ident = format_ident!("{}", ident.to_string().to_snake_case());
-                let (handler, path) = def_handler(thr, idx, kind);
+                let (handler, path) = def_handler(thr, profiler, idx, kind);
                 tokens.push(handler);
                 vtable_ctor_tokens.push(quote! {
                     #field_ident: Some(#path)
@@ -441,19 +520,34 @@ fn def_vtable(thr: &Thr, vtable: &Vtable, threads: &[(Option<usize>, Thread)]) -
     }
 }
 
-fn def_array(thr: &Thr, threads: &[(Option<usize>, Thread)]) -> TokenStream2 {
+fn def_array(
+    thr: &Thr,
+    profiler: &Option<Profiler>,
+    threads: &[(Option<usize>, Thread)],
+) -> TokenStream2 {
     let Thr { ident: thr_ident, .. } = thr;
     let mut array_tokens = Vec::new();
+    let mut counters_tokens = Vec::new();
     for (idx, _) in threads {
         if let Some(idx) = idx {
             array_tokens.push(quote! {
                 #thr_ident::new(#idx)
             });
+            counters_tokens.push(quote! {
+                ::drone_cortexm::thr::profile::ThrCounters::new()
+            });
         }
     }
     let array_len = array_tokens.len();
+    let counters = profiler.as_ref().map(|_| {
+        quote! {
+            static THR_COUNTERS: [::drone_cortexm::thr::profile::ThrCounters; #array_len] =
+                [#(#counters_tokens),*];
+        }
+    });
     quote! {
         static mut THREADS: [#thr_ident; #array_len] = [#(#array_tokens),*];
+        #counters
     }
 }
 
@@ -497,9 +591,31 @@ fn def_index(
     }
 }
 
-fn def_init(index: &Index, init: &Init) -> TokenStream2 {
+fn def_init(index: &Index, init: &Init, threads: &[(Option<usize>, Thread)]) -> TokenStream2 {
     let Init { attrs: init_attrs, vis: init_vis, ident: init_ident } = init;
     let Index { ident: index_ident, .. } = index;
+    let mut priority_tokens = Vec::new();
+    for (_, thread) in threads {
+        match thread {
+            Thread::Reset(_) => {}
+            Thread::Exception(ThreadSpec { ident, priority: Some(priority), .. }) => {
+                let variant = sys_exception(ident).unwrap();
+                priority_tokens.push(quote! {
+                    ::drone_cortexm::thr::priority::ThrPriority::Sys(
+                        ::drone_cortexm::thr::priority::SysException::#variant,
+                        #priority,
+                    )
+                });
+            }
+            Thread::Interrupt(num, ThreadSpec { priority: Some(priority), .. }) => {
+                let num = *num as usize;
+                priority_tokens.push(quote! {
+                    ::drone_cortexm::thr::priority::ThrPriority::Int(#num, #priority)
+                });
+            }
+            Thread::Exception(_) | Thread::Interrupt(_, _) => {}
+        }
+    }
     quote! {
         #(#init_attrs)*
         #init_vis struct #init_ident {
@@ -517,6 +633,36 @@ fn def_init(index: &Index, init: &Init) -> TokenStream2 {
 
         unsafe impl ::drone_cortexm::thr::ThrsInitToken for #init_ident {
             type ThrTokens = #index_ident;
+
+            const PRIORITIES: &'static [::drone_cortexm::thr::priority::ThrPriority] = &[
+                #(#priority_tokens),*
+            ];
+        }
+    }
+}
+
+fn def_profiler(profiler: &Profiler) -> TokenStream2 {
+    let Profiler { attrs: profiler_attrs, vis: profiler_vis, ident: profiler_ident } = profiler;
+    quote! {
+        #(#profiler_attrs)*
+        #profiler_vis struct #profiler_ident {
+            __priv: (),
+        }
+
+        unsafe impl ::drone_core::token::Token for #profiler_ident {
+            #[inline]
+            unsafe fn take() -> Self {
+                Self {
+                    __priv: (),
+                }
+            }
+        }
+
+        unsafe impl ::drone_cortexm::thr::profile::ThrProfiler for #profiler_ident {
+            #[inline]
+            fn counters(&self) -> &'static [::drone_cortexm::thr::profile::ThrCounters] {
+                &THR_COUNTERS
+            }
         }
     }
 }
@@ -547,7 +693,7 @@ fn def_thr_token(
     let Thr { ident: thr_ident, .. } = thr;
     match thread {
         Thread::Reset(spec) | Thread::Exception(spec) | Thread::Interrupt(_, spec) => {
-            let ThreadSpec { attrs, vis, kind, ident } = spec;
+            let ThreadSpec { attrs, vis, kind, ident, .. } = spec;
             match kind {
                 ThreadKind::Inner | ThreadKind::Outer(_) => {
                     let mut tokens = Vec::new();
@@ -582,6 +728,17 @@ fn def_thr_token(
                             }
                         });
                     }
+                    if let Thread::Exception(_) = thread {
+                        if let Some(variant) = sys_exception(ident) {
+                            let sys_exception =
+                                quote!(::drone_cortexm::thr::priority::SysException);
+                            tokens.push(quote! {
+                                impl ::drone_cortexm::thr::ThrSysPriority for #struct_ident {
+                                    const SYS_EXCEPTION: #sys_exception = #sys_exception::#variant;
+                                }
+                            });
+                        }
+                    }
                     if let Thread::Interrupt(num, _) = thread {
                         let num = *num as usize;
                         let nvic_block = format_ident!("NvicBlock{}", num / 32);
@@ -610,17 +767,36 @@ fn def_thr_token(
     }
 }
 
-fn def_handler(thr: &Thr, idx: &Option<usize>, kind: &ThreadKind) -> (TokenStream2, TokenStream2) {
+fn def_handler(
+    thr: &Thr,
+    profiler: &Option<Profiler>,
+    idx: &Option<usize>,
+    kind: &ThreadKind,
+) -> (TokenStream2, TokenStream2) {
     let Thr { ident: thr_ident, .. } = thr;
+    let profiled = |call: TokenStream2| {
+        if profiler.is_some() {
+            quote! {
+                ::drone_cortexm::thr::profile::run(&THR_COUNTERS, #idx, || unsafe {
+                    #call;
+                });
+            }
+        } else {
+            quote! {
+                unsafe {
+                    #call;
+                }
+            }
+        }
+    };
     match kind {
         ThreadKind::Inner => {
             let ident = format_ident!("thr_handler_{}", idx.unwrap());
+            let call = profiled(quote!(::drone_cortexm::thr::thread_resume::<#thr_ident>(#idx)));
             (
                 quote! {
                     unsafe extern "C" fn #ident() {
-                        unsafe {
-                            ::drone_cortexm::thr::thread_resume::<#thr_ident>(#idx);
-                        }
+                        #call
                     }
                 },
                 quote!(#ident),
@@ -628,12 +804,12 @@ fn def_handler(thr: &Thr, idx: &Option<usize>, kind: &ThreadKind) -> (TokenStrea
         }
         ThreadKind::Outer(path) => {
             let ident = format_ident!("thr_handler_{}_outer", idx.unwrap());
+            let call =
+                profiled(quote!(::drone_cortexm::thr::thread_call::<#thr_ident>(#idx, #path)));
             (
                 quote! {
                     unsafe extern "C" fn #ident() {
-                        unsafe {
-                            ::drone_cortexm::thr::thread_call::<#thr_ident>(#idx, #path);
-                        }
+                        #call
                     }
                 },
                 quote!(#ident),
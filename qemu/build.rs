@@ -0,0 +1,7 @@
+use std::env;
+
+fn main() {
+    println!("cargo:rerun-if-changed=layout.ld");
+    // Let the linker find `layout.ld` passed with `-Tlayout.ld`.
+    println!("cargo:rustc-link-search={}", env::var("CARGO_MANIFEST_DIR").unwrap());
+}
@@ -0,0 +1,221 @@
+//! On-target test image of drone-cortexm for QEMU.
+//!
+//! The image exercises fiber context switching, yielding, and preemption of
+//! threads with different priorities on the `lm3s6965evb` machine. Build and
+//! run it with `just test-qemu`.
+
+#![feature(const_fn_fn_ptr_basics)]
+#![feature(marker_trait_attr)]
+#![feature(prelude_import)]
+#![feature(unsafe_block_in_unsafe_fn)]
+#![warn(unsafe_op_in_unsafe_fn)]
+#![no_std]
+#![no_main]
+
+#[prelude_import]
+#[allow(unused_imports)]
+use drone_core::prelude::*;
+
+use core::{
+    alloc::{GlobalAlloc, Layout},
+    cell::UnsafeCell,
+    ptr,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+use drone_cortexm::{
+    fault, semihosting, sv,
+    sv::{sv_handler, SwitchBackService, SwitchContextService},
+    thr,
+    thr::prelude::*,
+};
+
+const HEAP_SIZE: usize = 0x4000;
+
+thr! {
+    thread => pub Thr {};
+    local => pub ThrLocal {};
+    vtable => pub Vtable;
+    index => pub Thrs;
+    init => pub ThrsInit;
+    supervisor => Sv;
+    threads => {
+        exceptions => {
+            pub naked(fault::handler) hard_fault;
+            pub naked(sv_handler::<Sv>) sv_call;
+        };
+        interrupts => {
+            0: pub low;
+            1: pub high;
+        };
+    };
+}
+
+sv! {
+    supervisor => pub Sv;
+    array => pub SERVICES;
+    services => {
+        SwitchContextService;
+        SwitchBackService;
+    }
+}
+
+semihosting::set_log!();
+fault::set_handler!(drone_cortexm::test::report_fault);
+
+/// Bump allocator, which never frees the memory. Enough for the fiber stacks
+/// of a test run.
+struct Heap {
+    next: AtomicUsize,
+    pool: UnsafeCell<[u8; HEAP_SIZE]>,
+}
+
+unsafe impl Sync for Heap {}
+
+#[global_allocator]
+static HEAP: Heap = Heap { next: AtomicUsize::new(0), pool: UnsafeCell::new([0; HEAP_SIZE]) };
+
+unsafe impl GlobalAlloc for Heap {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let base = self.pool.get() as usize;
+        let mut next = self.next.load(Ordering::Relaxed);
+        loop {
+            let start = (base + next + layout.align() - 1) & !(layout.align() - 1);
+            let end = start - base + layout.size();
+            if end > HEAP_SIZE {
+                break ptr::null_mut();
+            }
+            match self.next.compare_exchange_weak(next, end, Ordering::Relaxed, Ordering::Relaxed) {
+                Ok(_) => break start as *mut u8,
+                Err(current) => next = current,
+            }
+        }
+    }
+
+    unsafe fn dealloc(&self, _ptr: *mut u8, _layout: Layout) {}
+}
+
+#[drone_cortexm::test(Thrs)]
+mod tests {
+    use super::{Sv, Thrs};
+    use core::{
+        pin::Pin,
+        sync::atomic::{AtomicU32, Ordering},
+    };
+    use drone_cortexm::{
+        fib::{self, Fiber},
+        processor,
+        thr::{prelude::*, priority::critical_section},
+    };
+
+    const LOW_PRIORITY: u8 = 0xC0;
+    const HIGH_PRIORITY: u8 = 0x40;
+
+    static TRACE: AtomicU32 = AtomicU32::new(0);
+
+    /// Appends the digit `step` to the trace.
+    fn trace(step: u32) {
+        TRACE.store(TRACE.load(Ordering::SeqCst) * 10 + step, Ordering::SeqCst);
+    }
+
+    fn take_trace() -> u32 {
+        TRACE.swap(0, Ordering::SeqCst)
+    }
+
+    #[test]
+    fn proc_switch() {
+        let mut fiber = unsafe {
+            fib::new_proc_unchecked::<Sv, u32, u32, u32, _>(0x400, |input, yielder| {
+                yielder.proc_yield(input + 1) * 2
+            })
+        };
+        assert!(matches!(Pin::new(&mut fiber).resume(1), fib::Yielded(2)));
+        assert!(matches!(Pin::new(&mut fiber).resume(3), fib::Complete(6)));
+    }
+
+    #[test]
+    fn proc_yield_keeps_stack() {
+        let mut fiber = unsafe {
+            fib::new_proc_unchecked::<Sv, u32, u32, u32, _>(0x400, |mut input, yielder| {
+                let mut sum = 0;
+                for _ in 0..3 {
+                    sum += input;
+                    input = yielder.proc_yield(sum);
+                }
+                sum + input
+            })
+        };
+        assert!(matches!(Pin::new(&mut fiber).resume(1), fib::Yielded(1)));
+        assert!(matches!(Pin::new(&mut fiber).resume(2), fib::Yielded(3)));
+        assert!(matches!(Pin::new(&mut fiber).resume(3), fib::Yielded(6)));
+        assert!(matches!(Pin::new(&mut fiber).resume(4), fib::Complete(10)));
+    }
+
+    #[test]
+    fn priority_preemption(thr: &Thrs) {
+        let high = thr.high;
+        thr.low.set_priority(LOW_PRIORITY);
+        thr.high.set_priority(HIGH_PRIORITY);
+        thr.low.enable_int();
+        thr.high.enable_int();
+        thr.low.add_fn(move || {
+            trace(1);
+            high.set_pending_int();
+            processor::barrier();
+            trace(3);
+            fib::Complete(())
+        });
+        thr.high.add_fn(|| {
+            trace(2);
+            fib::Complete(())
+        });
+        thr.low.set_pending_int();
+        processor::barrier();
+        assert_eq!(take_trace(), 123);
+    }
+
+    #[test]
+    fn priority_ceiling(thr: &Thrs) {
+        thr.high.set_priority(HIGH_PRIORITY);
+        thr.high.enable_int();
+        thr.high.add_fn(|| {
+            trace(2);
+            fib::Complete(())
+        });
+        critical_section(HIGH_PRIORITY, || {
+            thr.high.set_pending_int();
+            trace(1);
+        });
+        processor::barrier();
+        trace(3);
+        assert_eq!(take_trace(), 123);
+    }
+}
+
+#[no_mangle]
+#[link_section = ".vtable"]
+pub static VTABLE: Vtable = Vtable::new(reset);
+
+unsafe extern "C" fn reset() -> ! {
+    extern "C" {
+        static mut DATA_START: u32;
+        static DATA_END: u32;
+        static DATA_LOAD: u32;
+        static mut BSS_START: u32;
+        static BSS_END: u32;
+    }
+    unsafe {
+        let mut load = &DATA_LOAD as *const u32;
+        let mut data = &mut DATA_START as *mut u32;
+        while data < &DATA_END as *const u32 as *mut u32 {
+            ptr::write_volatile(data, ptr::read(load));
+            data = data.add(1);
+            load = load.add(1);
+        }
+        let mut bss = &mut BSS_START as *mut u32;
+        while bss < &BSS_END as *const u32 as *mut u32 {
+            ptr::write_volatile(bss, 0);
+            bss = bss.add(1);
+        }
+    }
+    tests::run(thr::init(unsafe { ThrsInit::take() }))
+}
@@ -0,0 +1,165 @@
+//! Cortex-M Security Extensions.
+//!
+//! This module helps building a Secure image, e.g. a bootloader, and a
+//! Non-secure application that calls into it.
+//!
+//! * [`nonsecure_entry!`] exports a Secure function to the Non-secure state
+//!   through a secure gateway veneer.
+//! * [`NonSecureFn`] calls a Non-secure function from the Secure state.
+//!
+//! Veneers are placed in the `.gnu.sgstubs` section, which must be located in
+//! a Non-secure callable region configured with
+//! [`drv::sau`](crate::drv::sau).
+//!
+//! **NOTE** Neither the veneers nor [`NonSecureFn::call`] clear the
+//! floating-point registers. A Secure image that keeps secrets in
+//! floating-point registers must clear them by itself.
+
+#![cfg_attr(feature = "std", allow(unreachable_code, unused_variables, unused_mut))]
+
+/// A function pointer to a Non-secure function.
+///
+/// The function must follow the AAPCS and take up to four word-sized
+/// arguments.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NonSecureFn {
+    addr: u32,
+}
+
+impl NonSecureFn {
+    /// Creates a Non-secure function pointer from the address `addr`, e.g. the
+    /// reset handler from the Non-secure vector table.
+    ///
+    /// # Safety
+    ///
+    /// `addr` must point to a function located in Non-secure memory.
+    #[inline]
+    pub const unsafe fn from_addr(addr: u32) -> Self {
+        Self { addr }
+    }
+
+    /// Returns the address of the function.
+    #[inline]
+    pub fn addr(&self) -> u32 {
+        self.addr
+    }
+
+    /// Calls the function with `args` in `r0`-`r3` and returns `r0`.
+    ///
+    /// General-purpose registers `r4`-`r11` and the condition flags are cleared
+    /// before the transition to the Non-secure state, so no Secure data leaks
+    /// through them.
+    ///
+    /// # Safety
+    ///
+    /// The function must be safe to call with `args`.
+    pub unsafe fn call(&self, args: [u32; 4]) -> u32 {
+        let [mut r0, mut r1, mut r2, mut r3] = args;
+        let mut addr = self.addr;
+        #[cfg(feature = "std")]
+        return unimplemented!();
+        unsafe {
+            llvm_asm!("
+                push    {r4-r11}
+                mov     r4, #0
+                mov     r5, #0
+                mov     r6, #0
+                mov     r7, #0
+                mov     r8, #0
+                mov     r9, #0
+                mov     r10, #0
+                mov     r11, #0
+                msr     apsr_nzcvq, r4
+                bic     r12, r12, #1
+                blxns   r12
+                pop     {r4-r11}
+            "   : "+{r0}"(r0), "+{r1}"(r1), "+{r2}"(r2), "+{r3}"(r3), "+{r12}"(addr)
+                :
+                : "lr", "cc", "memory"
+                : "volatile"
+            );
+        }
+        r0
+    }
+}
+
+/// Assembly of a secure gateway veneer, with the address of the Secure target
+/// function as the operand `$0`.
+///
+/// The whole veneer is a single assembly block, so no compiler-generated
+/// instruction precedes SG or touches the argument registers.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! cmse_veneer_asm {
+    () => {
+        concat!(
+            "sg\n",
+            "push {r4, lr}\n",
+            "movw r12, :lower16:${0:c}\n",
+            "movt r12, :upper16:${0:c}\n",
+            "blx r12\n",
+            "pop {r4, lr}\n",
+            "mov r1, #0\n",
+            "mov r2, #0\n",
+            "mov r3, #0\n",
+            "mov r12, #0\n",
+            "msr apsr_nzcvq, r1\n",
+            "bxns lr\n",
+        )
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+#[allow_internal_unstable(core_intrinsics, llvm_asm)]
+macro_rules! cmse_nonsecure_entry {
+    ($($(#[$attr:meta])* $vis:vis fn $name:ident => $target:path;)*) => {
+        $(
+            $(#[$attr])*
+            #[naked]
+            #[no_mangle]
+            #[link_section = ".gnu.sgstubs"]
+            $vis unsafe extern "C" fn $name() {
+                #[cfg(target_arch = "arm")]
+                unsafe {
+                    llvm_asm!($crate::cmse_veneer_asm!()
+                        :
+                        : "i"($target as usize)
+                        :
+                        : "volatile"
+                    );
+                    ::core::intrinsics::unreachable()
+                }
+            }
+        )*
+    };
+}
+
+/// Exports Secure functions to the Non-secure state.
+///
+/// Each entry defines a secure gateway veneer `$name` for the `extern "C"`
+/// function `$target`. The veneer starts with an SG instruction, calls
+/// `$target` with the arguments in `r0`-`r3`, clears `r1`-`r3`, `r12`, and
+/// the condition flags, and returns `r0` to the Non-secure caller with BXNS.
+///
+/// The Non-secure image should declare the veneers as `extern "C"` functions
+/// with the signatures of the targets, and link against the veneer addresses.
+///
+/// # Examples
+///
+/// ```no_run
+/// # #![feature(naked_functions)]
+/// use drone_cortexm::cmse;
+///
+/// extern "C" fn read_counter(index: u32) -> u32 {
+///     index * 2
+/// }
+///
+/// cmse::nonsecure_entry! {
+///     /// Reads a counter owned by the Secure image.
+///     pub fn nsc_read_counter => read_counter;
+/// }
+/// # fn main() {}
+/// ```
+#[doc(inline)]
+pub use crate::cmse_nonsecure_entry as nonsecure_entry;
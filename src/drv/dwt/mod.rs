@@ -0,0 +1,347 @@
+//! Data Watchpoint and Trace unit.
+//!
+//! The DWT cycle counter is a 32-bit counter incremented on each processor
+//! clock cycle. It wraps around every 2<sup>32</sup> cycles, which is about 60
+//! seconds at 72 MHz. [`Instant`] arithmetic is wrapping, so durations are
+//! correct as long as they are shorter than the wrap period.
+//!
+//! [`DwtTimer`] implements [`Timer`] by polling the counter from a thread. The
+//! thread is kept pending while a sleep or an interval is active, so it should
+//! be a thread with the lowest priority.
+//!
+//! `Watchpoints` uses the DWT comparators to trap accesses to variables or
+//! address ranges at run-time, without a debug probe attached. It is available
+//! only on ARMv7-M cores, because ARMv8-M changed the comparator encoding.
+
+#![cfg_attr(feature = "std", allow(unreachable_code))]
+
+#[cfg(not(armv8m))]
+mod watchpoint;
+
+#[cfg(not(armv8m))]
+pub use self::watchpoint::{
+    WatchAccess, Watchpoint, WatchpointError, WatchpointHit, WatchpointHits, Watchpoints,
+};
+
+use crate::{
+    drv::timer::{Timer, TimerInterval, TimerOverflow, TimerSleep, TimerStop},
+    fib::{self, Fiber},
+    map::{
+        periph::dwt::DwtPeriph,
+        reg::{dwt, scb},
+    },
+    processor,
+    reg::prelude::*,
+    thr::prelude::*,
+};
+use alloc::sync::Arc;
+use core::{
+    num::NonZeroUsize,
+    ops::{Add, AddAssign, Sub, SubAssign},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+/// A measurement of the DWT cycle counter.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Instant(u32);
+
+/// DWT cycle counter driver.
+pub struct Dwt {
+    periph: DwtDiverged,
+}
+
+/// DWT cycle counter diverged peripheral.
+#[allow(missing_docs)]
+pub struct DwtDiverged {
+    pub scb_demcr_trcena: scb::demcr::Trcena<Srt>,
+    pub dwt_ctrl_cyccntena: dwt::ctrl::Cyccntena<Srt>,
+    pub dwt_cyccnt: dwt::Cyccnt<Crt>,
+}
+
+/// [`Timer`] implementation polling the DWT cycle counter from a thread.
+pub struct DwtTimer<I: IntToken> {
+    dwt: Dwt,
+    int: I,
+    running: Arc<AtomicBool>,
+    restarted: Arc<AtomicBool>,
+}
+
+impl Instant {
+    /// Creates a new [`Instant`] from the raw counter value.
+    #[inline]
+    pub const fn from_cycles(cycles: u32) -> Self {
+        Self(cycles)
+    }
+
+    /// Returns the raw counter value.
+    #[inline]
+    pub const fn cycles(self) -> u32 {
+        self.0
+    }
+
+    /// Returns the number of cycles elapsed from `earlier` to `self`.
+    #[inline]
+    pub fn duration_since(self, earlier: Self) -> u32 {
+        self.0.wrapping_sub(earlier.0)
+    }
+
+    /// Returns `true` if `self` is later than `other`. The instants must be
+    /// less than 2<sup>31</sup> cycles apart.
+    #[inline]
+    pub fn is_after(self, other: Self) -> bool {
+        let diff = self.0.wrapping_sub(other.0);
+        diff != 0 && diff < 1 << 31
+    }
+}
+
+impl Add<u32> for Instant {
+    type Output = Self;
+
+    #[inline]
+    fn add(self, rhs: u32) -> Self {
+        Self(self.0.wrapping_add(rhs))
+    }
+}
+
+impl AddAssign<u32> for Instant {
+    #[inline]
+    fn add_assign(&mut self, rhs: u32) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub<u32> for Instant {
+    type Output = Self;
+
+    #[inline]
+    fn sub(self, rhs: u32) -> Self {
+        Self(self.0.wrapping_sub(rhs))
+    }
+}
+
+impl SubAssign<u32> for Instant {
+    #[inline]
+    fn sub_assign(&mut self, rhs: u32) {
+        *self = *self - rhs;
+    }
+}
+
+impl Sub for Instant {
+    type Output = u32;
+
+    #[inline]
+    fn sub(self, rhs: Self) -> u32 {
+        self.duration_since(rhs)
+    }
+}
+
+impl Dwt {
+    /// Creates a new driver from the peripheral, and enables the cycle
+    /// counter.
+    #[inline]
+    pub fn new(periph: DwtPeriph) -> Self {
+        let periph = DwtDiverged {
+            scb_demcr_trcena: periph.scb_demcr_trcena,
+            dwt_ctrl_cyccntena: periph.dwt_ctrl_cyccntena,
+            dwt_cyccnt: periph.dwt_cyccnt.into_copy(),
+        };
+        periph.scb_demcr_trcena.set_bit();
+        periph.dwt_ctrl_cyccntena.set_bit();
+        Self { periph }
+    }
+
+    /// Creates a new driver from the diverged peripheral.
+    ///
+    /// # Safety
+    ///
+    /// Some of the `Crt` register tokens can be still in use.
+    #[inline]
+    pub unsafe fn from_diverged(periph: DwtDiverged) -> Self {
+        Self { periph }
+    }
+
+    /// Releases the peripheral.
+    #[inline]
+    pub fn free(self) -> DwtDiverged {
+        self.periph
+    }
+
+    /// Converts the driver into a [`Timer`] running on the `int` thread.
+    #[inline]
+    pub fn into_timer<I: IntToken>(self, int: I) -> DwtTimer<I> {
+        DwtTimer {
+            dwt: self,
+            int,
+            running: Arc::new(AtomicBool::new(false)),
+            restarted: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Returns the current value of the cycle counter.
+    #[inline]
+    pub fn now(&self) -> Instant {
+        Instant(self.periph.dwt_cyccnt.load().cyccnt())
+    }
+
+    /// Returns the number of cycles elapsed since `earlier`.
+    #[inline]
+    pub fn elapsed(&self, earlier: Instant) -> u32 {
+        self.now() - earlier
+    }
+
+    /// Runs `f` and returns its result together with the number of cycles it
+    /// took.
+    #[inline]
+    pub fn measure<R>(&self, f: impl FnOnce() -> R) -> (R, u32) {
+        let start = self.now();
+        let result = f();
+        (result, self.elapsed(start))
+    }
+
+    /// Busy-waits for at least `cycles` processor cycles.
+    ///
+    /// Unlike [`processor::spin`](crate::processor::spin), the delay doesn't
+    /// depend on the code alignment, the flash wait states, and interrupts.
+    #[inline]
+    pub fn delay_cycles(&self, cycles: u32) {
+        #[cfg(feature = "std")]
+        return;
+        let start = self.now();
+        while self.elapsed(start) < cycles {}
+    }
+
+    /// Busy-waits until the cycle counter reaches `deadline`. The deadline must
+    /// be less than 2<sup>31</sup> cycles ahead.
+    #[inline]
+    pub fn delay_until(&self, deadline: Instant) {
+        #[cfg(feature = "std")]
+        return;
+        while deadline.is_after(self.now()) {}
+    }
+
+    /// Generates an ITM synchronization packet.
+    ///
+    /// This method sets the cycle counter to its maximum value, so instants
+    /// taken before the call can't be compared with instants taken after, and
+    /// the running [`DwtTimer`] sleeps and intervals are disturbed.
+    #[inline]
+    pub fn sync_itm(&self) {
+        crate::swo::sync_with(&self.periph.dwt_cyccnt);
+    }
+}
+
+/// Busy-waits for at least `cycles` processor cycles without the driver.
+///
+/// The cycle counter is only read, so the delay doesn't disturb the [`Dwt`]
+/// driver. Falls back to [`processor::spin`] if the counter is disabled.
+pub(crate) fn delay_cycles(cycles: u32) {
+    #[cfg(feature = "std")]
+    return;
+    let ctrl = unsafe { dwt::Ctrl::<Urt>::take() };
+    if ctrl.load().cyccntena() {
+        let cyccnt = unsafe { dwt::Cyccnt::<Urt>::take() };
+        let start = cyccnt.load().cyccnt();
+        while cyccnt.load().cyccnt().wrapping_sub(start) < cycles {}
+    } else {
+        processor::spin(cycles);
+    }
+}
+
+impl<I: IntToken> Timer for DwtTimer<I> {
+    type Stop = Self;
+
+    fn sleep(&mut self, duration: u32) -> TimerSleep<'_, Self> {
+        let running = self.start();
+        let cyccnt = self.dwt.periph.dwt_cyccnt;
+        let int = self.int;
+        let start = self.dwt.now().0;
+        let fut = Box::pin(self.int.add_future(fib::new_fn(move || {
+            if !running.load(Ordering::Relaxed)
+                || cyccnt.load().cyccnt().wrapping_sub(start) >= duration
+            {
+                fib::Complete(())
+            } else {
+                int.trigger();
+                fib::Yielded(())
+            }
+        })));
+        self.int.trigger();
+        TimerSleep::new(self, fut)
+    }
+
+    fn interval(
+        &mut self,
+        duration: u32,
+    ) -> TimerInterval<'_, Self, Result<NonZeroUsize, TimerOverflow>> {
+        let fib = self.interval_fib(duration);
+        let stream = Box::pin(self.int.add_pulse_try_stream(|| Err(TimerOverflow), fib));
+        self.int.trigger();
+        TimerInterval::new(self, stream)
+    }
+
+    fn interval_skip(&mut self, duration: u32) -> TimerInterval<'_, Self, NonZeroUsize> {
+        let fib = self.interval_fib(duration);
+        let stream = Box::pin(self.int.add_saturating_pulse_stream(fib));
+        self.int.trigger();
+        TimerInterval::new(self, stream)
+    }
+}
+
+impl<I: IntToken> TimerStop for DwtTimer<I> {
+    fn stop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+    }
+
+    fn restart(&mut self) {
+        self.restarted.store(true, Ordering::Relaxed);
+    }
+}
+
+impl<I: IntToken> DwtTimer<I> {
+    /// Stops the timer and releases the driver.
+    #[inline]
+    pub fn free(mut self) -> Dwt {
+        self.stop();
+        self.dwt
+    }
+
+    /// Returns the underlying driver.
+    #[inline]
+    pub fn dwt(&self) -> &Dwt {
+        &self.dwt
+    }
+
+    fn start(&mut self) -> Arc<AtomicBool> {
+        self.stop();
+        self.running = Arc::new(AtomicBool::new(true));
+        Arc::clone(&self.running)
+    }
+
+    fn interval_fib<T>(
+        &mut self,
+        duration: u32,
+    ) -> impl Fiber<Input = (), Yield = Option<usize>, Return = T> {
+        assert!(duration > 0, "interval duration must be non-zero");
+        let running = self.start();
+        self.restarted = Arc::new(AtomicBool::new(false));
+        let restarted = Arc::clone(&self.restarted);
+        let cyccnt = self.dwt.periph.dwt_cyccnt;
+        let int = self.int;
+        let mut last = self.dwt.now().0;
+        fib::new_fn(move || {
+            if !running.load(Ordering::Relaxed) {
+                return fib::Yielded(None);
+            }
+            int.trigger();
+            if restarted.swap(false, Ordering::Relaxed) {
+                last = cyccnt.load().cyccnt();
+            }
+            let count = cyccnt.load().cyccnt().wrapping_sub(last) / duration;
+            if count == 0 {
+                return fib::Yielded(None);
+            }
+            last = last.wrapping_add(count * duration);
+            fib::Yielded(Some(count as usize))
+        })
+    }
+}
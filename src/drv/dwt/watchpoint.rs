@@ -0,0 +1,329 @@
+use crate::{
+    fib::{self, FiberState},
+    map::{
+        periph::dwt::DwtWatchPeriph,
+        reg::{dwt, scb},
+    },
+    reg::prelude::*,
+    thr::prelude::*,
+};
+use alloc::sync::Arc;
+use core::{
+    fmt,
+    mem::size_of,
+    pin::Pin,
+    sync::atomic::{AtomicU8, AtomicUsize, Ordering},
+    task::{Context, Poll},
+};
+use futures::{stream::Stream, task::AtomicWaker};
+
+/// Maximum number of comparators supported by the driver.
+const COMPARATORS: u8 = 4;
+
+/// Dispatches to the register tokens of the comparator `$comparator`.
+macro_rules! comparator {
+    ($regs:expr, $comparator:expr, | $comp:ident, $mask:ident, $function:ident | $body:expr) => {
+        match $comparator {
+            0 => {
+                let ($comp, $mask, $function) =
+                    (&$regs.dwt_comp0, &$regs.dwt_mask0, &$regs.dwt_function0);
+                $body
+            }
+            1 => {
+                let ($comp, $mask, $function) =
+                    (&$regs.dwt_comp1, &$regs.dwt_mask1, &$regs.dwt_function1);
+                $body
+            }
+            2 => {
+                let ($comp, $mask, $function) =
+                    (&$regs.dwt_comp2, &$regs.dwt_mask2, &$regs.dwt_function2);
+                $body
+            }
+            3 => {
+                let ($comp, $mask, $function) =
+                    (&$regs.dwt_comp3, &$regs.dwt_mask3, &$regs.dwt_function3);
+                $body
+            }
+            _ => unreachable!(),
+        }
+    };
+}
+
+/// DWT comparators driver.
+///
+/// Each armed comparator watches an address range, and generates the
+/// DebugMonitor exception on a matching access. The exception is handled by a
+/// fiber attached to the `debug` thread, which reports the hits through the
+/// [`Watchpoints::hits`] stream.
+///
+/// The DebugMonitor exception is not generated while halting debug is enabled,
+/// i.e. when a debug probe is attached. The driver uses the ARMv7-M comparator
+/// encoding, and is not available on ARMv8-M cores.
+pub struct Watchpoints {
+    shared: Arc<Shared>,
+}
+
+/// An armed comparator. The comparator is disarmed when dropped.
+pub struct Watchpoint {
+    shared: Arc<Shared>,
+    comparator: u8,
+}
+
+/// Stream created from [`Watchpoints::hits`].
+pub struct WatchpointHits {
+    shared: Arc<Shared>,
+}
+
+/// Watchpoint hit report.
+#[derive(Clone, Copy, Debug)]
+pub struct WatchpointHit {
+    /// Comparator number.
+    pub comparator: u8,
+    /// Start address of the watched range.
+    pub address: usize,
+}
+
+/// Type of memory access to watch.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WatchAccess {
+    /// Watch read accesses.
+    Read,
+    /// Watch write accesses.
+    Write,
+    /// Watch read and write accesses.
+    ReadWrite,
+}
+
+/// Error returned from [`Watchpoints::watch`].
+#[derive(Debug)]
+pub enum WatchpointError {
+    /// All comparators are in use.
+    NoComparator,
+    /// The size of the range is not a power of two, or the address is not
+    /// aligned to the size.
+    Unaligned,
+    /// The size of the range exceeds the maximum mask size supported by the
+    /// comparator.
+    Unsupported,
+}
+
+struct Shared {
+    regs: Regs,
+    count: u8,
+    used: AtomicU8,
+    hits: AtomicU8,
+    addresses: [AtomicUsize; COMPARATORS as usize],
+    waker: AtomicWaker,
+}
+
+struct Regs {
+    scb_dfsr: scb::Dfsr<Crt>,
+    dwt_comp0: dwt::Comp0<Crt>,
+    dwt_mask0: dwt::Mask0<Crt>,
+    dwt_function0: dwt::Function0<Crt>,
+    dwt_comp1: dwt::Comp1<Crt>,
+    dwt_mask1: dwt::Mask1<Crt>,
+    dwt_function1: dwt::Function1<Crt>,
+    dwt_comp2: dwt::Comp2<Crt>,
+    dwt_mask2: dwt::Mask2<Crt>,
+    dwt_function2: dwt::Function2<Crt>,
+    dwt_comp3: dwt::Comp3<Crt>,
+    dwt_mask3: dwt::Mask3<Crt>,
+    dwt_function3: dwt::Function3<Crt>,
+}
+
+impl Watchpoints {
+    /// Creates a new driver from the peripheral, and attaches the hit handler
+    /// to the `debug` thread.
+    pub fn new<T: ThrToken>(periph: DwtWatchPeriph, debug: T) -> Self {
+        let count = (periph.dwt_ctrl_numcomp.read_bits() as u8).min(COMPARATORS);
+        let regs = Regs {
+            scb_dfsr: periph.scb_dfsr.into_copy(),
+            dwt_comp0: periph.dwt_comp0.into_copy(),
+            dwt_mask0: periph.dwt_mask0.into_copy(),
+            dwt_function0: periph.dwt_function0.into_copy(),
+            dwt_comp1: periph.dwt_comp1.into_copy(),
+            dwt_mask1: periph.dwt_mask1.into_copy(),
+            dwt_function1: periph.dwt_function1.into_copy(),
+            dwt_comp2: periph.dwt_comp2.into_copy(),
+            dwt_mask2: periph.dwt_mask2.into_copy(),
+            dwt_function2: periph.dwt_function2.into_copy(),
+            dwt_comp3: periph.dwt_comp3.into_copy(),
+            dwt_mask3: periph.dwt_mask3.into_copy(),
+            dwt_function3: periph.dwt_function3.into_copy(),
+        };
+        for comparator in 0..count {
+            comparator!(regs, comparator, |_comp, _mask, function| function.reset());
+        }
+        let shared = Arc::new(Shared {
+            regs,
+            count,
+            used: AtomicU8::new(0),
+            hits: AtomicU8::new(0),
+            addresses: Default::default(),
+            waker: AtomicWaker::new(),
+        });
+        let handler = Arc::clone(&shared);
+        debug.add_fn(move || -> FiberState<(), ()> {
+            handler.handle();
+            fib::Yielded(())
+        });
+        periph.scb_demcr_mon_en.set_bit();
+        Self { shared }
+    }
+
+    /// Returns the number of available comparators.
+    #[inline]
+    pub fn count(&self) -> u8 {
+        self.shared.count
+    }
+
+    /// Arms a free comparator to watch `access` to the range of `size` bytes
+    /// starting at `address`.
+    ///
+    /// `size` must be a power of two, and `address` must be aligned to `size`.
+    pub fn watch(
+        &self,
+        address: usize,
+        size: usize,
+        access: WatchAccess,
+    ) -> Result<Watchpoint, WatchpointError> {
+        if !size.is_power_of_two() || address % size != 0 {
+            return Err(WatchpointError::Unaligned);
+        }
+        let comparator = self.shared.acquire().ok_or(WatchpointError::NoComparator)?;
+        let watchpoint = Watchpoint { shared: Arc::clone(&self.shared), comparator };
+        let mask = size.trailing_zeros();
+        let supported = comparator!(self.shared.regs, comparator, |comp, mask_reg, _function| {
+            comp.store(|r| r.write_comp(address as u32));
+            mask_reg.store(|r| r.write_mask(mask));
+            mask_reg.load().mask() == mask
+        });
+        if !supported {
+            return Err(WatchpointError::Unsupported);
+        }
+        self.shared.addresses[usize::from(comparator)].store(address, Ordering::Relaxed);
+        let encoding = match access {
+            WatchAccess::Read => 0b0101,
+            WatchAccess::Write => 0b0110,
+            WatchAccess::ReadWrite => 0b0111,
+        };
+        comparator!(self.shared.regs, comparator, |_comp, _mask, function| {
+            function.store(|r| r.write_function(encoding));
+        });
+        Ok(watchpoint)
+    }
+
+    /// Arms a free comparator to watch `access` to `var`.
+    ///
+    /// The watched range is the size of `T` rounded up to a power of two, so it
+    /// can cover adjacent memory.
+    pub fn watch_var<T>(
+        &self,
+        var: &T,
+        access: WatchAccess,
+    ) -> Result<Watchpoint, WatchpointError> {
+        self.watch(var as *const T as usize, size_of::<T>().next_power_of_two(), access)
+    }
+
+    /// Returns a stream of watchpoint hits.
+    ///
+    /// Only one hits stream should be polled at a time.
+    #[inline]
+    pub fn hits(&self) -> WatchpointHits {
+        WatchpointHits { shared: Arc::clone(&self.shared) }
+    }
+}
+
+impl Watchpoint {
+    /// Returns the comparator number.
+    #[inline]
+    pub fn comparator(&self) -> u8 {
+        self.comparator
+    }
+
+    /// Returns the start address of the watched range.
+    #[inline]
+    pub fn address(&self) -> usize {
+        self.shared.addresses[usize::from(self.comparator)].load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for Watchpoint {
+    fn drop(&mut self) {
+        comparator!(self.shared.regs, self.comparator, |_comp, _mask, function| function.reset());
+        self.shared.used.fetch_and(!(1 << self.comparator), Ordering::AcqRel);
+    }
+}
+
+impl Stream for WatchpointHits {
+    type Item = WatchpointHit;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<WatchpointHit>> {
+        let mut hits = self.shared.hits.load(Ordering::Acquire);
+        if hits == 0 {
+            self.shared.waker.register(cx.waker());
+            hits = self.shared.hits.load(Ordering::Acquire);
+            if hits == 0 {
+                return Poll::Pending;
+            }
+        }
+        let comparator = hits.trailing_zeros() as u8;
+        self.shared.hits.fetch_and(!(1 << comparator), Ordering::AcqRel);
+        let address = self.shared.addresses[usize::from(comparator)].load(Ordering::Relaxed);
+        Poll::Ready(Some(WatchpointHit { comparator, address }))
+    }
+}
+
+impl Shared {
+    fn acquire(&self) -> Option<u8> {
+        let mut used = self.used.load(Ordering::Acquire);
+        loop {
+            let comparator = (!used).trailing_zeros() as u8;
+            if comparator >= self.count {
+                break None;
+            }
+            match self.used.compare_exchange_weak(
+                used,
+                used | 1 << comparator,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => break Some(comparator),
+                Err(actual) => used = actual,
+            }
+        }
+    }
+
+    /// Runs on each DebugMonitor exception.
+    fn handle(&self) {
+        if !self.regs.scb_dfsr.load().dwttrap() {
+            return;
+        }
+        // The DFSR bits are write-one-to-clear.
+        self.regs.scb_dfsr.store(|r| r.set_dwttrap());
+        let mut hits = 0;
+        for comparator in 0..self.count {
+            let matched = comparator!(self.regs, comparator, |_comp, _mask, function| {
+                function.load().matched()
+            });
+            if matched {
+                hits |= 1 << comparator;
+            }
+        }
+        if hits != 0 {
+            self.hits.fetch_or(hits, Ordering::AcqRel);
+            self.waker.wake();
+        }
+    }
+}
+
+impl fmt::Display for WatchpointError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoComparator => write!(f, "All DWT comparators are in use."),
+            Self::Unaligned => write!(f, "Watched range is not naturally aligned."),
+            Self::Unsupported => write!(f, "Watched range is too large."),
+        }
+    }
+}
@@ -6,5 +6,13 @@
 //! **NOTE** A device-specific Drone crate may re-export this module with its
 //! own additions, in which case it should be used instead.
 
+pub mod dwt;
+pub mod mono;
+#[cfg(all(feature = "memory-protection-unit", not(armv8m)))]
+pub mod mpu;
+#[cfg(feature = "security-extension")]
+pub mod sau;
 pub mod sys_tick;
 pub mod timer;
+
+mod waiter;
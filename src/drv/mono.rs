@@ -0,0 +1,491 @@
+//! Tickless monotonic time base.
+//!
+//! [`Mono`] extends the 24-bit SysTick counter with a software overflow
+//! counter, providing a 64-bit monotonic clock measured in SysTick ticks. The
+//! tick rate is the SysTick clock source rate: either the processor clock or
+//! the external reference clock, depending on `stk::Ctrl::CLKSOURCE`.
+//!
+//! Any number of sleeps and deadlines share the single SysTick timer. The
+//! driver keeps them in a queue and reprograms the reload value to the earliest
+//! one. When nothing is waiting, the counter wraps every 2<sup>24</sup> ticks,
+//! which is only needed to keep track of time.
+//!
+//! [`Mono`] handles are cheap to clone, and each clone can be moved to a
+//! different thread. Each reprogramming of the counter may lose a few ticks,
+//! so the clock is not suitable for long-term timekeeping.
+
+#![cfg_attr(feature = "std", allow(unreachable_code, unused_variables))]
+
+use crate::{
+    drv::{
+        sys_tick::SysTick,
+        timer::{Elapsed, Timer, TimerInterval, TimerOverflow, TimerSleep, TimerStop},
+        waiter::{self, Waiter, WaiterList},
+    },
+    fib::{self, FiberState},
+    reg::prelude::*,
+    thr::{prelude::*, priority::critical_section},
+};
+use alloc::sync::Arc;
+use core::{
+    cell::UnsafeCell,
+    convert::TryFrom,
+    future::Future,
+    num::NonZeroUsize,
+    ops::{Add, AddAssign, Sub, SubAssign},
+    pin::Pin,
+    task::{Context, Poll},
+};
+use futures::{ready, stream::Stream, StreamExt};
+
+/// Maximum value of the SysTick reload register.
+const MAX_RELOAD: u32 = 0x00FF_FFFF;
+
+/// Minimum number of ticks between the counter reprogramming and the next
+/// counter wrap. The counter must not wrap twice before the SysTick handler is
+/// run, otherwise one of the wraps would be lost.
+const MIN_RELOAD: u32 = 0x3FF;
+
+/// A measurement of a monotonic clock, such as [`Mono`] or
+/// [`TimerQueue`](crate::drv::timer::TimerQueue).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Instant(u64);
+
+/// A span of time in ticks of a monotonic clock.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Duration(u64);
+
+/// Tickless monotonic clock driver.
+pub struct Mono<I: IntToken> {
+    shared: Arc<Shared<I>>,
+}
+
+/// Future created from [`Mono::sleep`] or [`Mono::sleep_until`].
+pub struct MonoSleep<I: IntToken> {
+    shared: Arc<Shared<I>>,
+    waiter: Arc<Waiter>,
+    registered: bool,
+}
+
+/// Future created from [`Mono::timeout`] or [`Mono::deadline`].
+pub struct MonoTimeout<I: IntToken, F: Future> {
+    future: F,
+    sleep: MonoSleep<I>,
+}
+
+/// Stream created from [`Mono::interval`].
+pub struct MonoInterval<I: IntToken> {
+    sleep: MonoSleep<I>,
+    deadline: Instant,
+    period: Duration,
+}
+
+struct Shared<I: IntToken> {
+    timer: SysTick<I>,
+    state: UnsafeCell<State>,
+    pending: WaiterList,
+}
+
+struct State {
+    /// Clock value at the moment the counter was last zero.
+    base: u64,
+    /// Current value of the reload register.
+    reload: u32,
+}
+
+unsafe impl<I: IntToken> Sync for Shared<I> {}
+
+impl Instant {
+    /// Creates a new [`Instant`] from the number of ticks since the clock
+    /// start.
+    #[inline]
+    pub const fn from_ticks(ticks: u64) -> Self {
+        Self(ticks)
+    }
+
+    /// Returns the number of ticks since the clock start.
+    #[inline]
+    pub const fn ticks(self) -> u64 {
+        self.0
+    }
+
+    /// Returns the amount of time elapsed from `earlier` to `self`, or `None`
+    /// if `earlier` is later than `self`.
+    #[inline]
+    pub fn checked_duration_since(self, earlier: Self) -> Option<Duration> {
+        self.0.checked_sub(earlier.0).map(Duration)
+    }
+
+    /// Returns the amount of time elapsed from `earlier` to `self`, or zero
+    /// duration if `earlier` is later than `self`.
+    #[inline]
+    pub fn saturating_duration_since(self, earlier: Self) -> Duration {
+        Duration(self.0.saturating_sub(earlier.0))
+    }
+
+    /// Returns `Some(t)` where `t` is `self + duration` if `t` can be
+    /// represented, `None` otherwise.
+    #[inline]
+    pub fn checked_add(self, duration: Duration) -> Option<Self> {
+        self.0.checked_add(duration.0).map(Self)
+    }
+
+    /// Returns `Some(t)` where `t` is `self - duration` if `t` can be
+    /// represented, `None` otherwise.
+    #[inline]
+    pub fn checked_sub(self, duration: Duration) -> Option<Self> {
+        self.0.checked_sub(duration.0).map(Self)
+    }
+}
+
+impl Add<Duration> for Instant {
+    type Output = Self;
+
+    #[inline]
+    fn add(self, rhs: Duration) -> Self {
+        self.checked_add(rhs).expect("overflow when adding duration to instant")
+    }
+}
+
+impl AddAssign<Duration> for Instant {
+    #[inline]
+    fn add_assign(&mut self, rhs: Duration) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub<Duration> for Instant {
+    type Output = Self;
+
+    #[inline]
+    fn sub(self, rhs: Duration) -> Self {
+        self.checked_sub(rhs).expect("overflow when subtracting duration from instant")
+    }
+}
+
+impl SubAssign<Duration> for Instant {
+    #[inline]
+    fn sub_assign(&mut self, rhs: Duration) {
+        *self = *self - rhs;
+    }
+}
+
+impl Sub for Instant {
+    type Output = Duration;
+
+    /// Returns the amount of time elapsed from `rhs` to `self`, or zero
+    /// duration if `rhs` is later than `self`.
+    #[inline]
+    fn sub(self, rhs: Self) -> Duration {
+        self.saturating_duration_since(rhs)
+    }
+}
+
+impl Duration {
+    /// A zero duration.
+    pub const ZERO: Self = Self(0);
+
+    /// Creates a new [`Duration`] from the number of ticks.
+    #[inline]
+    pub const fn from_ticks(ticks: u64) -> Self {
+        Self(ticks)
+    }
+
+    /// Returns the number of ticks.
+    #[inline]
+    pub const fn ticks(self) -> u64 {
+        self.0
+    }
+
+    /// Checked duration addition. Returns `None` if overflow occurred.
+    #[inline]
+    pub fn checked_add(self, rhs: Self) -> Option<Self> {
+        self.0.checked_add(rhs.0).map(Self)
+    }
+
+    /// Checked duration subtraction. Returns `None` if the result would be
+    /// negative.
+    #[inline]
+    pub fn checked_sub(self, rhs: Self) -> Option<Self> {
+        self.0.checked_sub(rhs.0).map(Self)
+    }
+
+    /// Checked duration multiplication. Returns `None` if overflow occurred.
+    #[inline]
+    pub fn checked_mul(self, rhs: u64) -> Option<Self> {
+        self.0.checked_mul(rhs).map(Self)
+    }
+
+    /// Saturating duration subtraction. Returns zero duration if the result
+    /// would be negative.
+    #[inline]
+    pub fn saturating_sub(self, rhs: Self) -> Self {
+        Self(self.0.saturating_sub(rhs.0))
+    }
+}
+
+impl Add for Duration {
+    type Output = Self;
+
+    #[inline]
+    fn add(self, rhs: Self) -> Self {
+        self.checked_add(rhs).expect("overflow when adding durations")
+    }
+}
+
+impl AddAssign for Duration {
+    #[inline]
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub for Duration {
+    type Output = Self;
+
+    #[inline]
+    fn sub(self, rhs: Self) -> Self {
+        self.checked_sub(rhs).expect("overflow when subtracting durations")
+    }
+}
+
+impl SubAssign for Duration {
+    #[inline]
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl<I: IntToken> Mono<I> {
+    /// Creates a new clock from the SysTick driver and starts it.
+    ///
+    /// The driver takes over the SysTick counter and attaches a never-ending
+    /// fiber to the SysTick thread.
+    pub fn new(timer: SysTick<I>) -> Self {
+        let int = timer.int();
+        let shared = Arc::new(Shared {
+            timer,
+            state: UnsafeCell::new(State { base: 0, reload: MAX_RELOAD }),
+            pending: WaiterList::new(),
+        });
+        let handler = Arc::clone(&shared);
+        let mut queue = Vec::new();
+        int.add_fn(move || -> FiberState<(), ()> {
+            handler.handle(&mut queue);
+            fib::Yielded(())
+        });
+        shared.timer.load().store(|r| r.write_reload(MAX_RELOAD));
+        shared.timer.val().store(|r| r.write_current(0));
+        let ctrl = shared.timer.ctrl();
+        let mut ctrl_val = ctrl.load();
+        ctrl.store_val(ctrl_val.set_enable().set_tickint().val());
+        Self { shared }
+    }
+
+    /// Returns the current value of the clock.
+    pub fn now(&self) -> Instant {
+        Instant(critical_section(0, || unsafe { self.shared.now() }))
+    }
+
+    /// Returns a future that resolves when `duration` time is elapsed.
+    pub fn sleep(&self, duration: Duration) -> MonoSleep<I> {
+        self.sleep_until(self.now() + duration)
+    }
+
+    /// Returns a future that resolves when the clock reaches `deadline`.
+    pub fn sleep_until(&self, deadline: Instant) -> MonoSleep<I> {
+        MonoSleep {
+            shared: Arc::clone(&self.shared),
+            waiter: Waiter::new(deadline.0),
+            registered: false,
+        }
+    }
+
+    /// Returns a future that resolves to the output of `future`, or to
+    /// [`Elapsed`] error if `future` doesn't complete within `duration` time.
+    ///
+    /// `future` is dropped together with the returned future.
+    pub fn timeout<F: Future>(&self, duration: Duration, future: F) -> MonoTimeout<I, F> {
+        MonoTimeout { future, sleep: self.sleep(duration) }
+    }
+
+    /// Returns a future that resolves to the output of `future`, or to
+    /// [`Elapsed`] error if `future` doesn't complete before the clock reaches
+    /// `deadline`.
+    ///
+    /// `future` is dropped together with the returned future.
+    pub fn deadline<F: Future>(&self, deadline: Instant, future: F) -> MonoTimeout<I, F> {
+        MonoTimeout { future, sleep: self.sleep_until(deadline) }
+    }
+
+    /// Returns a stream of pulses that are generated on each `period` interval,
+    /// starting from now.
+    ///
+    /// Each item is the number of periods elapsed since the previous item. It
+    /// is more than one if the stream wasn't polled in time.
+    ///
+    /// # Panics
+    ///
+    /// If `period` is zero.
+    pub fn interval(&self, period: Duration) -> MonoInterval<I> {
+        assert!(period > Duration::ZERO, "interval period must be non-zero");
+        let deadline = self.now() + period;
+        MonoInterval { sleep: self.sleep_until(deadline), deadline, period }
+    }
+}
+
+impl<I: IntToken> Clone for Mono<I> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self { shared: Arc::clone(&self.shared) }
+    }
+}
+
+impl<I: IntToken> Timer for Mono<I> {
+    type Stop = Self;
+
+    fn sleep(&mut self, duration: u32) -> TimerSleep<'_, Self> {
+        let fut = Box::pin(Mono::sleep(self, Duration(duration.into())));
+        TimerSleep::new(self, fut)
+    }
+
+    fn interval(
+        &mut self,
+        duration: u32,
+    ) -> TimerInterval<'_, Self, Result<NonZeroUsize, TimerOverflow>> {
+        let stream = Box::pin(Mono::interval(self, Duration(duration.into())).map(Ok));
+        TimerInterval::new(self, stream)
+    }
+
+    fn interval_skip(&mut self, duration: u32) -> TimerInterval<'_, Self, NonZeroUsize> {
+        let stream = Box::pin(Mono::interval(self, Duration(duration.into())));
+        TimerInterval::new(self, stream)
+    }
+}
+
+impl<I: IntToken> TimerStop for Mono<I> {
+    /// Does nothing, the shared clock is never stopped. Pending sleeps are
+    /// cancelled when dropped.
+    fn stop(&mut self) {}
+}
+
+impl<I: IntToken> Future for MonoSleep<I> {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.registered {
+            return self.waiter.poll(cx);
+        }
+        if critical_section(0, || unsafe { self.shared.now() }) >= self.waiter.deadline() {
+            return Poll::Ready(());
+        }
+        let poll = self.waiter.poll(cx);
+        self.registered = true;
+        self.shared.pending.push(Arc::clone(&self.waiter));
+        self.shared.timer.set_pending();
+        poll
+    }
+}
+
+impl<I: IntToken> Drop for MonoSleep<I> {
+    fn drop(&mut self) {
+        self.waiter.cancel();
+    }
+}
+
+impl<I: IntToken, F: Future> Future for MonoTimeout<I, F> {
+    type Output = Result<F::Output, Elapsed>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = unsafe { self.get_unchecked_mut() };
+        if let Poll::Ready(output) = unsafe { Pin::new_unchecked(&mut this.future) }.poll(cx) {
+            return Poll::Ready(Ok(output));
+        }
+        Pin::new(&mut this.sleep).poll(cx).map(|()| Err(Elapsed))
+    }
+}
+
+impl<I: IntToken> Stream for MonoInterval<I> {
+    type Item = NonZeroUsize;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<NonZeroUsize>> {
+        ready!(Pin::new(&mut self.sleep).poll(cx));
+        let now = Instant(critical_section(0, || unsafe { self.sleep.shared.now() }));
+        let periods = (now - self.deadline).ticks() / self.period.ticks() + 1;
+        let advance = self.period.checked_mul(periods).expect("interval overflow");
+        self.deadline += advance;
+        let sleep = MonoSleep {
+            shared: Arc::clone(&self.sleep.shared),
+            waiter: Waiter::new(self.deadline.0),
+            registered: false,
+        };
+        self.sleep = sleep;
+        let periods = usize::try_from(periods).unwrap_or(usize::MAX);
+        Poll::Ready(Some(unsafe { NonZeroUsize::new_unchecked(periods) }))
+    }
+}
+
+impl<I: IntToken> Shared<I> {
+    /// Runs on each SysTick exception or when a new waiter is registered.
+    fn handle(&self, queue: &mut Vec<Arc<Waiter>>) {
+        loop {
+            self.pending.take(|waiter| waiter::enqueue(queue, waiter));
+            let now = critical_section(0, || unsafe { self.now() });
+            let deadline = waiter::expire(queue, now).unwrap_or(u64::MAX);
+            if critical_section(0, || unsafe { self.schedule(deadline) }) {
+                break;
+            }
+        }
+    }
+
+    /// Returns the current clock value, accounting a counter wrap.
+    ///
+    /// # Safety
+    ///
+    /// Must be called within a critical section.
+    unsafe fn now(&self) -> u64 {
+        let state = unsafe { &mut *self.state.get() };
+        let before = self.timer.val().load().current();
+        let wrapped = self.timer.ctrl().load().countflag();
+        let after = self.timer.val().load().current();
+        if wrapped {
+            state.base += u64::from(state.reload) + 1;
+            state.base + offset(after, state.reload)
+        } else {
+            // If the counter wrapped after reading the flag, `after` belongs to
+            // the next period, and `before` should be used instead.
+            state.base + offset(before, state.reload).max(offset(after, state.reload))
+        }
+    }
+
+    /// Reprograms the counter to wrap at `deadline`, unless the next wrap
+    /// happens earlier. Returns `false` if `deadline` has already passed.
+    ///
+    /// # Safety
+    ///
+    /// Must be called within a critical section.
+    unsafe fn schedule(&self, deadline: u64) -> bool {
+        let now = unsafe { self.now() };
+        if deadline <= now {
+            return false;
+        }
+        let state = unsafe { &mut *self.state.get() };
+        let next_wrap = state.base + u64::from(state.reload) + 1;
+        if deadline < next_wrap || (state.reload != MAX_RELOAD && deadline > next_wrap) {
+            let reload = (deadline - now)
+                .max(u64::from(MIN_RELOAD) + 1)
+                .min(u64::from(MAX_RELOAD) + 1) as u32
+                - 1;
+            self.timer.load().store(|r| r.write_reload(reload));
+            self.timer.val().store(|r| r.write_current(0));
+            state.base = now;
+            state.reload = reload;
+        }
+        true
+    }
+}
+
+/// Returns the number of ticks since the counter was last zero.
+fn offset(current: u32, reload: u32) -> u64 {
+    if current == 0 { 0 } else { u64::from(reload) + 1 - u64::from(current) }
+}
@@ -0,0 +1,340 @@
+//! Memory Protection Unit.
+//!
+//! This module supports the ARMv7-M MPU (PMSAv7) only.
+//!
+//! Regions are described with [`MpuRegion`], a `const` builder, so the region
+//! table can be computed at compile time:
+//!
+//! ```
+//! use drone_cortexm::drv::mpu::{Access, MemAttr, MpuRegion, RegionSize};
+//!
+//! // Flash: read-only, executable.
+//! static FLASH: MpuRegion = MpuRegion::new(0x0800_0000, RegionSize::M1)
+//!     .access(Access::ReadOnly)
+//!     .attributes(MemAttr::NORMAL_WT);
+//!
+//! // Peripherals: privileged access only, never executable.
+//! static PERIPHERALS: MpuRegion = MpuRegion::new(0x4000_0000, RegionSize::M512)
+//!     .access(Access::PrivRw)
+//!     .attributes(MemAttr::DEVICE)
+//!     .execute_never();
+//!
+//! // DMA buffer: non-cacheable, the last 1/8 of the region is not covered.
+//! static DMA_BUFFER: MpuRegion = MpuRegion::new(0x2000_8000, RegionSize::K1)
+//!     .access(Access::FullAccess)
+//!     .attributes(MemAttr::NORMAL_NON_CACHEABLE)
+//!     .execute_never()
+//!     .disable_subregions(0b1000_0000);
+//! ```
+//!
+//! A misaligned base address or an invalid subregion mask is reported as a
+//! compile error when the region is a constant or a static, and as a panic
+//! otherwise.
+//!
+//! Regions 0 to 3 are reserved for [`FiberProc`](crate::fib::FiberProc): region
+//! 0 is the stack guard, and regions 1 to 3 are set per fiber with
+//! [`FiberProc::set_mpu_region`](crate::fib::FiberProc::set_mpu_region).
+//! Region 7 is reserved for the main stack guard set up by
+//! [`thr::guard_main_stack`](crate::thr::guard_main_stack).
+//! [`Mpu::set_region`] and [`Mpu::clear_region`] panic on the reserved regions.
+//!
+//! The driver owns the MPU register tokens, which are extracted with
+//! [`periph_mpu!`](crate::map::periph::mpu::periph_mpu):
+//!
+//! ```no_run
+//! use drone_cortexm::drv::mpu::{Access, Mpu, MpuRegion, RegionSize};
+//! # use drone_cortexm::map::periph::mpu::MpuPeriph;
+//!
+//! const SRAM: MpuRegion = MpuRegion::new(0x2000_0000, RegionSize::K64).access(Access::FullAccess);
+//!
+//! # fn handler(periph: MpuPeriph) {
+//! let mut mpu = Mpu::new(periph);
+//! mpu.set_region(4, &SRAM);
+//! mpu.enable(true, false);
+//! # }
+//! # fn main() {}
+//! ```
+//!
+//! The driver can be combined with [`FiberProc`](crate::fib::FiberProc) stacks.
+//! Switching to a fiber loads regions 0 to 3 and sets the ENABLE and
+//! PRIVDEFENA bits, keeping HFNMIENA. While the MPU is enabled with
+//! [`Mpu::enable`], switching back disables only regions 0 to 3, so regions 4
+//! to 6 stay in effect.
+
+#![cfg_attr(feature = "std", allow(unreachable_code))]
+
+use crate::{
+    map::{periph::mpu::MpuPeriph, reg::mpu},
+    reg::prelude::*,
+    util::assert,
+};
+use core::sync::atomic::{AtomicBool, Ordering};
+
+/// Regions 0 to 3 are switched with [`FiberProc`](crate::fib::FiberProc).
+const FIBER_REGIONS: u8 = 4;
+/// The region of [`thr::guard_main_stack`](crate::thr::guard_main_stack).
+const MAIN_STACK_GUARD_REGION: u8 = 7;
+
+/// Whether the MPU is enabled with [`Mpu::enable`]. Switching back from a
+/// [`FiberProc`](crate::fib::FiberProc) stack keeps the MPU enabled if this
+/// flag is set.
+pub(crate) static ENABLED: AtomicBool = AtomicBool::new(false);
+
+const RBAR_VALID: u32 = 1 << 4;
+const RASR_XN: u32 = 1 << 28;
+const RASR_S: u32 = 1 << 18;
+const RASR_ENABLE: u32 = 1 << 0;
+
+/// MPU driver.
+pub struct Mpu {
+    periph: MpuPeriph,
+}
+
+/// MPU region configuration.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MpuRegion {
+    base: u32,
+    rasr: u32,
+}
+
+/// Region size.
+#[allow(missing_docs)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum RegionSize {
+    B32 = 4,
+    B64 = 5,
+    B128 = 6,
+    B256 = 7,
+    B512 = 8,
+    K1 = 9,
+    K2 = 10,
+    K4 = 11,
+    K8 = 12,
+    K16 = 13,
+    K32 = 14,
+    K64 = 15,
+    K128 = 16,
+    K256 = 17,
+    K512 = 18,
+    M1 = 19,
+    M2 = 20,
+    M4 = 21,
+    M8 = 22,
+    M16 = 23,
+    M32 = 24,
+    M64 = 25,
+    M128 = 26,
+    M256 = 27,
+    M512 = 28,
+    G1 = 29,
+    G2 = 30,
+    G4 = 31,
+}
+
+/// Region access permissions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Access {
+    /// No access.
+    NoAccess = 0b000,
+    /// Privileged read-write, unprivileged no access.
+    PrivRw = 0b001,
+    /// Privileged read-write, unprivileged read-only.
+    PrivRwUnprivRo = 0b010,
+    /// Full access.
+    FullAccess = 0b011,
+    /// Privileged read-only, unprivileged no access.
+    PrivRo = 0b101,
+    /// Read-only.
+    ReadOnly = 0b110,
+}
+
+/// Region memory attributes, encoded in the TEX, C, and B bits.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MemAttr {
+    tex: u8,
+    c: bool,
+    b: bool,
+}
+
+impl MemAttr {
+    /// Strongly-ordered memory.
+    pub const STRONGLY_ORDERED: Self = Self::new(0b000, false, false);
+    /// Device memory.
+    pub const DEVICE: Self = Self::new(0b000, false, true);
+    /// Normal memory, write-through, no write-allocate.
+    pub const NORMAL_WT: Self = Self::new(0b000, true, false);
+    /// Normal memory, write-back, no write-allocate.
+    pub const NORMAL_WB: Self = Self::new(0b000, true, true);
+    /// Normal memory, non-cacheable.
+    pub const NORMAL_NON_CACHEABLE: Self = Self::new(0b001, false, false);
+    /// Normal memory, write-back, write and read allocate.
+    pub const NORMAL_WB_WA: Self = Self::new(0b001, true, true);
+
+    /// Creates memory attributes from raw TEX, C, and B values.
+    pub const fn new(tex: u8, c: bool, b: bool) -> Self {
+        Self { tex: tex & 0b111, c, b }
+    }
+}
+
+impl MpuRegion {
+    /// Creates a region of `size` bytes starting at `base`. The region is
+    /// strongly-ordered and has no access by default.
+    ///
+    /// # Panics
+    ///
+    /// If `base` is not aligned to `size`.
+    pub const fn new(base: u32, size: RegionSize) -> Self {
+        let mask = ((1_u64 << size as u8 + 1) - 1) as u32;
+        assert(base & mask == 0);
+        Self { base, rasr: (size as u32) << 1 | RASR_ENABLE }
+    }
+
+    /// Sets access permissions.
+    pub const fn access(mut self, access: Access) -> Self {
+        self.rasr = self.rasr & !(0b111 << 24) | (access as u32) << 24;
+        self
+    }
+
+    /// Sets memory attributes.
+    pub const fn attributes(mut self, attr: MemAttr) -> Self {
+        self.rasr = self.rasr & !(0b111 << 19 | 0b11 << 16)
+            | (attr.tex as u32) << 19
+            | (attr.c as u32) << 17
+            | (attr.b as u32) << 16;
+        self
+    }
+
+    /// Marks the region as shareable.
+    pub const fn shareable(mut self) -> Self {
+        self.rasr |= RASR_S;
+        self
+    }
+
+    /// Disables instruction fetches from the region.
+    pub const fn execute_never(mut self) -> Self {
+        self.rasr |= RASR_XN;
+        self
+    }
+
+    /// Disables subregions. Each bit of `mask` disables one of eight equal
+    /// parts of the region, starting from the lowest address.
+    ///
+    /// # Panics
+    ///
+    /// If the region is smaller than 256 bytes.
+    pub const fn disable_subregions(mut self, mask: u8) -> Self {
+        assert(mask == 0 || self.size() >= RegionSize::B256 as u32);
+        self.rasr = self.rasr & !(0xFF << 8) | (mask as u32) << 8;
+        self
+    }
+
+    /// Returns the base address.
+    pub const fn base(&self) -> u32 {
+        self.base
+    }
+
+    /// Returns the value of the RBAR register to configure the region as the
+    /// region `number`.
+    pub const fn rbar(&self, number: u8) -> u32 {
+        self.base | RBAR_VALID | number as u32 & 0b1111
+    }
+
+    /// Returns the value of the RASR register.
+    pub const fn rasr(&self) -> u32 {
+        self.rasr
+    }
+
+    const fn size(&self) -> u32 {
+        self.rasr >> 1 & 0b1_1111
+    }
+}
+
+impl Mpu {
+    /// Creates a new MPU driver from the peripheral.
+    ///
+    /// The MPU configuration is reset by [`thr::init`](crate::thr::init), so
+    /// the driver should be created after it.
+    #[inline]
+    pub fn new(periph: MpuPeriph) -> Self {
+        Self { periph }
+    }
+
+    /// Releases the peripheral.
+    #[inline]
+    pub fn free(self) -> MpuPeriph {
+        self.periph
+    }
+
+    /// Returns the number of regions supported by the MPU, or zero if the MPU
+    /// is not present.
+    #[inline]
+    pub fn region_count(&self) -> u8 {
+        self.periph.mpu_type.load().dregion() as u8
+    }
+
+    /// Configures the region `number`.
+    ///
+    /// # Panics
+    ///
+    /// If `number` exceeds the number of regions, or is one of the reserved
+    /// regions 0 to 3 and 7.
+    pub fn set_region(&mut self, number: u8, region: &MpuRegion) {
+        self.check_region(number);
+        // The region number is written to RNR through RBAR.VALID.
+        self.periph.mpu_rbar.store_val(unsafe { mpu::Rbar::<Srt>::val_from(region.rbar(number)) });
+        self.periph.mpu_rasr.store_val(unsafe { mpu::Rasr::<Srt>::val_from(region.rasr()) });
+    }
+
+    /// Disables the region `number`.
+    ///
+    /// # Panics
+    ///
+    /// If `number` exceeds the number of regions, or is one of the reserved
+    /// regions 0 to 3 and 7.
+    pub fn clear_region(&mut self, number: u8) {
+        self.check_region(number);
+        self.periph.mpu_rnr.store(|r| r.write_region(u32::from(number)));
+        self.periph.mpu_rasr.reset();
+    }
+
+    /// Enables the MPU.
+    ///
+    /// If `privdefena` is `true`, the default memory map is used as a
+    /// background region for privileged accesses. If `hfnmiena` is `true`, the
+    /// MPU stays enabled in HardFault and NMI handlers.
+    pub fn enable(&mut self, privdefena: bool, hfnmiena: bool) {
+        self.periph.mpu_ctrl.store(|r| {
+            r.set_enable();
+            if privdefena {
+                r.set_privdefena();
+            }
+            if hfnmiena {
+                r.set_hfnmiena();
+            }
+            r
+        });
+        ENABLED.store(true, Ordering::Relaxed);
+        barrier();
+    }
+
+    /// Disables the MPU.
+    pub fn disable(&mut self) {
+        barrier();
+        ENABLED.store(false, Ordering::Relaxed);
+        self.periph.mpu_ctrl.reset();
+    }
+
+    fn check_region(&self, number: u8) {
+        assert!(number < self.region_count(), "MPU region number out of range");
+        let reserved = number < FIBER_REGIONS || number == MAIN_STACK_GUARD_REGION;
+        assert!(!reserved, "MPU region is reserved");
+    }
+}
+
+fn barrier() {
+    #[cfg(feature = "std")]
+    return;
+    unsafe { llvm_asm!("dsb\nisb" :::: "volatile") };
+}
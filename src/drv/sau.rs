@@ -0,0 +1,152 @@
+//! Security Attribution Unit.
+//!
+//! The SAU divides the memory map into Secure, Non-secure callable, and
+//! Non-secure regions. Memory not covered by any enabled region is Secure.
+//!
+//! ```no_run
+//! use drone_cortexm::drv::sau::{Sau, SauRegion};
+//! # use drone_cortexm::map::periph::sau::SauPeriph;
+//!
+//! // Non-secure application flash.
+//! const NS_FLASH: SauRegion = SauRegion::new(0x0804_0000, 0x080F_FFFF);
+//! // Veneers of the functions exported by the secure image.
+//! const NSC_VENEERS: SauRegion =
+//!     SauRegion::new(0x0803_FE00, 0x0803_FFFF).non_secure_callable();
+//! // Non-secure application RAM.
+//! const NS_RAM: SauRegion = SauRegion::new(0x2001_0000, 0x2001_FFFF);
+//!
+//! # fn handler(periph: SauPeriph) {
+//! let mut sau = Sau::new(periph);
+//! sau.set_region(0, &NS_FLASH);
+//! sau.set_region(1, &NSC_VENEERS);
+//! sau.set_region(2, &NS_RAM);
+//! sau.enable();
+//! # }
+//! # fn main() {}
+//! ```
+
+#![cfg_attr(feature = "std", allow(unreachable_code))]
+
+use crate::{map::periph::sau::SauPeriph, reg::prelude::*, util::assert};
+
+/// SAU driver.
+pub struct Sau {
+    periph: SauPeriph,
+}
+
+/// SAU region configuration.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SauRegion {
+    base: u32,
+    limit: u32,
+    nsc: bool,
+}
+
+impl SauRegion {
+    /// Creates a Non-secure region from `base` to `limit` inclusive.
+    ///
+    /// # Panics
+    ///
+    /// If `base` or `limit + 1` is not aligned to 32 bytes, or `limit` is less
+    /// than `base`. The panic is reported as a compile error when the region is
+    /// a constant or a static.
+    pub const fn new(base: u32, limit: u32) -> Self {
+        assert(base & 0x1F == 0 && limit & 0x1F == 0x1F && limit > base);
+        Self { base, limit, nsc: false }
+    }
+
+    /// Marks the region as Non-secure callable. Non-secure code can call
+    /// Secure code only through SG instructions in Non-secure callable regions.
+    pub const fn non_secure_callable(mut self) -> Self {
+        self.nsc = true;
+        self
+    }
+
+    /// Returns the base address.
+    pub const fn base(&self) -> u32 {
+        self.base
+    }
+
+    /// Returns the limit address.
+    pub const fn limit(&self) -> u32 {
+        self.limit
+    }
+
+    /// Returns `true` if the region is Non-secure callable.
+    pub const fn is_non_secure_callable(&self) -> bool {
+        self.nsc
+    }
+}
+
+impl Sau {
+    /// Creates a new SAU driver from the peripheral.
+    #[inline]
+    pub fn new(periph: SauPeriph) -> Self {
+        Self { periph }
+    }
+
+    /// Releases the peripheral.
+    #[inline]
+    pub fn free(self) -> SauPeriph {
+        self.periph
+    }
+
+    /// Returns the number of implemented SAU regions.
+    #[inline]
+    pub fn region_count(&self) -> u8 {
+        self.periph.sau_type.load().sregion() as u8
+    }
+
+    /// Configures the region `number`.
+    ///
+    /// # Panics
+    ///
+    /// If `number` exceeds the number of regions.
+    pub fn set_region(&mut self, number: u8, region: &SauRegion) {
+        assert!(number < self.region_count(), "SAU region number out of range");
+        self.periph.sau_rnr.store(|r| r.write_region(u32::from(number)));
+        self.periph.sau_rbar.store(|r| r.write_baddr(region.base >> 5));
+        self.periph.sau_rlar.store(|r| {
+            r.write_laddr(region.limit >> 5).set_enable();
+            if region.nsc {
+                r.set_nsc();
+            }
+            r
+        });
+    }
+
+    /// Disables the region `number`.
+    ///
+    /// # Panics
+    ///
+    /// If `number` exceeds the number of regions.
+    pub fn clear_region(&mut self, number: u8) {
+        assert!(number < self.region_count(), "SAU region number out of range");
+        self.periph.sau_rnr.store(|r| r.write_region(u32::from(number)));
+        self.periph.sau_rlar.reset();
+    }
+
+    /// Enables the SAU.
+    pub fn enable(&mut self) {
+        self.periph.sau_ctrl.store(|r| r.set_enable());
+        barrier();
+    }
+
+    /// Disables the SAU. If `all_non_secure` is `true`, the whole memory is
+    /// Non-secure, otherwise it is Secure.
+    pub fn disable(&mut self, all_non_secure: bool) {
+        self.periph.sau_ctrl.store(|r| {
+            if all_non_secure {
+                r.set_allns();
+            }
+            r
+        });
+        barrier();
+    }
+}
+
+fn barrier() {
+    #[cfg(feature = "std")]
+    return;
+    unsafe { llvm_asm!("dsb\nisb" :::: "volatile") };
+}
@@ -73,6 +73,10 @@ impl<I: IntToken> TimerStop for SysTick<I> {
         let mut ctrl_val = self.periph.stk_ctrl.load();
         self.periph.stk_ctrl.store_val(disable(&mut ctrl_val).val());
     }
+
+    fn restart(&mut self) {
+        self.periph.stk_val.store(|r| r.write_current(0));
+    }
 }
 
 impl<I: IntToken> SysTick<I> {
@@ -0,0 +1,252 @@
+//! Generic timer.
+
+mod queue;
+
+pub use self::queue::{TimerQueue, TimerQueueSleep, TimerQueueTimeout};
+
+use core::{
+    fmt,
+    future::Future,
+    num::NonZeroUsize,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use futures::{ready, stream::Stream};
+
+/// Error returned from [`Timer::interval`] on overflow.
+#[derive(Debug)]
+pub struct TimerOverflow;
+
+/// Error returned from [`Timer::timeout`] or [`TimerQueue::timeout`] when the
+/// deadline has elapsed.
+#[derive(Debug)]
+pub struct Elapsed;
+
+/// Generic timer driver.
+pub trait Timer: Send {
+    /// Timer stop handler.
+    type Stop: TimerStop;
+
+    /// Returns a future that resolves when `duration` time is elapsed.
+    fn sleep(&mut self, duration: u32) -> TimerSleep<'_, Self::Stop>;
+
+    /// Returns a stream of pulses that are generated on each `duration`
+    /// interval. Fails on overflow.
+    fn interval(
+        &mut self,
+        duration: u32,
+    ) -> TimerInterval<'_, Self::Stop, Result<NonZeroUsize, TimerOverflow>>;
+
+    /// Returns a stream of pulses that are generated on each `duration`
+    /// interval. Overflows are ignored.
+    fn interval_skip(&mut self, duration: u32) -> TimerInterval<'_, Self::Stop, NonZeroUsize>;
+
+    /// Returns a future that resolves to the output of `future`, or to
+    /// [`Elapsed`] error if `future` doesn't complete within `duration` time.
+    ///
+    /// The timer is stopped as soon as the returned future completes or is
+    /// dropped.
+    fn timeout<F: Future>(&mut self, duration: u32, future: F) -> TimerTimeout<'_, Self::Stop, F>
+    where
+        Self: Sized,
+    {
+        TimerTimeout { sleep: self.sleep(duration), future }
+    }
+}
+
+/// Timer stop handler.
+pub trait TimerStop: Send {
+    /// Stops the timer.
+    fn stop(&mut self);
+
+    /// Restarts the current period of the running timer, so that the next
+    /// pulse is generated one full period from now.
+    ///
+    /// Used by [`MissedTickPolicy::Delay`]. The default implementation does
+    /// nothing, which keeps the original phase of the timer.
+    fn restart(&mut self) {}
+}
+
+/// Future created from [`Timer::sleep`].
+pub struct TimerSleep<'a, T: TimerStop> {
+    stop: &'a mut T,
+    future: Pin<Box<dyn Future<Output = ()> + Send + 'a>>,
+    stopped: bool,
+}
+
+/// Future created from [`Timer::timeout`].
+pub struct TimerTimeout<'a, T: TimerStop, F: Future> {
+    sleep: TimerSleep<'a, T>,
+    future: F,
+}
+
+/// Stream created from [`Timer::interval`] or  [`Timer::interval_skip`].
+pub struct TimerInterval<'a, T: TimerStop, I> {
+    stop: &'a mut T,
+    stream: Pin<Box<dyn Stream<Item = I> + Send + 'a>>,
+    missed_ticks: Option<MissedTicks<I>>,
+}
+
+/// Behavior of [`TimerInterval`] when it is polled too late to observe each
+/// timer pulse separately.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MissedTickPolicy {
+    /// Yields each missed pulse as a separate item, back-to-back.
+    Burst,
+    /// Yields all missed pulses as a single item with their total number. This
+    /// is the default behavior.
+    Skip,
+    /// Yields all missed pulses as a single pulse and restarts the timer, so
+    /// that the next pulse comes one full period after the late one. The
+    /// missed pulses are never caught up.
+    Delay,
+}
+
+struct MissedTicks<I> {
+    policy: MissedTickPolicy,
+    ticks: fn(&I) -> Option<NonZeroUsize>,
+    item: fn(NonZeroUsize) -> I,
+    burst: usize,
+}
+
+impl<'a, T: TimerStop> TimerSleep<'a, T> {
+    /// Creates a new [`TimerSleep`].
+    pub fn new(stop: &'a mut T, future: Pin<Box<dyn Future<Output = ()> + Send + 'a>>) -> Self {
+        Self { stop, future, stopped: false }
+    }
+
+    /// Stops the timer, unless it is already stopped.
+    fn stop(&mut self) {
+        if !self.stopped {
+            self.stopped = true;
+            self.stop.stop();
+        }
+    }
+}
+
+impl<'a, T: TimerStop> Future for TimerSleep<'a, T> {
+    type Output = ();
+
+    #[inline]
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        self.future.as_mut().poll(cx)
+    }
+}
+
+impl<'a, T: TimerStop> Drop for TimerSleep<'a, T> {
+    #[inline]
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+impl<'a, T: TimerStop, F: Future> Future for TimerTimeout<'a, T, F> {
+    type Output = Result<F::Output, Elapsed>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = unsafe { self.get_unchecked_mut() };
+        if let Poll::Ready(output) = unsafe { Pin::new_unchecked(&mut this.future) }.poll(cx) {
+            this.sleep.stop();
+            return Poll::Ready(Ok(output));
+        }
+        Pin::new(&mut this.sleep).poll(cx).map(|()| Err(Elapsed))
+    }
+}
+
+impl<'a, T: TimerStop, I> TimerInterval<'a, T, I> {
+    /// Creates a new [`TimerInterval`].
+    pub fn new(stop: &'a mut T, stream: Pin<Box<dyn Stream<Item = I> + Send + 'a>>) -> Self {
+        Self { stop, stream, missed_ticks: None }
+    }
+
+    /// Stops the timer and the stream.
+    #[inline]
+    pub fn stop(mut self: Pin<&mut Self>) {
+        self.stop.stop();
+    }
+}
+
+impl<'a, T: TimerStop> TimerInterval<'a, T, NonZeroUsize> {
+    /// Sets the behavior of the stream on missed pulses.
+    pub fn with_missed_tick_policy(self, policy: MissedTickPolicy) -> Self {
+        self.missed_ticks(policy, |&ticks| Some(ticks), |ticks| ticks)
+    }
+}
+
+impl<'a, T: TimerStop> TimerInterval<'a, T, Result<NonZeroUsize, TimerOverflow>> {
+    /// Sets the behavior of the stream on missed pulses.
+    pub fn with_missed_tick_policy(self, policy: MissedTickPolicy) -> Self {
+        self.missed_ticks(policy, |item| item.as_ref().ok().copied(), Ok)
+    }
+}
+
+impl<'a, T: TimerStop, I> TimerInterval<'a, T, I> {
+    fn missed_ticks(
+        mut self,
+        policy: MissedTickPolicy,
+        ticks: fn(&I) -> Option<NonZeroUsize>,
+        item: fn(NonZeroUsize) -> I,
+    ) -> Self {
+        self.missed_ticks = Some(MissedTicks { policy, ticks, item, burst: 0 });
+        self
+    }
+}
+
+impl<'a, T: TimerStop, I> Stream for TimerInterval<'a, T, I> {
+    type Item = I;
+
+    #[inline]
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<I>> {
+        let Self { stop, stream, missed_ticks } = &mut *self;
+        match missed_ticks {
+            Some(missed_ticks) => missed_ticks.poll_next(stream.as_mut(), *stop, cx),
+            None => stream.as_mut().poll_next(cx),
+        }
+    }
+}
+
+impl<'a, T: TimerStop, I> Drop for TimerInterval<'a, T, I> {
+    #[inline]
+    fn drop(&mut self) {
+        self.stop.stop();
+    }
+}
+
+impl<I> MissedTicks<I> {
+    fn poll_next<T: TimerStop>(
+        &mut self,
+        stream: Pin<&mut (dyn Stream<Item = I> + Send + '_)>,
+        stop: &mut T,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<I>> {
+        let one = NonZeroUsize::new(1).unwrap();
+        if self.burst > 0 {
+            self.burst -= 1;
+            return Poll::Ready(Some((self.item)(one)));
+        }
+        let item = ready!(stream.poll_next(cx));
+        Poll::Ready(item.map(|item| match (self.policy, (self.ticks)(&item)) {
+            (MissedTickPolicy::Burst, Some(ticks)) => {
+                self.burst = ticks.get() - 1;
+                (self.item)(one)
+            }
+            (MissedTickPolicy::Delay, Some(ticks)) if ticks.get() > 1 => {
+                stop.restart();
+                (self.item)(one)
+            }
+            _ => item,
+        }))
+    }
+}
+
+impl fmt::Display for TimerOverflow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Timer stream overflow.")
+    }
+}
+
+impl fmt::Display for Elapsed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Deadline has elapsed.")
+    }
+}
@@ -0,0 +1,186 @@
+use crate::{
+    drv::{
+        mono::{Duration, Instant},
+        timer::{Elapsed, Timer},
+        waiter::{self, Waiter, WaiterList},
+    },
+    thr::prelude::*,
+};
+use alloc::sync::Arc;
+use core::{
+    future::Future,
+    pin::Pin,
+    sync::atomic::{AtomicU32, Ordering},
+    task::{Context, Poll},
+};
+use futures::stream::StreamExt;
+
+/// A queue of deadlines sharing a single [`Timer`].
+///
+/// The queue runs the timer in the [`Timer::interval_skip`] mode and counts
+/// its pulses. One tick of the queue equals one pulse, i.e. `resolution` units
+/// of the underlying timer. Deadlines are checked on each tick, so a sleep
+/// lasts at least the requested number of ticks, and at most one tick more.
+///
+/// The queue handle is cheap to clone, and each clone can be moved to a
+/// different thread.
+pub struct TimerQueue {
+    shared: Arc<Shared>,
+}
+
+/// Future created from [`TimerQueue::sleep`] or [`TimerQueue::sleep_until`].
+///
+/// The deadline is cancelled when the future is dropped.
+pub struct TimerQueueSleep {
+    shared: Arc<Shared>,
+    waiter: Arc<Waiter>,
+    registered: bool,
+}
+
+/// Future created from [`TimerQueue::timeout`] or [`TimerQueue::deadline`].
+pub struct TimerQueueTimeout<F: Future> {
+    future: F,
+    sleep: TimerQueueSleep,
+}
+
+struct Shared {
+    ticks: Ticks,
+    pending: WaiterList,
+}
+
+/// A 64-bit counter with a single writer, which can be read from any thread
+/// without locking.
+struct Ticks {
+    hi_first: AtomicU32,
+    lo: AtomicU32,
+    hi_last: AtomicU32,
+}
+
+impl TimerQueue {
+    /// Creates a new queue from `timer` and starts it.
+    ///
+    /// The queue is driven by a future executed on the `int` thread, which
+    /// must be the thread of the timer interrupt.
+    pub fn new<T, I>(timer: T, resolution: u32, int: I) -> Self
+    where
+        T: Timer + 'static,
+        T::Stop: 'static,
+        I: IntToken,
+    {
+        let shared = Arc::new(Shared { ticks: Ticks::new(), pending: WaiterList::new() });
+        let driver = Arc::clone(&shared);
+        int.exec(async move {
+            let mut timer = timer;
+            let mut pulses = timer.interval_skip(resolution);
+            let mut queue = Vec::new();
+            let mut now = 0;
+            while let Some(count) = pulses.next().await {
+                now += count.get() as u64;
+                driver.ticks.store(now);
+                driver.pending.take(|waiter| waiter::enqueue(&mut queue, waiter));
+                waiter::expire(&mut queue, now);
+            }
+        });
+        Self { shared }
+    }
+
+    /// Returns the number of ticks elapsed since the queue start.
+    #[inline]
+    pub fn now(&self) -> Instant {
+        Instant::from_ticks(self.shared.ticks.load())
+    }
+
+    /// Returns a future that resolves when `duration` ticks are elapsed.
+    pub fn sleep(&self, duration: Duration) -> TimerQueueSleep {
+        self.sleep_until(self.now() + duration)
+    }
+
+    /// Returns a future that resolves when the queue reaches `deadline`.
+    pub fn sleep_until(&self, deadline: Instant) -> TimerQueueSleep {
+        TimerQueueSleep {
+            shared: Arc::clone(&self.shared),
+            waiter: Waiter::new(deadline.ticks()),
+            registered: false,
+        }
+    }
+
+    /// Returns a future that resolves to the output of `future`, or to
+    /// [`Elapsed`] error if `future` doesn't complete within `duration` ticks.
+    ///
+    /// `future` is dropped together with the returned future.
+    pub fn timeout<F: Future>(&self, duration: Duration, future: F) -> TimerQueueTimeout<F> {
+        TimerQueueTimeout { future, sleep: self.sleep(duration) }
+    }
+
+    /// Returns a future that resolves to the output of `future`, or to
+    /// [`Elapsed`] error if `future` doesn't complete before the queue reaches
+    /// `deadline`.
+    ///
+    /// `future` is dropped together with the returned future.
+    pub fn deadline<F: Future>(&self, deadline: Instant, future: F) -> TimerQueueTimeout<F> {
+        TimerQueueTimeout { future, sleep: self.sleep_until(deadline) }
+    }
+}
+
+impl Clone for TimerQueue {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self { shared: Arc::clone(&self.shared) }
+    }
+}
+
+impl Future for TimerQueueSleep {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if !self.registered {
+            if self.shared.ticks.load() >= self.waiter.deadline() {
+                return Poll::Ready(());
+            }
+            self.registered = true;
+            self.shared.pending.push(Arc::clone(&self.waiter));
+        }
+        self.waiter.poll(cx)
+    }
+}
+
+impl Drop for TimerQueueSleep {
+    fn drop(&mut self) {
+        self.waiter.cancel();
+    }
+}
+
+impl<F: Future> Future for TimerQueueTimeout<F> {
+    type Output = Result<F::Output, Elapsed>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = unsafe { self.get_unchecked_mut() };
+        if let Poll::Ready(output) = unsafe { Pin::new_unchecked(&mut this.future) }.poll(cx) {
+            return Poll::Ready(Ok(output));
+        }
+        Pin::new(&mut this.sleep).poll(cx).map(|()| Err(Elapsed))
+    }
+}
+
+impl Ticks {
+    fn new() -> Self {
+        Self { hi_first: AtomicU32::new(0), lo: AtomicU32::new(0), hi_last: AtomicU32::new(0) }
+    }
+
+    fn load(&self) -> u64 {
+        let hi_last = self.hi_last.load(Ordering::Acquire);
+        let lo = self.lo.load(Ordering::Acquire);
+        let hi_first = self.hi_first.load(Ordering::Acquire);
+        // If the high word is being updated concurrently, the low word tells
+        // whether it was read before or after the carry.
+        let hi = if hi_last == hi_first || lo >= 1 << 31 { hi_last } else { hi_first };
+        u64::from(hi) << 32 | u64::from(lo)
+    }
+
+    fn store(&self, value: u64) {
+        let hi = (value >> 32) as u32;
+        self.hi_first.store(hi, Ordering::Release);
+        self.lo.store(value as u32, Ordering::Release);
+        self.hi_last.store(hi, Ordering::Release);
+    }
+}
@@ -0,0 +1,126 @@
+//! Lock-free registry of timer waiters.
+
+use alloc::sync::Arc;
+use core::{
+    ptr,
+    sync::atomic::{AtomicPtr, AtomicU8, Ordering},
+    task::{Context, Poll},
+};
+use futures::task::AtomicWaker;
+
+const PENDING: u8 = 0;
+const FIRED: u8 = 1;
+const CANCELLED: u8 = 2;
+
+/// A single deadline registered by a sleeping future.
+pub(crate) struct Waiter {
+    deadline: u64,
+    state: AtomicU8,
+    waker: AtomicWaker,
+    next: AtomicPtr<Waiter>,
+}
+
+/// A multi-producer stack of newly registered waiters.
+///
+/// Any thread can push a waiter, while the timer thread takes all of them at
+/// once.
+pub(crate) struct WaiterList {
+    head: AtomicPtr<Waiter>,
+}
+
+impl Waiter {
+    pub(crate) fn new(deadline: u64) -> Arc<Self> {
+        Arc::new(Self {
+            deadline,
+            state: AtomicU8::new(PENDING),
+            waker: AtomicWaker::new(),
+            next: AtomicPtr::new(ptr::null_mut()),
+        })
+    }
+
+    pub(crate) fn deadline(&self) -> u64 {
+        self.deadline
+    }
+
+    /// Marks the waiter as elapsed and wakes its task.
+    pub(crate) fn fire(&self) {
+        if self.state.compare_exchange(PENDING, FIRED, Ordering::AcqRel, Ordering::Acquire).is_ok()
+        {
+            self.waker.wake();
+        }
+    }
+
+    /// Marks the waiter as no longer interested in the deadline.
+    pub(crate) fn cancel(&self) {
+        let _ =
+            self.state.compare_exchange(PENDING, CANCELLED, Ordering::AcqRel, Ordering::Acquire);
+    }
+
+    pub(crate) fn is_cancelled(&self) -> bool {
+        self.state.load(Ordering::Acquire) == CANCELLED
+    }
+
+    pub(crate) fn poll(&self, cx: &mut Context<'_>) -> Poll<()> {
+        if self.state.load(Ordering::Acquire) == FIRED {
+            return Poll::Ready(());
+        }
+        self.waker.register(cx.waker());
+        if self.state.load(Ordering::Acquire) == FIRED { Poll::Ready(()) } else { Poll::Pending }
+    }
+}
+
+impl WaiterList {
+    pub(crate) const fn new() -> Self {
+        Self { head: AtomicPtr::new(ptr::null_mut()) }
+    }
+
+    /// Pushes `waiter` to the list.
+    pub(crate) fn push(&self, waiter: Arc<Waiter>) {
+        let node = Arc::into_raw(waiter) as *mut Waiter;
+        let mut head = self.head.load(Ordering::Relaxed);
+        loop {
+            unsafe { (*node).next.store(head, Ordering::Relaxed) };
+            match self.head.compare_exchange_weak(head, node, Ordering::Release, Ordering::Relaxed)
+            {
+                Ok(_) => break,
+                Err(actual) => head = actual,
+            }
+        }
+    }
+
+    /// Takes all pushed waiters, passing each of them to `f`.
+    pub(crate) fn take(&self, mut f: impl FnMut(Arc<Waiter>)) {
+        let mut node = self.head.swap(ptr::null_mut(), Ordering::Acquire);
+        while !node.is_null() {
+            let waiter = unsafe { Arc::from_raw(node) };
+            node = waiter.next.swap(ptr::null_mut(), Ordering::Relaxed);
+            f(waiter);
+        }
+    }
+}
+
+impl Drop for WaiterList {
+    fn drop(&mut self) {
+        self.take(drop);
+    }
+}
+
+/// Inserts `waiter` into the deadline-ordered `queue`.
+pub(crate) fn enqueue(queue: &mut Vec<Arc<Waiter>>, waiter: Arc<Waiter>) {
+    let idx = queue
+        .iter()
+        .position(|item| item.deadline() > waiter.deadline())
+        .unwrap_or_else(|| queue.len());
+    queue.insert(idx, waiter);
+}
+
+/// Fires all waiters from `queue` which deadlines are not after `now`, and
+/// removes cancelled ones. Returns the earliest remaining deadline.
+pub(crate) fn expire(queue: &mut Vec<Arc<Waiter>>, now: u64) -> Option<u64> {
+    let expired = queue.iter().take_while(|waiter| waiter.deadline() <= now).count();
+    for waiter in queue.drain(..expired) {
+        waiter.fire();
+    }
+    queue.retain(|waiter| !waiter.is_cancelled());
+    queue.first().map(Waiter::deadline)
+}
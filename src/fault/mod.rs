@@ -0,0 +1,465 @@
+//! Fault analysis.
+//!
+//! This module provides [`handler`], a ready-made naked exception handler for
+//! the fault exceptions. The handler captures the exception frame stacked by
+//! the processor, decodes the fault status registers into a [`FaultReport`],
+//! and passes the result to a hook defined with [`set_handler!`].
+//!
+//! # Examples
+//!
+//! ```
+//! # #![feature(const_fn_fn_ptr_basics)]
+//! # #![feature(marker_trait_attr)]
+//! # fn main() {}
+//! use drone_cortexm::{fault, map::thr::*, thr};
+//!
+//! thr! {
+//!     thread => pub Thr {};
+//!     local => pub ThrLocal {};
+//!     vtable => pub Vtable;
+//!     index => pub Thrs;
+//!     init => pub ThrsInit;
+//!     threads => {
+//!         exceptions => {
+//!             pub naked(fault::handler) hard_fault;
+//!             pub naked(fault::handler) mem_manage;
+//!             pub naked(fault::handler) bus_fault;
+//!             pub naked(fault::handler) usage_fault;
+//!         };
+//!     };
+//! }
+//!
+//! // Log the fault report and reset the processor.
+//! fault::set_handler!();
+//! ```
+//!
+//! MemManage, BusFault, and UsageFault exceptions are escalated to HardFault
+//! unless they are enabled in the `SCB_SHCSR` register.
+//!
+//! With the `security-extension` feature, the handler can also be used for the
+//! `secure_fault` exception. The SecureFault Status Register is checked first,
+//! and its cause is reported as [`FaultReport::Secure`].
+
+#![cfg_attr(feature = "std", allow(unreachable_code))]
+
+pub mod record;
+
+#[cfg(feature = "security-extension")]
+use crate::map::reg::sau;
+use crate::{map::reg::scb, processor, reg::prelude::*};
+use core::{fmt, intrinsics::unreachable};
+use drone_core::token::Token;
+
+const HFSR_VECTTBL: u32 = 1 << 1;
+const HFSR_FORCED: u32 = 1 << 30;
+const HFSR_DEBUGEVT: u32 = 1 << 31;
+
+const MMFSR_IACCVIOL: u32 = 1 << 0;
+const MMFSR_DACCVIOL: u32 = 1 << 1;
+const MMFSR_MUNSTKERR: u32 = 1 << 3;
+const MMFSR_MSTKERR: u32 = 1 << 4;
+const MMFSR_MLSPERR: u32 = 1 << 5;
+const MMFSR_MMARVALID: u32 = 1 << 7;
+
+const BFSR_IBUSERR: u32 = 1 << 8;
+const BFSR_PRECISERR: u32 = 1 << 9;
+const BFSR_IMPRECISERR: u32 = 1 << 10;
+const BFSR_UNSTKERR: u32 = 1 << 11;
+const BFSR_STKERR: u32 = 1 << 12;
+const BFSR_LSPERR: u32 = 1 << 13;
+const BFSR_BFARVALID: u32 = 1 << 15;
+
+const UFSR_UNDEFINSTR: u32 = 1 << 16;
+const UFSR_INVSTATE: u32 = 1 << 17;
+const UFSR_INVPC: u32 = 1 << 18;
+const UFSR_NOCP: u32 = 1 << 19;
+const UFSR_UNALIGNED: u32 = 1 << 24;
+const UFSR_DIVBYZERO: u32 = 1 << 25;
+
+#[cfg(feature = "security-extension")]
+const SFSR_INVEP: u32 = 1 << 0;
+#[cfg(feature = "security-extension")]
+const SFSR_INVIS: u32 = 1 << 1;
+#[cfg(feature = "security-extension")]
+const SFSR_INVER: u32 = 1 << 2;
+#[cfg(feature = "security-extension")]
+const SFSR_AUVIOL: u32 = 1 << 3;
+#[cfg(feature = "security-extension")]
+const SFSR_INVTRAN: u32 = 1 << 4;
+#[cfg(feature = "security-extension")]
+const SFSR_LSPERR: u32 = 1 << 5;
+#[cfg(feature = "security-extension")]
+const SFSR_SFARVALID: u32 = 1 << 6;
+#[cfg(feature = "security-extension")]
+const SFSR_LSERR: u32 = 1 << 7;
+
+extern "Rust" {
+    fn drone_fault_handler(fault: &Fault) -> !;
+}
+
+/// Exception frame stacked by the processor on exception entry.
+#[allow(missing_docs)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(C)]
+pub struct ExceptionFrame {
+    pub r0: u32,
+    pub r1: u32,
+    pub r2: u32,
+    pub r3: u32,
+    pub r12: u32,
+    pub lr: u32,
+    pub pc: u32,
+    pub xpsr: u32,
+}
+
+/// Captured fault information.
+#[derive(Clone, Copy, Debug)]
+pub struct Fault {
+    /// Decoded fault cause.
+    pub report: FaultReport,
+    /// Exception frame of the faulting context.
+    pub frame: ExceptionFrame,
+    /// `EXC_RETURN` value of the fault exception.
+    pub exc_return: u32,
+    /// Whether the fault has been escalated to HardFault.
+    pub escalated: bool,
+    /// Raw value of the HardFault Status Register.
+    pub hfsr: u32,
+    /// Raw value of the Configurable Fault Status Register.
+    pub cfsr: u32,
+    /// Raw value of the MemManage Fault Address Register.
+    pub mmfar: u32,
+    /// Raw value of the BusFault Address Register.
+    pub bfar: u32,
+}
+
+/// Decoded fault cause.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FaultReport {
+    /// BusFault on a vector table read during exception processing.
+    VectorTable,
+    /// Memory management fault.
+    MemManage {
+        /// Fault cause.
+        cause: MemManageCause,
+        /// Address of the faulting access, if valid.
+        address: Option<u32>,
+    },
+    /// Bus fault.
+    Bus {
+        /// Fault cause.
+        cause: BusCause,
+        /// Address of the faulting access, if valid.
+        address: Option<u32>,
+    },
+    /// Usage fault.
+    Usage {
+        /// Fault cause.
+        cause: UsageCause,
+    },
+    /// Secure fault.
+    #[cfg(feature = "security-extension")]
+    Secure {
+        /// Fault cause.
+        cause: SecureCause,
+        /// Address of the faulting access, if valid.
+        address: Option<u32>,
+    },
+    /// Debug event escalated to HardFault.
+    DebugEvent,
+    /// The fault status registers don't indicate any cause.
+    Unknown,
+}
+
+/// Memory management fault cause.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MemManageCause {
+    /// Instruction fetch from a location that doesn't permit execution.
+    InstructionAccess,
+    /// Load or store at a location that doesn't permit the operation.
+    DataAccess,
+    /// Unstacking for an exception return.
+    Unstacking,
+    /// Stacking for an exception entry.
+    Stacking,
+    /// Lazy floating-point state preservation.
+    LazyFpStacking,
+}
+
+/// Bus fault cause.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BusCause {
+    /// Instruction prefetch.
+    InstructionBus,
+    /// Precise data access.
+    Precise,
+    /// Imprecise data access.
+    Imprecise,
+    /// Unstacking for an exception return.
+    Unstacking,
+    /// Stacking for an exception entry.
+    Stacking,
+    /// Lazy floating-point state preservation.
+    LazyFpStacking,
+}
+
+/// Usage fault cause.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UsageCause {
+    /// Undefined instruction.
+    UndefinedInstruction,
+    /// Invalid execution state, e.g. an attempt to switch to the ARM state.
+    InvalidState,
+    /// Invalid `EXC_RETURN` value loaded to the PC.
+    InvalidPc,
+    /// Attempt to access a disabled or absent coprocessor.
+    NoCoprocessor,
+    /// Unaligned memory access.
+    Unaligned,
+    /// Division by zero.
+    DivideByZero,
+}
+
+/// Secure fault cause.
+#[cfg(feature = "security-extension")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SecureCause {
+    /// Non-secure code called a Secure address that isn't a valid entry point.
+    InvalidEntryPoint,
+    /// Unstacked exception frame has an invalid integrity signature.
+    InvalidIntegritySignature,
+    /// Invalid `EXC_RETURN` value on an exception return from Non-secure state.
+    InvalidExceptionReturn,
+    /// Non-secure access to Secure memory.
+    AttributionUnitViolation,
+    /// Branch to Non-secure memory without a state transition instruction.
+    InvalidTransition,
+    /// Lazy floating-point state preservation violated the SAU or IDAU.
+    LazyStatePreservation,
+    /// Error during the lazy floating-point state activation or deactivation.
+    LazyStateError,
+}
+
+/// Fault exception handler.
+///
+/// Captures the exception frame and the fault status registers, and calls the
+/// hook defined with [`set_handler!`].
+///
+/// # Safety
+///
+/// This function should be called only by NVIC as part of a vector table.
+#[naked]
+pub unsafe extern "C" fn handler() {
+    #[cfg(feature = "std")]
+    return unimplemented!();
+    llvm_asm!("
+        tst lr, #4
+        ite eq
+        mrseq r0, msp
+        mrsne r0, psp
+        mov r1, lr
+        bx r2
+    "   :
+        : "{r2}"(handle as unsafe extern "C" fn(&ExceptionFrame, u32) -> !)
+        : "r0", "r1", "cc"
+        : "volatile"
+    );
+    unsafe { unreachable() };
+}
+
+/// Decodes the fault status registers.
+///
+/// `hfsr` is the value of the HardFault Status Register, `cfsr` is the value
+/// of the Configurable Fault Status Register, `mmfar` and `bfar` are the
+/// values of the MemManage and BusFault Address Registers.
+pub fn decode(hfsr: u32, cfsr: u32, mmfar: u32, bfar: u32) -> FaultReport {
+    if hfsr & HFSR_VECTTBL != 0 {
+        return FaultReport::VectorTable;
+    }
+    let mem_manage = [
+        (MMFSR_IACCVIOL, MemManageCause::InstructionAccess),
+        (MMFSR_DACCVIOL, MemManageCause::DataAccess),
+        (MMFSR_MUNSTKERR, MemManageCause::Unstacking),
+        (MMFSR_MSTKERR, MemManageCause::Stacking),
+        (MMFSR_MLSPERR, MemManageCause::LazyFpStacking),
+    ];
+    if let Some(&(_, cause)) = mem_manage.iter().find(|(bit, _)| cfsr & bit != 0) {
+        let address = if cfsr & MMFSR_MMARVALID == 0 { None } else { Some(mmfar) };
+        return FaultReport::MemManage { cause, address };
+    }
+    let bus = [
+        (BFSR_IBUSERR, BusCause::InstructionBus),
+        (BFSR_PRECISERR, BusCause::Precise),
+        (BFSR_IMPRECISERR, BusCause::Imprecise),
+        (BFSR_UNSTKERR, BusCause::Unstacking),
+        (BFSR_STKERR, BusCause::Stacking),
+        (BFSR_LSPERR, BusCause::LazyFpStacking),
+    ];
+    if let Some(&(_, cause)) = bus.iter().find(|(bit, _)| cfsr & bit != 0) {
+        let address = if cfsr & BFSR_BFARVALID == 0 { None } else { Some(bfar) };
+        return FaultReport::Bus { cause, address };
+    }
+    let usage = [
+        (UFSR_UNDEFINSTR, UsageCause::UndefinedInstruction),
+        (UFSR_INVSTATE, UsageCause::InvalidState),
+        (UFSR_INVPC, UsageCause::InvalidPc),
+        (UFSR_NOCP, UsageCause::NoCoprocessor),
+        (UFSR_UNALIGNED, UsageCause::Unaligned),
+        (UFSR_DIVBYZERO, UsageCause::DivideByZero),
+    ];
+    if let Some(&(_, cause)) = usage.iter().find(|(bit, _)| cfsr & bit != 0) {
+        return FaultReport::Usage { cause };
+    }
+    if hfsr & HFSR_DEBUGEVT != 0 {
+        return FaultReport::DebugEvent;
+    }
+    FaultReport::Unknown
+}
+
+/// Decodes the SecureFault Status Register.
+///
+/// `sfsr` is the value of the SecureFault Status Register, `sfar` is the value
+/// of the SecureFault Address Register. Returns `None` if `sfsr` doesn't
+/// indicate any cause.
+#[cfg(feature = "security-extension")]
+pub fn decode_secure(sfsr: u32, sfar: u32) -> Option<FaultReport> {
+    let secure = [
+        (SFSR_INVEP, SecureCause::InvalidEntryPoint),
+        (SFSR_INVIS, SecureCause::InvalidIntegritySignature),
+        (SFSR_INVER, SecureCause::InvalidExceptionReturn),
+        (SFSR_AUVIOL, SecureCause::AttributionUnitViolation),
+        (SFSR_INVTRAN, SecureCause::InvalidTransition),
+        (SFSR_LSPERR, SecureCause::LazyStatePreservation),
+        (SFSR_LSERR, SecureCause::LazyStateError),
+    ];
+    secure.iter().find(|(bit, _)| sfsr & bit != 0).map(|&(_, cause)| {
+        let address = if sfsr & SFSR_SFARVALID == 0 { None } else { Some(sfar) };
+        FaultReport::Secure { cause, address }
+    })
+}
+
+/// Logs `fault` and resets the processor.
+///
+/// This is the default hook for [`set_handler!`].
+pub fn report_and_reset(fault: &Fault) -> ! {
+    drone_core::eprintln!("{}", fault);
+    drone_core::log::flush();
+    processor::self_reset()
+}
+
+impl Fault {
+    /// Reads the fault status registers and decodes them.
+    pub fn capture(frame: ExceptionFrame, exc_return: u32) -> Self {
+        let (hfsr, cfsr, mmfar, bfar) = unsafe {
+            (
+                scb::Hfsr::<Urt>::take().load().val().bits(),
+                u32::from(scb::Mmfsr::<Urt>::take().load().val().bits())
+                    | u32::from(scb::Bfsr::<Urt>::take().load().val().bits()) << 8
+                    | u32::from(scb::Ufsr::<Urt>::take().load().val().bits()) << 16,
+                scb::Mmfar::<Urt>::take().load().val().bits(),
+                scb::Bfar::<Urt>::take().load().val().bits(),
+            )
+        };
+        let report = decode(hfsr, cfsr, mmfar, bfar);
+        #[cfg(feature = "security-extension")]
+        let report = unsafe {
+            decode_secure(
+                sau::Sfsr::<Urt>::take().load().val().bits(),
+                sau::Sfar::<Urt>::take().load().val().bits(),
+            )
+        }
+        .unwrap_or(report);
+        Self {
+            report,
+            frame,
+            exc_return,
+            escalated: hfsr & HFSR_FORCED != 0,
+            hfsr,
+            cfsr,
+            mmfar,
+            bfar,
+        }
+    }
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.report)?;
+        if self.escalated {
+            write!(f, " (escalated to HardFault)")?;
+        }
+        let ExceptionFrame { r0, r1, r2, r3, r12, lr, pc, xpsr } = self.frame;
+        write!(
+            f,
+            "\n  pc={:#010X} lr={:#010X} xpsr={:#010X} exc_return={:#010X}\n  r0={:#010X} \
+             r1={:#010X} r2={:#010X} r3={:#010X} r12={:#010X}\n  hfsr={:#010X} cfsr={:#010X}",
+            pc, lr, xpsr, self.exc_return, r0, r1, r2, r3, r12, self.hfsr, self.cfsr
+        )
+    }
+}
+
+impl fmt::Display for FaultReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::VectorTable => write!(f, "HardFault on vector table read"),
+            Self::MemManage { cause, address } => {
+                write!(f, "MemManage fault: {:?}", cause)?;
+                address.map_or(Ok(()), |address| write!(f, " at {:#010X}", address))
+            }
+            Self::Bus { cause, address } => {
+                write!(f, "BusFault: {:?}", cause)?;
+                address.map_or(Ok(()), |address| write!(f, " at {:#010X}", address))
+            }
+            Self::Usage { cause } => write!(f, "UsageFault: {:?}", cause),
+            #[cfg(feature = "security-extension")]
+            Self::Secure { cause, address } => {
+                write!(f, "SecureFault: {:?}", cause)?;
+                address.map_or(Ok(()), |address| write!(f, " at {:#010X}", address))
+            }
+            Self::DebugEvent => write!(f, "HardFault on debug event"),
+            Self::Unknown => write!(f, "Unknown fault"),
+        }
+    }
+}
+
+unsafe extern "C" fn handle(frame: &ExceptionFrame, exc_return: u32) -> ! {
+    let fault = Fault::capture(*frame, exc_return);
+    unsafe { drone_fault_handler(&fault) }
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! fault_set_handler {
+    () => {
+        $crate::fault_set_handler!($crate::fault::report_and_reset);
+    };
+    ($handler:path) => {
+        const _: () = {
+            #[no_mangle]
+            fn drone_fault_handler(fault: &$crate::fault::Fault) -> ! {
+                $handler(fault)
+            }
+        };
+    };
+}
+
+/// Sets the hook called by [`handler`] with the captured fault.
+///
+/// Without arguments, the hook is [`report_and_reset`], which logs the fault
+/// through the default logger, e.g. SWO, and resets the processor.
+///
+/// # Examples
+///
+/// ```
+/// use drone_cortexm::fault::{self, Fault};
+///
+/// fn on_fault(fault: &Fault) -> ! {
+///     // Save `fault` for the post-mortem analysis.
+///     fault::report_and_reset(fault)
+/// }
+///
+/// fault::set_handler!(on_fault);
+/// # fn main() {}
+/// ```
+#[doc(inline)]
+pub use crate::fault_set_handler as set_handler;
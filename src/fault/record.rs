@@ -0,0 +1,178 @@
+//! Crash record persisted across resets.
+//!
+//! The record is stored in the `.noinit` RAM section, which must be placed by
+//! the linker script outside of the `.bss` and `.data` sections, e.g. as a
+//! `NOLOAD` section. The contents of the section are kept intact by
+//! [`processor::self_reset`](crate::processor::self_reset), so the record can
+//! be read back on the next boot.
+//!
+//! # Examples
+//!
+//! ```
+//! use drone_cortexm::fault::{self, record};
+//!
+//! // Save the fault to the crash record, log it, and reset the processor.
+//! fault::set_handler!(record::save_and_reset);
+//!
+//! unsafe extern "C" fn reset() -> ! {
+//!     // Initialize `.bss` and `.data` sections here, as usual.
+//!     if let Some(crash) = record::take() {
+//!         // Report the previous crash.
+//!         drone_core::eprintln!("{}", crash);
+//!     }
+//!     loop {}
+//! }
+//! # fn main() {}
+//! ```
+
+use super::{report_and_reset, ExceptionFrame, Fault, FaultReport};
+use core::{
+    fmt,
+    mem::MaybeUninit,
+    ptr::{read_volatile, write_volatile},
+};
+
+const MAGIC: u32 = 0xC4A5_11ED;
+const WORDS: usize = 14;
+const VECTACTIVE_MASK: u32 = 0x1FF;
+
+#[cfg_attr(not(feature = "std"), link_section = ".noinit")]
+static mut SLOT: MaybeUninit<Slot> = MaybeUninit::uninit();
+
+#[repr(C)]
+struct Slot {
+    magic: u32,
+    words: [u32; WORDS],
+    checksum: u32,
+}
+
+/// Fault information saved by [`save`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CrashRecord {
+    /// Exception frame of the faulting context.
+    pub frame: ExceptionFrame,
+    /// `EXC_RETURN` value of the fault exception.
+    pub exc_return: u32,
+    /// Value of the HardFault Status Register.
+    pub hfsr: u32,
+    /// Value of the Configurable Fault Status Register.
+    pub cfsr: u32,
+    /// Value of the MemManage Fault Address Register.
+    pub mmfar: u32,
+    /// Value of the BusFault Address Register.
+    pub bfar: u32,
+    /// Exception number of the faulting context, taken from the IPSR bits of
+    /// the stacked xPSR. Zero if the fault occurred in Thread mode.
+    pub vectactive: u16,
+}
+
+impl CrashRecord {
+    /// Decodes the saved fault status registers.
+    pub fn report(&self) -> FaultReport {
+        super::decode(self.hfsr, self.cfsr, self.mmfar, self.bfar)
+    }
+
+    /// Restores the saved fault.
+    pub fn fault(&self) -> Fault {
+        Fault {
+            report: self.report(),
+            frame: self.frame,
+            exc_return: self.exc_return,
+            escalated: self.hfsr & super::HFSR_FORCED != 0,
+            hfsr: self.hfsr,
+            cfsr: self.cfsr,
+            mmfar: self.mmfar,
+            bfar: self.bfar,
+        }
+    }
+
+    fn to_words(&self) -> [u32; WORDS] {
+        let ExceptionFrame { r0, r1, r2, r3, r12, lr, pc, xpsr } = self.frame;
+        [
+            r0,
+            r1,
+            r2,
+            r3,
+            r12,
+            lr,
+            pc,
+            xpsr,
+            self.exc_return,
+            self.hfsr,
+            self.cfsr,
+            self.mmfar,
+            self.bfar,
+            u32::from(self.vectactive),
+        ]
+    }
+
+    fn from_words(words: &[u32; WORDS]) -> Self {
+        let &[r0, r1, r2, r3, r12, lr, pc, xpsr, exc_return, hfsr, cfsr, mmfar, bfar, vectactive] =
+            words;
+        Self {
+            frame: ExceptionFrame { r0, r1, r2, r3, r12, lr, pc, xpsr },
+            exc_return,
+            hfsr,
+            cfsr,
+            mmfar,
+            bfar,
+            vectactive: (vectactive & VECTACTIVE_MASK) as u16,
+        }
+    }
+}
+
+impl fmt::Display for CrashRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}\n  vectactive={}", self.fault(), self.vectactive)
+    }
+}
+
+/// Saves `fault` to the crash record, overwriting the previous one.
+pub fn save(fault: &Fault) {
+    let record = CrashRecord {
+        frame: fault.frame,
+        exc_return: fault.exc_return,
+        hfsr: fault.hfsr,
+        cfsr: fault.cfsr,
+        mmfar: fault.mmfar,
+        bfar: fault.bfar,
+        vectactive: (fault.frame.xpsr & VECTACTIVE_MASK) as u16,
+    };
+    let words = record.to_words();
+    let slot = Slot { magic: MAGIC, words, checksum: checksum(&words) };
+    unsafe { write_volatile(SLOT.as_mut_ptr(), slot) };
+}
+
+/// Saves `fault` to the crash record, then logs it and resets the processor.
+///
+/// This function can be used as a hook for [`set_handler!`](super::set_handler).
+pub fn save_and_reset(fault: &Fault) -> ! {
+    save(fault);
+    report_and_reset(fault)
+}
+
+/// Returns the saved crash record, if the record is present and valid.
+pub fn load() -> Option<CrashRecord> {
+    let slot = unsafe { read_volatile(SLOT.as_ptr()) };
+    if slot.magic != MAGIC || slot.checksum != checksum(&slot.words) {
+        return None;
+    }
+    Some(CrashRecord::from_words(&slot.words))
+}
+
+/// Invalidates the saved crash record.
+pub fn clear() {
+    unsafe { write_volatile(&mut (*SLOT.as_mut_ptr()).magic, 0) };
+}
+
+/// Returns the saved crash record, if the record is present and valid, and
+/// invalidates it.
+pub fn take() -> Option<CrashRecord> {
+    let record = load();
+    clear();
+    record
+}
+
+fn checksum(words: &[u32; WORDS]) -> u32 {
+    words.iter().fold(MAGIC, |sum, &word| (sum ^ word).wrapping_mul(0x0100_0193))
+}
@@ -4,6 +4,7 @@ use super::{Data, ProcData, Yielder};
 use crate::{
     fib::{Fiber, FiberRoot, FiberState},
     sv::Switch,
+    thr::stack,
 };
 use ::alloc::alloc;
 use core::{
@@ -14,6 +15,15 @@ use core::{
     pin::Pin,
 };
 
+#[cfg(feature = "memory-protection-unit")]
+use self::mpu::GUARD_LEN;
+#[cfg(all(feature = "memory-protection-unit", not(armv8m)))]
+use crate::drv::mpu::MpuRegion;
+
+/// Size of the MPU guard region.
+#[cfg(not(feature = "memory-protection-unit"))]
+const GUARD_LEN: usize = 0;
+
 /// Stackful fiber for [`FnMut`] closure.
 ///
 /// Can be created with [`fib::new_proc`](crate::fib::new_proc),
@@ -30,8 +40,10 @@ where
     R: Send + 'static,
 {
     stack_bottom: *mut u8,
+    stack_limit: *mut u8,
     stack_ptr: *const u8,
     stack_size: usize,
+    painted: bool,
     _f: PhantomData<*const F>,
     _sv: PhantomData<*const Sv>,
     _input: PhantomData<*const I>,
@@ -57,12 +69,14 @@ where
         if stack_bottom.is_null() {
             panic!("Stack allocation failure");
         }
-        let stack_ptr =
+        let (stack_ptr, stack_limit) =
             unsafe { Self::stack_init(stack_bottom, stack_size, unprivileged, unchecked, f) };
         Self {
             stack_bottom,
+            stack_limit,
             stack_ptr,
             stack_size,
+            painted: false,
             _f: PhantomData,
             _sv: PhantomData,
             _input: PhantomData,
@@ -77,7 +91,7 @@ where
         unprivileged: bool,
         unchecked: bool,
         f: F,
-    ) -> *const u8 {
+    ) -> (*const u8, *mut u8) {
         assert!(
             stack_size
                 >= size_of::<ProcData<I, Y, R>>()
@@ -125,10 +139,100 @@ where
             stack_ptr = stack_ptr.sub(1);
             stack_ptr.write(if unprivileged { 0b11 } else { 0b10 });
             // MPU CONFIG
+            let mpu_config = mpu_config(unchecked, stack_bottom);
             stack_ptr = stack_ptr.sub(1);
-            stack_ptr.write(mpu_config(unchecked, stack_bottom));
-            stack_ptr as *const u8
+            stack_ptr.write(mpu_config);
+            // The guard region starts at the MPU table.
+            let stack_limit = if mpu_config == 0 {
+                stack_bottom
+            } else {
+                (mpu_config as usize + GUARD_LEN) as *mut u8
+            };
+            (stack_ptr as *const u8, stack_limit)
+        }
+    }
+
+    /// Paints the unused part of the stack with a known pattern, which allows
+    /// querying the stack high-water mark with
+    /// [`stack_high_water_mark`](FiberProc::stack_high_water_mark).
+    ///
+    /// The painted area excludes the MPU guard region at the bottom of the
+    /// stack. Calling this method again resets the mark to the current stack
+    /// usage.
+    pub fn paint_stack(&mut self) {
+        let size = self.stack_ptr as usize - self.stack_limit as usize;
+        unsafe { stack::paint(self.stack_limit, size) };
+        self.painted = true;
+    }
+
+    /// Returns the maximum number of bytes of the stack that have been used
+    /// since the last [`paint_stack`](FiberProc::paint_stack) call, or `None`
+    /// if the stack is not painted.
+    pub fn stack_high_water_mark(&self) -> Option<usize> {
+        if !self.painted {
+            return None;
+        }
+        let size = self.stack_size - (self.stack_limit as usize - self.stack_bottom as usize);
+        Some(size - unsafe { stack::unused(self.stack_limit, size) })
+    }
+
+    /// Attaches `region` to the fiber as the MPU region `number`.
+    ///
+    /// The region is enabled each time the fiber is resumed, and disabled when
+    /// the fiber yields or completes. This allows giving an unprivileged fiber
+    /// access to its own RAM window and to the allowed peripherals only.
+    ///
+    /// Regions 1 to 3 are available, region 0 is the stack guard.
+    ///
+    /// # Panics
+    ///
+    /// * If `number` is not in the range from 1 to 3.
+    /// * If the fiber is created without memory protection.
+    #[cfg(all(feature = "memory-protection-unit", not(armv8m)))]
+    pub fn set_mpu_region(&mut self, number: u8, region: &MpuRegion) {
+        let (rbar, rasr) = (region.rbar(number), region.rasr());
+        let table = self.mpu_region_table(number);
+        unsafe { mpu::set_region(table, number, rbar, rasr) };
+    }
+
+    /// Detaches the MPU region `number` from the fiber.
+    ///
+    /// # Panics
+    ///
+    /// * If `number` is not in the range from 1 to 3.
+    /// * If the fiber is created without memory protection.
+    #[cfg(all(feature = "memory-protection-unit", not(armv8m)))]
+    pub fn clear_mpu_region(&mut self, number: u8) {
+        let table = self.mpu_region_table(number);
+        unsafe { mpu::set_region(table, number, mpu::rbar_disabled(number), 0) };
+    }
+
+    /// Returns the RBAR and RASR values of the MPU regions 0 to 3, which are
+    /// written to the MPU each time the fiber is resumed.
+    ///
+    /// # Panics
+    ///
+    /// If the fiber is created without memory protection.
+    #[cfg(all(feature = "memory-protection-unit", not(armv8m)))]
+    pub fn mpu_regions(&self) -> [(u32, u32); mpu::FIBER_REGIONS as usize] {
+        let table = self.mpu_table();
+        let mut regions = [(0, 0); mpu::FIBER_REGIONS as usize];
+        for (number, region) in regions.iter_mut().enumerate() {
+            *region = unsafe { (table.add(number * 2).read(), table.add(number * 2 + 1).read()) };
         }
+        regions
+    }
+
+    #[cfg(all(feature = "memory-protection-unit", not(armv8m)))]
+    fn mpu_region_table(&self, number: u8) -> *mut u32 {
+        assert!((1..mpu::FIBER_REGIONS).contains(&number), "fiber MPU region number out of range");
+        self.mpu_table()
+    }
+
+    #[cfg(all(feature = "memory-protection-unit", not(armv8m)))]
+    fn mpu_table(&self) -> *mut u32 {
+        assert!(self.stack_limit != self.stack_bottom, "fiber without memory protection");
+        unsafe { self.stack_limit.sub(GUARD_LEN) as *mut u32 }
     }
 
     unsafe fn stack_reserve<T>(mut stack_ptr: *mut u8) -> *mut u8 {
@@ -262,6 +366,11 @@ mod mpu {
 
     const GUARD_SIZE: u32 = 5;
 
+    pub(super) const GUARD_LEN: usize = 1 << GUARD_SIZE + 1;
+
+    /// Number of MPU regions switched on each fiber context switch.
+    pub(super) const FIBER_REGIONS: u8 = 4;
+
     pub(super) fn check() {
         #[cfg(feature = "std")]
         return;
@@ -270,10 +379,12 @@ mod mpu {
         }
     }
 
+    #[cfg(not(armv8m))]
     pub(super) fn guard_size() -> usize {
         1 + (1 << GUARD_SIZE + 1) + (1 << GUARD_SIZE + 1) - 1
     }
 
+    #[cfg(not(armv8m))]
     #[allow(clippy::cast_ptr_alignment)]
     pub(super) unsafe fn config(mut guard_ptr: *mut u8) -> u32 {
         unsafe {
@@ -300,18 +411,95 @@ mod mpu {
                     (1 << GUARD_SIZE + 1) - ((guard_ptr as usize) & (1 << GUARD_SIZE + 1) - 1),
                 );
             }
+            // The table is written to the MPU as 4 RBAR/RASR pairs, which
+            // target regions 0 to 3. Region 0 is the guard, regions 1 to 3 are
+            // disabled until set with `FiberProc::set_mpu_region`, and the
+            // other regions, e.g. the main stack guard, stay in effect.
+            let mut table_ptr = guard_ptr as *mut u32;
+            for region in 0..FIBER_REGIONS {
+                let (rbar, rasr) = if region == 0 {
+                    (rbar_bits(0, guard_ptr as u32), rasr_bits())
+                } else {
+                    (rbar_disabled(region), 0)
+                };
+                table_ptr.write(rbar);
+                table_ptr = table_ptr.add(1);
+                table_ptr.write(rasr);
+                table_ptr = table_ptr.add(1);
+            }
+            table_ptr.sub(usize::from(FIBER_REGIONS) * 2) as u32
+        }
+    }
+
+    #[cfg(not(armv8m))]
+    pub(super) fn rbar_disabled(region: u8) -> u32 {
+        unsafe { mpu::Rbar::<Srt>::take() }
+            .default()
+            .set_valid()
+            .write_region(u32::from(region))
+            .val()
+            .bits()
+    }
+
+    #[cfg(not(armv8m))]
+    #[allow(clippy::cast_ptr_alignment)]
+    pub(super) unsafe fn set_region(table_ptr: *mut u32, number: u8, rbar: u32, rasr: u32) {
+        unsafe {
+            let pair_ptr = table_ptr.add(usize::from(number) * 2);
+            pair_ptr.write(rbar);
+            pair_ptr.add(1).write(rasr);
+        }
+    }
+
+    #[cfg(armv8m)]
+    pub(super) fn guard_size() -> usize {
+        1 + (1 << GUARD_SIZE + 1) + (1 << 5) - 1
+    }
+
+    #[cfg(armv8m)]
+    #[allow(clippy::cast_ptr_alignment)]
+    pub(super) unsafe fn config(mut guard_ptr: *mut u8) -> u32 {
+        unsafe {
+            // PMSAv8 regions are aligned to 32 bytes, and have no no-access
+            // permission. The guard is read-only for privileged code, which
+            // faults on stack pushes.
+            let rbar_bits = |addr| {
+                mpu::Rbar::<Srt>::take()
+                    .default()
+                    .write_base(addr >> 5)
+                    .write_ap(0b10)
+                    .set_xn()
+                    .val()
+                    .bits()
+            };
+            // Attribute 0 of MAIR0 is programmed by `thr::init`.
+            let rlar_bits = |addr| {
+                mpu::Rlar::<Srt>::take()
+                    .default()
+                    .write_limit(addr >> 5)
+                    .write_attrindx(0)
+                    .set_en()
+                    .val()
+                    .bits()
+            };
+            if (guard_ptr as usize).trailing_zeros() < 5 {
+                guard_ptr = guard_ptr.add((1 << 5) - ((guard_ptr as usize) & (1 << 5) - 1));
+            }
+            // The table is written to RBAR/RLAR and their three aliases, i.e.
+            // to regions 0 to 3. Region 0 is the guard, regions 1 to 3 are
+            // disabled, and the other regions, e.g. the main stack guard, stay
+            // in effect.
+            let guard =
+                (rbar_bits(guard_ptr as u32), rlar_bits(guard_ptr as u32 + GUARD_LEN as u32 - 1));
             let mut table_ptr = guard_ptr as *mut u32;
-            table_ptr.write(rbar_bits(0, guard_ptr as u32));
-            table_ptr = table_ptr.add(1);
-            table_ptr.write(rasr_bits());
-            table_ptr = table_ptr.add(1);
-            for i in 1..8 {
-                table_ptr.write(rbar_bits(i, 0));
+            for region in 0..FIBER_REGIONS {
+                let (rbar, rlar) = if region == 0 { guard } else { (0, 0) };
+                table_ptr.write(rbar);
                 table_ptr = table_ptr.add(1);
-                table_ptr.write(0);
+                table_ptr.write(rlar);
                 table_ptr = table_ptr.add(1);
             }
-            table_ptr.sub(16) as u32
+            table_ptr.sub(usize::from(FIBER_REGIONS) * 2) as u32
         }
     }
 }
@@ -44,11 +44,13 @@
 //! std = ["drone-cortexm/std"]
 //! ```
 
+#![feature(allow_internal_unstable)]
 #![feature(const_fn)]
 #![feature(core_intrinsics)]
 #![feature(exhaustive_patterns)]
 #![feature(llvm_asm)]
 #![feature(marker_trait_attr)]
+#![feature(min_const_generics)]
 #![feature(naked_functions)]
 #![feature(never_type)]
 #![feature(never_type_fallback)]
@@ -73,17 +75,26 @@
 
 extern crate alloc;
 
+#[cfg(feature = "security-extension")]
+pub mod cmse;
 pub mod drv;
+pub mod fault;
 pub mod fib;
 pub mod map;
 pub mod proc_loop;
 pub mod processor;
 pub mod reg;
+pub mod rtt;
+pub mod semihosting;
+#[cfg(feature = "std")]
+pub mod sim;
 pub mod sv;
 pub mod swo;
+pub mod test;
 pub mod thr;
 
 mod rt;
+mod util;
 
 mod drone_core_macro_reexport {
     pub use drone_core::{reg, thr};
@@ -103,6 +114,12 @@ pub use drone_cortexm_macros::thr;
 #[doc(inline)]
 pub use drone_cortexm_macros::sv;
 
+/// Defines an on-target test suite.
+///
+/// See [the module level documentation](mod@test) for details.
+#[doc(inline)]
+pub use drone_cortexm_macros::test;
+
 #[prelude_import]
 #[allow(unused_imports)]
 use drone_core::prelude::*;
@@ -0,0 +1,91 @@
+//! Data Watchpoint and Trace unit.
+
+use drone_core::periph;
+
+periph::singular! {
+    #[doc(hidden)]
+    pub macro periph_dwt_inner;
+
+    /// DWT cycle counter peripheral.
+    pub struct DwtPeriph;
+
+    crate::map::reg;
+    crate::map::periph::dwt;
+
+    SCB {
+        DEMCR {
+            TRCENA;
+        }
+    }
+
+    DWT {
+        CTRL {
+            CYCCNTENA;
+        }
+        CYCCNT;
+    }
+}
+
+// Workaround the `macro_expanded_macro_exports_accessed_by_absolute_paths`
+// error.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! periph_dwt {
+    ($($tt:tt)*) => {
+        $crate::periph_dwt_inner!($($tt)*);
+    };
+}
+
+/// Extracts DWT cycle counter register tokens.
+#[doc(inline)]
+pub use crate::periph_dwt;
+
+periph::singular! {
+    #[doc(hidden)]
+    pub macro periph_dwt_watch_inner;
+
+    /// DWT comparators peripheral.
+    pub struct DwtWatchPeriph;
+
+    crate::map::reg;
+    crate::map::periph::dwt;
+
+    SCB {
+        DEMCR {
+            MON_EN;
+        }
+        DFSR;
+    }
+
+    DWT {
+        CTRL {
+            NUMCOMP;
+        }
+        COMP0;
+        MASK0;
+        FUNCTION0;
+        COMP1;
+        MASK1;
+        FUNCTION1;
+        COMP2;
+        MASK2;
+        FUNCTION2;
+        COMP3;
+        MASK3;
+        FUNCTION3;
+    }
+}
+
+// Workaround the `macro_expanded_macro_exports_accessed_by_absolute_paths`
+// error.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! periph_dwt_watch {
+    ($($tt:tt)*) => {
+        $crate::periph_dwt_watch_inner!($($tt)*);
+    };
+}
+
+/// Extracts DWT comparator register tokens.
+#[doc(inline)]
+pub use crate::periph_dwt_watch;
@@ -1,5 +1,10 @@
 //! Core ARM Cortex-M peripheral mappings.
 
+pub mod dwt;
 #[cfg(feature = "floating-point-unit")]
 pub mod fpu;
+#[cfg(all(feature = "memory-protection-unit", not(armv8m)))]
+pub mod mpu;
+#[cfg(feature = "security-extension")]
+pub mod sau;
 pub mod sys_tick;
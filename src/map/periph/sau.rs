@@ -0,0 +1,36 @@
+//! Security Attribution Unit.
+
+use drone_core::periph;
+
+periph::singular! {
+    #[doc(hidden)]
+    pub macro periph_sau_inner;
+
+    /// SAU peripheral.
+    pub struct SauPeriph;
+
+    crate::map::reg;
+    crate::map::periph::sau;
+
+    SAU {
+        CTRL;
+        TYPE;
+        RNR;
+        RBAR;
+        RLAR;
+    }
+}
+
+// Workaround the `macro_expanded_macro_exports_accessed_by_absolute_paths`
+// error.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! periph_sau {
+    ($($tt:tt)*) => {
+        $crate::periph_sau_inner!($($tt)*);
+    };
+}
+
+/// Extracts SAU register tokens.
+#[doc(inline)]
+pub use crate::periph_sau;
@@ -69,3 +69,255 @@ reg! {
         };
     };
 }
+
+reg! {
+    /// Comparator Register 0.
+    pub DWT COMP0 => {
+        address => 0xE000_1020;
+        size => 0x20;
+        reset => 0x0000_0000;
+        traits => { RReg WReg };
+        fields => {
+            /// Reference value for comparison.
+            COMP => { offset => 0; width => 32; traits => { RRRegField WWRegField } };
+        };
+    };
+}
+
+reg! {
+    /// Comparator Mask Register 0.
+    pub DWT MASK0 => {
+        address => 0xE000_1024;
+        size => 0x20;
+        reset => 0x0000_0000;
+        traits => { RReg WReg };
+        fields => {
+            /// The size of the ignore mask applied to the access address for
+            /// address range matching by comparator 0.
+            MASK => { offset => 0; width => 5; traits => { RRRegField WWRegField } };
+        };
+    };
+}
+
+reg! {
+    /// Comparator Function Register 0.
+    pub DWT FUNCTION0 => {
+        address => 0xE000_1028;
+        size => 0x20;
+        reset => 0x0000_0000;
+        traits => { RReg WReg };
+        fields => {
+            /// Comparator match. It indicates that the operation defined by
+            /// FUNCTION has occurred since the last read of the register.
+            MATCHED => { offset => 24; width => 1; traits => { RRRegField } };
+            /// Identity of a second linked address comparator for data value
+            /// matching.
+            DATAVADDR1 => { offset => 16; width => 4; traits => { RRRegField WWRegField } };
+            /// Identity of a linked address comparator for data value matching.
+            DATAVADDR0 => { offset => 12; width => 4; traits => { RRRegField WWRegField } };
+            /// Defines the size of the data in the COMP register that is to be
+            /// matched.
+            DATAVSIZE => { offset => 10; width => 2; traits => { RRRegField WWRegField } };
+            /// Indicates whether the implementation supports use of a second
+            /// linked comparator.
+            LNK1ENA => { offset => 9; width => 1; traits => { RRRegField } };
+            /// Enables data value comparison.
+            DATAVMATCH => { offset => 8; width => 1; traits => { RRRegField WWRegField } };
+            /// Enables cycle count comparison. Supported only by comparator 0.
+            CYCMATCH => { offset => 7; width => 1; traits => { RRRegField WWRegField } };
+            /// Enables generation of Data trace address offset packets.
+            EMITRANGE => { offset => 5; width => 1; traits => { RRRegField WWRegField } };
+            /// Selects action taken on comparator match.
+            FUNCTION => { offset => 0; width => 4; traits => { RRRegField WWRegField } };
+        };
+    };
+}
+
+reg! {
+    /// Comparator Register 1.
+    pub DWT COMP1 => {
+        address => 0xE000_1030;
+        size => 0x20;
+        reset => 0x0000_0000;
+        traits => { RReg WReg };
+        fields => {
+            /// Reference value for comparison.
+            COMP => { offset => 0; width => 32; traits => { RRRegField WWRegField } };
+        };
+    };
+}
+
+reg! {
+    /// Comparator Mask Register 1.
+    pub DWT MASK1 => {
+        address => 0xE000_1034;
+        size => 0x20;
+        reset => 0x0000_0000;
+        traits => { RReg WReg };
+        fields => {
+            /// The size of the ignore mask applied to the access address for
+            /// address range matching by comparator 1.
+            MASK => { offset => 0; width => 5; traits => { RRRegField WWRegField } };
+        };
+    };
+}
+
+reg! {
+    /// Comparator Function Register 1.
+    pub DWT FUNCTION1 => {
+        address => 0xE000_1038;
+        size => 0x20;
+        reset => 0x0000_0000;
+        traits => { RReg WReg };
+        fields => {
+            /// Comparator match. It indicates that the operation defined by
+            /// FUNCTION has occurred since the last read of the register.
+            MATCHED => { offset => 24; width => 1; traits => { RRRegField } };
+            /// Identity of a second linked address comparator for data value
+            /// matching.
+            DATAVADDR1 => { offset => 16; width => 4; traits => { RRRegField WWRegField } };
+            /// Identity of a linked address comparator for data value matching.
+            DATAVADDR0 => { offset => 12; width => 4; traits => { RRRegField WWRegField } };
+            /// Defines the size of the data in the COMP register that is to be
+            /// matched.
+            DATAVSIZE => { offset => 10; width => 2; traits => { RRRegField WWRegField } };
+            /// Indicates whether the implementation supports use of a second
+            /// linked comparator.
+            LNK1ENA => { offset => 9; width => 1; traits => { RRRegField } };
+            /// Enables data value comparison.
+            DATAVMATCH => { offset => 8; width => 1; traits => { RRRegField WWRegField } };
+            /// Enables cycle count comparison. Supported only by comparator 0.
+            CYCMATCH => { offset => 7; width => 1; traits => { RRRegField WWRegField } };
+            /// Enables generation of Data trace address offset packets.
+            EMITRANGE => { offset => 5; width => 1; traits => { RRRegField WWRegField } };
+            /// Selects action taken on comparator match.
+            FUNCTION => { offset => 0; width => 4; traits => { RRRegField WWRegField } };
+        };
+    };
+}
+
+reg! {
+    /// Comparator Register 2.
+    pub DWT COMP2 => {
+        address => 0xE000_1040;
+        size => 0x20;
+        reset => 0x0000_0000;
+        traits => { RReg WReg };
+        fields => {
+            /// Reference value for comparison.
+            COMP => { offset => 0; width => 32; traits => { RRRegField WWRegField } };
+        };
+    };
+}
+
+reg! {
+    /// Comparator Mask Register 2.
+    pub DWT MASK2 => {
+        address => 0xE000_1044;
+        size => 0x20;
+        reset => 0x0000_0000;
+        traits => { RReg WReg };
+        fields => {
+            /// The size of the ignore mask applied to the access address for
+            /// address range matching by comparator 2.
+            MASK => { offset => 0; width => 5; traits => { RRRegField WWRegField } };
+        };
+    };
+}
+
+reg! {
+    /// Comparator Function Register 2.
+    pub DWT FUNCTION2 => {
+        address => 0xE000_1048;
+        size => 0x20;
+        reset => 0x0000_0000;
+        traits => { RReg WReg };
+        fields => {
+            /// Comparator match. It indicates that the operation defined by
+            /// FUNCTION has occurred since the last read of the register.
+            MATCHED => { offset => 24; width => 1; traits => { RRRegField } };
+            /// Identity of a second linked address comparator for data value
+            /// matching.
+            DATAVADDR1 => { offset => 16; width => 4; traits => { RRRegField WWRegField } };
+            /// Identity of a linked address comparator for data value matching.
+            DATAVADDR0 => { offset => 12; width => 4; traits => { RRRegField WWRegField } };
+            /// Defines the size of the data in the COMP register that is to be
+            /// matched.
+            DATAVSIZE => { offset => 10; width => 2; traits => { RRRegField WWRegField } };
+            /// Indicates whether the implementation supports use of a second
+            /// linked comparator.
+            LNK1ENA => { offset => 9; width => 1; traits => { RRRegField } };
+            /// Enables data value comparison.
+            DATAVMATCH => { offset => 8; width => 1; traits => { RRRegField WWRegField } };
+            /// Enables cycle count comparison. Supported only by comparator 0.
+            CYCMATCH => { offset => 7; width => 1; traits => { RRRegField WWRegField } };
+            /// Enables generation of Data trace address offset packets.
+            EMITRANGE => { offset => 5; width => 1; traits => { RRRegField WWRegField } };
+            /// Selects action taken on comparator match.
+            FUNCTION => { offset => 0; width => 4; traits => { RRRegField WWRegField } };
+        };
+    };
+}
+
+reg! {
+    /// Comparator Register 3.
+    pub DWT COMP3 => {
+        address => 0xE000_1050;
+        size => 0x20;
+        reset => 0x0000_0000;
+        traits => { RReg WReg };
+        fields => {
+            /// Reference value for comparison.
+            COMP => { offset => 0; width => 32; traits => { RRRegField WWRegField } };
+        };
+    };
+}
+
+reg! {
+    /// Comparator Mask Register 3.
+    pub DWT MASK3 => {
+        address => 0xE000_1054;
+        size => 0x20;
+        reset => 0x0000_0000;
+        traits => { RReg WReg };
+        fields => {
+            /// The size of the ignore mask applied to the access address for
+            /// address range matching by comparator 3.
+            MASK => { offset => 0; width => 5; traits => { RRRegField WWRegField } };
+        };
+    };
+}
+
+reg! {
+    /// Comparator Function Register 3.
+    pub DWT FUNCTION3 => {
+        address => 0xE000_1058;
+        size => 0x20;
+        reset => 0x0000_0000;
+        traits => { RReg WReg };
+        fields => {
+            /// Comparator match. It indicates that the operation defined by
+            /// FUNCTION has occurred since the last read of the register.
+            MATCHED => { offset => 24; width => 1; traits => { RRRegField } };
+            /// Identity of a second linked address comparator for data value
+            /// matching.
+            DATAVADDR1 => { offset => 16; width => 4; traits => { RRRegField WWRegField } };
+            /// Identity of a linked address comparator for data value matching.
+            DATAVADDR0 => { offset => 12; width => 4; traits => { RRRegField WWRegField } };
+            /// Defines the size of the data in the COMP register that is to be
+            /// matched.
+            DATAVSIZE => { offset => 10; width => 2; traits => { RRRegField WWRegField } };
+            /// Indicates whether the implementation supports use of a second
+            /// linked comparator.
+            LNK1ENA => { offset => 9; width => 1; traits => { RRRegField } };
+            /// Enables data value comparison.
+            DATAVMATCH => { offset => 8; width => 1; traits => { RRRegField WWRegField } };
+            /// Enables cycle count comparison. Supported only by comparator 0.
+            CYCMATCH => { offset => 7; width => 1; traits => { RRRegField WWRegField } };
+            /// Enables generation of Data trace address offset packets.
+            EMITRANGE => { offset => 5; width => 1; traits => { RRRegField WWRegField } };
+            /// Selects action taken on comparator match.
+            FUNCTION => { offset => 0; width => 4; traits => { RRRegField WWRegField } };
+        };
+    };
+}
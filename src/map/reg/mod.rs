@@ -6,16 +6,24 @@ mod inner {
     #[cfg(feature = "floating-point-unit")]
     mod fpu;
     mod itm;
-    #[cfg(feature = "memory-protection-unit")]
+    #[cfg(all(feature = "memory-protection-unit", not(armv8m)))]
     mod mpu;
+    #[cfg(all(feature = "memory-protection-unit", armv8m))]
+    mod pmsav8;
+    #[cfg(feature = "security-extension")]
+    mod sau;
     mod scb;
     mod stk;
     mod tpiu;
 
     #[cfg(feature = "floating-point-unit")]
     pub use self::fpu::*;
-    #[cfg(feature = "memory-protection-unit")]
+    #[cfg(all(feature = "memory-protection-unit", not(armv8m)))]
     pub use self::mpu::*;
+    #[cfg(all(feature = "memory-protection-unit", armv8m))]
+    pub use self::pmsav8::*;
+    #[cfg(feature = "security-extension")]
+    pub use self::sau::*;
     pub use self::{dwt::*, itm::*, scb::*, stk::*, tpiu::*};
 }
 
@@ -29,7 +37,8 @@ reg::tokens! {
 
     /// Data watchpoint and trace.
     pub mod DWT {
-        CYCCNT;
+        CTRL; CYCCNT; COMP0; MASK0; FUNCTION0; COMP1; MASK1; FUNCTION1; COMP2; MASK2;
+        FUNCTION2; COMP3; MASK3; FUNCTION3;
     }
 
     /// Instrumentation trace macrocell.
@@ -55,11 +64,23 @@ reg::tokens! {
     }
 
     /// Memory protection unit.
-    #[cfg(feature = "memory-protection-unit")]
+    #[cfg(all(feature = "memory-protection-unit", not(armv8m)))]
     pub mod MPU {
         TYPE; CTRL; RNR; RBAR; RASR;
     }
 
+    /// Memory protection unit.
+    #[cfg(all(feature = "memory-protection-unit", armv8m))]
+    pub mod MPU {
+        TYPE; CTRL; RNR; RBAR; RLAR; MAIR0; MAIR1;
+    }
+
+    /// Security attribution unit.
+    #[cfg(feature = "security-extension")]
+    pub mod SAU {
+        CTRL; TYPE; RNR; RBAR; RLAR; SFSR; SFAR;
+    }
+
     /// Trace port interface unit.
     pub mod TPIU {
         ACPR; SPPR; FFCR;
@@ -0,0 +1,139 @@
+use crate::reg::prelude::*;
+use drone_core::reg;
+
+reg! {
+    /// The MPU Type Register indicates how many regions the MPU support.
+    /// Software can use it to determine if the processor implements an MPU.
+    pub MPU TYPE => {
+        address => 0xE000_ED90;
+        size => 0x20;
+        reset => 0x0000_0000;
+        traits => { RReg RoReg };
+        fields => {
+            /// Instruction region.
+            IREGION => { offset => 16; width => 8; traits => { RRRegField RoRRegField } };
+            /// Number of regions supported by the MPU. If this field reads-as-zero the
+            /// processor does not implement an MPU.
+            DREGION => { offset => 8; width => 8; traits => { RRRegField RoRRegField } };
+            /// Indicates support for separate instruction and data address maps.
+            SEPARATE => { offset => 0; width => 1; traits => { RRRegField RoRRegField } };
+        };
+    };
+}
+
+reg! {
+    /// Enables the MPU, and when the MPU is enabled, controls whether the
+    /// default memory map is enabled as a background region for privileged
+    /// accesses, and whether the MPU is enabled for HardFaults, NMIs, and
+    /// exception handlers when FAULTMASK is set to 1.
+    pub MPU CTRL => {
+        address => 0xE000_ED94;
+        size => 0x20;
+        reset => 0x0000_0000;
+        traits => { RReg WReg };
+        fields => {
+            /// Enables the default memory map as a background region for privileged
+            /// access.
+            PRIVDEFENA => { offset => 2; width => 1; traits => { RRRegField WWRegField } };
+            /// Enables the operation of MPU during hard fault, NMI, and FAULTMASK
+            /// handlers.
+            HFNMIENA => { offset => 1; width => 1; traits => { RRRegField WWRegField } };
+            /// Enables the MPU.
+            ENABLE => { offset => 0; width => 1; traits => { RRRegField WWRegField } };
+        };
+    };
+}
+
+reg! {
+    /// Selects the region currently accessed by RBAR and RLAR.
+    pub MPU RNR => {
+        address => 0xE000_ED98;
+        size => 0x20;
+        reset => 0x0000_0000;
+        traits => { RReg WReg };
+        fields => {
+            /// Indicates the memory region accessed by RBAR and RLAR.
+            REGION => { offset => 0; width => 8; traits => { RRRegField WWRegField } };
+        };
+    };
+}
+
+reg! {
+    /// Holds the base address, the shareability, the access permissions, and
+    /// the execute-never attribute of the region identified by RNR.
+    pub MPU RBAR => {
+        address => 0xE000_ED9C;
+        size => 0x20;
+        reset => 0x0000_0000;
+        traits => { RReg WReg };
+        fields => {
+            /// Bits [31:5] of the lower inclusive limit of the region.
+            BASE => { offset => 5; width => 27; traits => { RRRegField WWRegField } };
+            /// Shareability.
+            SH => { offset => 3; width => 2; traits => { RRRegField WWRegField } };
+            /// Access permissions.
+            AP => { offset => 1; width => 2; traits => { RRRegField WWRegField } };
+            /// Execute never.
+            XN => { offset => 0; width => 1; traits => { RRRegField WWRegField } };
+        };
+    };
+}
+
+reg! {
+    /// Holds the limit address, the memory attributes index, and the enable
+    /// bit of the region identified by RNR.
+    pub MPU RLAR => {
+        address => 0xE000_EDA0;
+        size => 0x20;
+        reset => 0x0000_0000;
+        traits => { RReg WReg };
+        fields => {
+            /// Bits [31:5] of the upper inclusive limit of the region.
+            LIMIT => { offset => 5; width => 27; traits => { RRRegField WWRegField } };
+            /// Index of the attribute in MAIR0 or MAIR1.
+            ATTRINDX => { offset => 1; width => 3; traits => { RRRegField WWRegField } };
+            /// Region enable bit.
+            EN => { offset => 0; width => 1; traits => { RRRegField WWRegField } };
+        };
+    };
+}
+
+reg! {
+    /// Memory attribute indirection register 0.
+    pub MPU MAIR0 => {
+        address => 0xE000_EDC0;
+        size => 0x20;
+        reset => 0x0000_0000;
+        traits => { RReg WReg };
+        fields => {
+            /// Memory attribute encoding for regions with attribute index 3.
+            ATTR3 => { offset => 24; width => 8; traits => { RRRegField WWRegField } };
+            /// Memory attribute encoding for regions with attribute index 2.
+            ATTR2 => { offset => 16; width => 8; traits => { RRRegField WWRegField } };
+            /// Memory attribute encoding for regions with attribute index 1.
+            ATTR1 => { offset => 8; width => 8; traits => { RRRegField WWRegField } };
+            /// Memory attribute encoding for regions with attribute index 0.
+            ATTR0 => { offset => 0; width => 8; traits => { RRRegField WWRegField } };
+        };
+    };
+}
+
+reg! {
+    /// Memory attribute indirection register 1.
+    pub MPU MAIR1 => {
+        address => 0xE000_EDC4;
+        size => 0x20;
+        reset => 0x0000_0000;
+        traits => { RReg WReg };
+        fields => {
+            /// Memory attribute encoding for regions with attribute index 7.
+            ATTR7 => { offset => 24; width => 8; traits => { RRRegField WWRegField } };
+            /// Memory attribute encoding for regions with attribute index 6.
+            ATTR6 => { offset => 16; width => 8; traits => { RRRegField WWRegField } };
+            /// Memory attribute encoding for regions with attribute index 5.
+            ATTR5 => { offset => 8; width => 8; traits => { RRRegField WWRegField } };
+            /// Memory attribute encoding for regions with attribute index 4.
+            ATTR4 => { offset => 0; width => 8; traits => { RRRegField WWRegField } };
+        };
+    };
+}
@@ -0,0 +1,128 @@
+use crate::reg::prelude::*;
+use drone_core::reg;
+
+reg! {
+    /// Allows enabling of the Security Attribution Unit.
+    pub SAU CTRL => {
+        address => 0xE000_EDD0;
+        size => 0x20;
+        reset => 0x0000_0000;
+        traits => { RReg WReg };
+        fields => {
+            /// When SAU_CTRL.ENABLE is 0 this bit controls if the memory is marked as
+            /// Non-secure or Secure.
+            ALLNS => { offset => 1; width => 1; traits => { RRRegField WWRegField } };
+            /// Enables the SAU.
+            ENABLE => { offset => 0; width => 1; traits => { RRRegField WWRegField } };
+        };
+    };
+}
+
+reg! {
+    /// Indicates the number of regions implemented by the Security Attribution
+    /// Unit.
+    pub SAU TYPE => {
+        address => 0xE000_EDD4;
+        size => 0x20;
+        reset => 0x0000_0000;
+        traits => { RReg RoReg };
+        fields => {
+            /// SAU regions. The number of implemented SAU regions.
+            SREGION => { offset => 0; width => 8; traits => { RRRegField RoRRegField } };
+        };
+    };
+}
+
+reg! {
+    /// Selects the region currently accessed by SAU_RBAR and SAU_RLAR.
+    pub SAU RNR => {
+        address => 0xE000_EDD8;
+        size => 0x20;
+        reset => 0x0000_0000;
+        traits => { RReg WReg };
+        fields => {
+            /// Indicates the SAU region accessed by SAU_RBAR and SAU_RLAR.
+            REGION => { offset => 0; width => 8; traits => { RRRegField WWRegField } };
+        };
+    };
+}
+
+reg! {
+    /// Provides indirect read and write access to the base address of the
+    /// currently selected SAU region.
+    pub SAU RBAR => {
+        address => 0xE000_EDDC;
+        size => 0x20;
+        reset => 0x0000_0000;
+        traits => { RReg WReg };
+        fields => {
+            /// Base address. Holds bits [31:5] of the base address for the selected SAU
+            /// region.
+            BADDR => { offset => 5; width => 27; traits => { RRRegField WWRegField } };
+        };
+    };
+}
+
+reg! {
+    /// Provides indirect read and write access to the limit address of the
+    /// currently selected SAU region.
+    pub SAU RLAR => {
+        address => 0xE000_EDE0;
+        size => 0x20;
+        reset => 0x0000_0000;
+        traits => { RReg WReg };
+        fields => {
+            /// Limit address. Holds bits [31:5] of the limit address for the selected
+            /// SAU region.
+            LADDR => { offset => 5; width => 27; traits => { RRRegField WWRegField } };
+            /// Non-secure callable. Controls whether Non-secure state is permitted to
+            /// execute an SG instruction from this region.
+            NSC => { offset => 1; width => 1; traits => { RRRegField WWRegField } };
+            /// Enable. SAU region enable.
+            ENABLE => { offset => 0; width => 1; traits => { RRRegField WWRegField } };
+        };
+    };
+}
+
+reg! {
+    /// Provides information about any security related faults.
+    pub SAU SFSR => {
+        address => 0xE000_EDE4;
+        size => 0x20;
+        reset => 0x0000_0000;
+        traits => { RReg WReg };
+        fields => {
+            /// Lazy state error flag.
+            LSERR => { offset => 7; width => 1; traits => { RRRegField WWRegField } };
+            /// Secure fault address valid.
+            SFARVALID => { offset => 6; width => 1; traits => { RRRegField WWRegField } };
+            /// Lazy state preservation error flag.
+            LSPERR => { offset => 5; width => 1; traits => { RRRegField WWRegField } };
+            /// Invalid transition flag.
+            INVTRAN => { offset => 4; width => 1; traits => { RRRegField WWRegField } };
+            /// Attribution unit violation flag.
+            AUVIOL => { offset => 3; width => 1; traits => { RRRegField WWRegField } };
+            /// Invalid exception return flag.
+            INVER => { offset => 2; width => 1; traits => { RRRegField WWRegField } };
+            /// Invalid integrity signature flag.
+            INVIS => { offset => 1; width => 1; traits => { RRRegField WWRegField } };
+            /// Invalid entry point.
+            INVEP => { offset => 0; width => 1; traits => { RRRegField WWRegField } };
+        };
+    };
+}
+
+reg! {
+    /// Shows the address of the memory location that caused a Security
+    /// violation.
+    pub SAU SFAR => {
+        address => 0xE000_EDE8;
+        size => 0x20;
+        reset => 0x0000_0000;
+        traits => { RReg WReg };
+        fields => {
+            /// The address of an access that caused an attribution unit violation.
+            ADDRESS => { offset => 0; width => 32; traits => { RRRegField WWRegField } };
+        };
+    };
+}
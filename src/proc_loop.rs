@@ -269,6 +269,24 @@ where
         unsafe { Self::new_with(fib::new_proc_unchecked) }
     }
 
+    /// Paints the unused part of the stack with a known pattern. See
+    /// [`fib::FiberProc::paint_stack`] for details.
+    ///
+    /// Together with [`stack_high_water_mark`](Fiber::stack_high_water_mark),
+    /// this allows tuning [`ProcLoop::STACK_SIZE`] from real measurements.
+    #[inline]
+    pub fn paint_stack(&mut self) {
+        self.0.paint_stack();
+    }
+
+    /// Returns the maximum number of bytes of the stack that have been used
+    /// since the last [`paint_stack`](Fiber::paint_stack) call, or `None` if
+    /// the stack is not painted.
+    #[inline]
+    pub fn stack_high_water_mark(&self) -> Option<usize> {
+        self.0.stack_high_water_mark()
+    }
+
     unsafe fn new_with(f: unsafe fn(usize, CmdLoop<Sv, T>) -> InnerFiber<Sv, T>) -> Self {
         T::on_create();
         Self(unsafe { f(T::STACK_SIZE, Self::cmd_loop) })
@@ -1,4 +1,8 @@
 //! Common utility functions for working with ARM Cortex-M processors.
+//!
+//! With the `std` feature, the hint instructions are no-ops, and
+//! [`self_reset`] panics after requesting the reset through the
+//! [simulated](crate::sim) `SCB_AIRCR` register.
 
 #![cfg_attr(feature = "std", allow(unreachable_code, unused_mut))]
 
@@ -10,7 +14,7 @@
 #[inline]
 pub fn wait_for_int() {
     #[cfg(feature = "std")]
-    return unimplemented!();
+    return;
     unsafe { llvm_asm!("wfi" :::: "volatile") };
 }
 
@@ -25,7 +29,7 @@ pub fn wait_for_int() {
 #[inline]
 pub fn wait_for_event() {
     #[cfg(feature = "std")]
-    return unimplemented!();
+    return;
     unsafe { llvm_asm!("wfe" :::: "volatile") };
 }
 
@@ -38,10 +42,22 @@ pub fn wait_for_event() {
 #[inline]
 pub fn send_event() {
     #[cfg(feature = "std")]
-    return unimplemented!();
+    return;
     unsafe { llvm_asm!("sev" :::: "volatile") };
 }
 
+/// Waits for preceding memory accesses and flushes the pipeline.
+///
+/// Issues the `DSB` and `ISB` instructions, so the effects of preceding writes
+/// to system registers, such as pending an interrupt or changing its priority,
+/// are visible to the following instructions.
+#[inline]
+pub fn barrier() {
+    #[cfg(feature = "std")]
+    return;
+    unsafe { llvm_asm!("dsb\nisb" :::: "volatile") };
+}
+
 /// Requests system reset.
 ///
 /// Generates a system reset request to the microcontroller's system reset
@@ -52,11 +68,15 @@ pub fn send_event() {
 #[allow(clippy::empty_loop)]
 #[inline]
 pub fn self_reset() -> ! {
+    use crate::{map::reg::scb, reg::prelude::*};
+    use drone_core::token::Token;
     #[cfg(feature = "std")]
-    return unimplemented!();
+    {
+        unsafe { scb::Aircr::<Urt>::take() }
+            .store(|r| r.write_vectkey(0x05FA).set_sysresetreq());
+        panic!("self reset requested");
+    }
     unsafe {
-        use crate::{map::reg::scb, reg::prelude::*};
-        use drone_core::token::Token;
         llvm_asm!("
             dmb
             cpsid f
@@ -71,11 +91,15 @@ pub fn self_reset() -> ! {
 }
 
 /// Spins the `cycles` number of processor cycles in a loop.
+///
+/// The actual delay depends on the code alignment, the flash wait states, and
+/// interrupts. See [`Dwt::delay_cycles`](crate::drv::dwt::Dwt::delay_cycles)
+/// for a cycle-accurate delay.
 #[allow(unused_assignments, unused_variables)]
 #[inline(always)]
 pub fn spin(mut cycles: u32) {
     #[cfg(feature = "std")]
-    return unimplemented!();
+    return;
     unsafe {
         llvm_asm!("
         0:
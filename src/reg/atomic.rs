@@ -211,7 +211,7 @@ macro_rules! atomic_bits {
         impl AtomicBits for $type {
             unsafe fn load_excl(address: usize) -> Self {
                 #[cfg(feature = "std")]
-                return unimplemented!();
+                return unsafe { core::ptr::read_volatile(address as *const Self) };
                 let raw: Self;
                 llvm_asm!($ldrex
                      : "=r"(raw)
@@ -224,7 +224,10 @@ macro_rules! atomic_bits {
 
             unsafe fn store_excl(self, address: usize) -> bool {
                 #[cfg(feature = "std")]
-                return unimplemented!();
+                {
+                    unsafe { core::ptr::write_volatile(address as *mut Self, self) };
+                    return true;
+                }
                 let status: Self;
                 llvm_asm!($strex
                      : "=r"(status)
@@ -1,6 +1,22 @@
 use crate::processor;
+use core::{
+    mem::transmute,
+    ptr,
+    sync::atomic::{AtomicPtr, Ordering},
+};
+
+static SELF_RESET_HOOK: AtomicPtr<()> = AtomicPtr::new(ptr::null_mut());
+
+/// Sets `hook` to be called by `drone_self_reset` before the reset.
+pub(crate) fn set_self_reset_hook(hook: fn()) {
+    SELF_RESET_HOOK.store(hook as *mut (), Ordering::SeqCst);
+}
 
 #[no_mangle]
 extern "C" fn drone_self_reset() -> ! {
+    let hook = SELF_RESET_HOOK.load(Ordering::SeqCst);
+    if !hook.is_null() {
+        unsafe { transmute::<*mut (), fn()>(hook)() };
+    }
     processor::self_reset()
 }
@@ -0,0 +1,415 @@
+//! SEGGER RTT (Real-Time Transfer) interface.
+//!
+//! RTT moves data between the target and the host through ring buffers in the
+//! target RAM, which the debug probe accesses in the background without
+//! halting the processor. Unlike ITM, RTT works with any probe that can read
+//! memory at run-time, and supports host-to-target channels.
+//!
+//! The [`control_block!`](crate::rtt::control_block!) macro defines a control
+//! block compatible with SEGGER tools (J-Link RTT Viewer, `probe-rs`, OpenOCD
+//! `rtt` commands). The probe finds the block by its ID in RAM. Up channels
+//! carry data from the target to the host, down channels from the host to the
+//! target.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! # #![feature(const_fn_fn_ptr_basics)]
+//! # #![feature(marker_trait_attr)]
+//! use drone_cortexm::{rtt, thr, thr::prelude::*};
+//! use futures::prelude::*;
+//!
+//! thr! {
+//!     thread => pub Thr {};
+//!     local => pub ThrLocal {};
+//!     vtable => pub Vtable;
+//!     index => pub Thrs;
+//!     init => pub ThrsInit;
+//!     threads => {
+//!         interrupts => {
+//!             5: pub rcc;
+//!         };
+//!     };
+//! }
+//!
+//! rtt::control_block! {
+//!     /// The RTT control block.
+//!     pub static RTT;
+//!     up => {
+//!         "Terminal", 1024;
+//!     };
+//!     down => {
+//!         "Terminal", 16;
+//!     };
+//! }
+//!
+//! rtt::set_log!(RTT);
+//!
+//! # fn main() {
+//! let thr = thr::init(unsafe { ThrsInit::take() });
+//! RTT.up()[0].write(b"Hello, world!\n");
+//! // The stream is checked for new data every time `rcc` thread runs.
+//! let mut input = RTT.down()[0].stream(thr.rcc);
+//! thr.rcc.exec(async move {
+//!     while let Some(bytes) = input.next().await {
+//!         RTT.up()[0].write(&bytes);
+//!     }
+//! });
+//! # }
+//! ```
+
+use crate::{
+    fib::{self, FiberState},
+    thr::{prelude::*, priority::critical_section},
+    util::assert,
+};
+use alloc::{sync::Arc, vec::Vec};
+use core::{
+    cell::UnsafeCell,
+    mem::size_of,
+    pin::Pin,
+    ptr::{read_volatile, write_volatile},
+    sync::atomic::{AtomicUsize, Ordering},
+    task::{Context, Poll},
+};
+use futures::{stream::Stream, task::AtomicWaker};
+
+const ID: [u8; 16] = *b"SEGGER RTT\0\0\0\0\0\0";
+
+/// RTT control block.
+///
+/// `U` and `D` are the arrays of the up and down [`Channel`]s respectively.
+/// Use [`control_block!`](crate::rtt::control_block!) to define a control
+/// block.
+#[repr(C)]
+pub struct ControlBlock<U, D> {
+    id: [u8; 16],
+    max_up: i32,
+    max_down: i32,
+    up: U,
+    down: D,
+}
+
+/// RTT channel, which is a ring buffer shared with the host.
+#[repr(C)]
+pub struct Channel {
+    name: *const u8,
+    buffer: *mut u8,
+    size: usize,
+    write: AtomicUsize,
+    read: AtomicUsize,
+    flags: AtomicUsize,
+}
+
+/// Behavior of an up channel when the buffer is full.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(usize)]
+pub enum Mode {
+    /// Skip the whole write if it doesn't fit.
+    NoBlockSkip = 0,
+    /// Write as many bytes as fit.
+    NoBlockTrim = 1,
+    /// Wait until the host reads enough bytes.
+    BlockIfFull = 2,
+}
+
+/// Channel buffer storage.
+#[doc(hidden)]
+pub struct Storage<T>(UnsafeCell<T>);
+
+/// Stream of the data received from the host through a down channel.
+pub struct DownStream {
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    channel: &'static Channel,
+    waker: AtomicWaker,
+}
+
+unsafe impl Sync for Channel {}
+unsafe impl<T> Sync for Storage<T> {}
+
+impl<U, D> ControlBlock<U, D> {
+    /// Creates a new control block with `up` and `down` channel arrays.
+    #[allow(clippy::cast_possible_wrap)]
+    #[inline]
+    pub const fn new(up: U, down: D) -> Self {
+        Self {
+            id: ID,
+            max_up: (size_of::<U>() / size_of::<Channel>()) as i32,
+            max_down: (size_of::<D>() / size_of::<Channel>()) as i32,
+            up,
+            down,
+        }
+    }
+}
+
+impl<U: AsRef<[Channel]>, D: AsRef<[Channel]>> ControlBlock<U, D> {
+    /// Returns the up channels, which send data to the host.
+    #[inline]
+    pub fn up(&self) -> &[Channel] {
+        self.up.as_ref()
+    }
+
+    /// Returns the down channels, which receive data from the host.
+    #[inline]
+    pub fn down(&self) -> &[Channel] {
+        self.down.as_ref()
+    }
+
+    /// Writes `bytes` of the log port `port` to the up channel with the same
+    /// number. Ports without a channel are ignored.
+    #[doc(hidden)]
+    #[inline]
+    pub fn write_port(&self, port: u8, bytes: &[u8]) {
+        if let Some(channel) = self.up().get(usize::from(port)) {
+            channel.write(bytes);
+        }
+    }
+}
+
+impl Channel {
+    /// Creates a new channel named `name` with the buffer `buffer`.
+    ///
+    /// `name` must be NUL-terminated. The buffer holds one byte less than its
+    /// size.
+    ///
+    /// # Panics
+    ///
+    /// If `name` is not NUL-terminated.
+    #[inline]
+    pub const fn new<T>(name: &'static str, buffer: &'static Storage<T>) -> Self {
+        assert(!name.is_empty() && name.as_bytes()[name.len() - 1] == 0);
+        Self {
+            name: name.as_ptr(),
+            buffer: buffer.0.get() as *mut u8,
+            size: size_of::<T>(),
+            write: AtomicUsize::new(0),
+            read: AtomicUsize::new(0),
+            flags: AtomicUsize::new(Mode::NoBlockSkip as usize),
+        }
+    }
+
+    /// Returns the behavior of the channel when the buffer is full.
+    #[inline]
+    pub fn mode(&self) -> Mode {
+        match self.flags.load(Ordering::Relaxed) & 0b11 {
+            0 => Mode::NoBlockSkip,
+            1 => Mode::NoBlockTrim,
+            _ => Mode::BlockIfFull,
+        }
+    }
+
+    /// Sets the behavior of the channel when the buffer is full.
+    #[inline]
+    pub fn set_mode(&self, mode: Mode) {
+        let flags = self.flags.load(Ordering::Relaxed);
+        self.flags.store(flags & !0b11 | mode as usize, Ordering::Relaxed);
+    }
+
+    /// Returns the number of bytes in the buffer.
+    #[inline]
+    pub fn len(&self) -> usize {
+        let write = self.write.load(Ordering::Acquire);
+        let read = self.read.load(Ordering::Acquire);
+        if write >= read { write - read } else { self.size - read + write }
+    }
+
+    /// Returns `true` if the buffer is empty.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Writes `bytes` to the channel according to the channel [`Mode`],
+    /// returning the number of written bytes.
+    ///
+    /// Writes from different threads are not interleaved, unless the mode is
+    /// [`Mode::BlockIfFull`] and the buffer is full.
+    pub fn write(&self, bytes: &[u8]) -> usize {
+        let mode = self.mode();
+        let mut written = 0;
+        loop {
+            written += critical_section(0, || {
+                let rest = &bytes[written..];
+                let free = self.size - 1 - self.len();
+                if mode == Mode::NoBlockSkip && free < rest.len() {
+                    return 0;
+                }
+                let count = rest.len().min(free);
+                let mut write = self.write.load(Ordering::Relaxed);
+                for &byte in &rest[..count] {
+                    unsafe { write_volatile(self.buffer.add(write), byte) };
+                    write = if write + 1 == self.size { 0 } else { write + 1 };
+                }
+                self.write.store(write, Ordering::Release);
+                count
+            });
+            if written == bytes.len() || mode != Mode::BlockIfFull {
+                break written;
+            }
+        }
+    }
+
+    /// Reads the received bytes into `buffer`, returning the number of read
+    /// bytes.
+    ///
+    /// The channel should have only one reader.
+    pub fn read(&self, buffer: &mut [u8]) -> usize {
+        let write = self.write.load(Ordering::Acquire);
+        let mut read = self.read.load(Ordering::Relaxed);
+        let mut count = 0;
+        while count < buffer.len() && read != write {
+            buffer[count] = unsafe { read_volatile(self.buffer.add(read)) };
+            read = if read + 1 == self.size { 0 } else { read + 1 };
+            count += 1;
+        }
+        self.read.store(read, Ordering::Release);
+        count
+    }
+
+    /// Returns a stream of the bytes received through the channel.
+    ///
+    /// The RTT host doesn't notify the target about new data, so the channel
+    /// is checked every time the thread `thr` runs. `thr` should be triggered
+    /// periodically, e.g. by a timer. The channel should have only one reader.
+    pub fn stream<I: IntToken>(&'static self, thr: I) -> DownStream {
+        let shared = Arc::new(Shared { channel: self, waker: AtomicWaker::new() });
+        let handler = Arc::clone(&shared);
+        thr.add_fn(move || -> FiberState<(), ()> {
+            if Arc::strong_count(&handler) == 1 {
+                return fib::Complete(());
+            }
+            if !handler.channel.is_empty() {
+                handler.waker.wake();
+            }
+            fib::Yielded(())
+        });
+        DownStream { shared }
+    }
+}
+
+impl<T> Storage<T> {
+    /// Creates a new storage.
+    #[inline]
+    pub const fn new(buffer: T) -> Self {
+        Self(UnsafeCell::new(buffer))
+    }
+}
+
+impl Stream for DownStream {
+    type Item = Vec<u8>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Vec<u8>>> {
+        let channel = self.shared.channel;
+        if channel.is_empty() {
+            self.shared.waker.register(cx.waker());
+            if channel.is_empty() {
+                return Poll::Pending;
+            }
+        }
+        let mut bytes = Vec::new();
+        bytes.resize(channel.len(), 0);
+        let count = channel.read(&mut bytes);
+        bytes.truncate(count);
+        Poll::Ready(Some(bytes))
+    }
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! rtt_control_block {
+    (@channel $channel_name:literal, $size:expr) => {
+        $crate::rtt::Channel::new(concat!($channel_name, "\0"), {
+            static BUFFER: $crate::rtt::Storage<[u8; $size]> =
+                $crate::rtt::Storage::new([0; $size]);
+            &BUFFER
+        })
+    };
+    (
+        $(#[$attr:meta])* $vis:vis static $name:ident;
+        up => { $($up_name:literal, $up_size:expr;)* };
+        down => { $($down_name:literal, $down_size:expr;)* };
+    ) => {
+        $(#[$attr])*
+        $vis static $name: $crate::rtt::ControlBlock<
+            [$crate::rtt::Channel; <[&str]>::len(&[$($up_name),*])],
+            [$crate::rtt::Channel; <[&str]>::len(&[$($down_name),*])],
+        > = $crate::rtt::ControlBlock::new(
+            [$($crate::rtt_control_block!(@channel $up_name, $up_size)),*],
+            [$($crate::rtt_control_block!(@channel $down_name, $down_size)),*],
+        );
+    };
+}
+
+/// Defines an RTT control block.
+///
+/// Each channel is defined by its name and its buffer size. The up channel
+/// number 0 is expected to be `"Terminal"` by the host tools.
+///
+/// See [the module level documentation](crate::rtt) for details.
+#[doc(inline)]
+pub use crate::rtt_control_block as control_block;
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! rtt_set_log {
+    ($block:path) => {
+        const _: () = {
+            #[no_mangle]
+            extern "C" fn drone_log_is_enabled(port: u8) -> bool {
+                usize::from(port) < $block.up().len()
+            }
+
+            #[no_mangle]
+            extern "C" fn drone_log_write_bytes(port: u8, buffer: *const u8, count: usize) {
+                let bytes = unsafe { ::core::slice::from_raw_parts(buffer, count) };
+                $block.write_port(port, bytes);
+            }
+
+            #[no_mangle]
+            extern "C" fn drone_log_write_u8(port: u8, value: u8) {
+                $block.write_port(port, &value.to_le_bytes());
+            }
+
+            #[no_mangle]
+            extern "C" fn drone_log_write_u16(port: u8, value: u16) {
+                $block.write_port(port, &value.to_le_bytes());
+            }
+
+            #[no_mangle]
+            extern "C" fn drone_log_write_u32(port: u8, value: u32) {
+                $block.write_port(port, &value.to_le_bytes());
+            }
+
+            #[no_mangle]
+            extern "C" fn drone_log_flush() {}
+        };
+    };
+}
+
+/// Sets RTT as default logger.
+///
+/// Each log port is written to the up channel with the same number. Ports
+/// without a channel are disabled. Flushing is a no-op, because the host reads
+/// the buffers in the background.
+///
+/// # Examples
+///
+/// ```
+/// use drone_cortexm::rtt;
+///
+/// rtt::control_block! {
+///     static RTT;
+///     up => {
+///         "Terminal", 256;
+///         "Errors", 64;
+///     };
+///     down => {};
+/// }
+///
+/// rtt::set_log!(RTT);
+/// ```
+#[doc(inline)]
+pub use crate::rtt_set_log as set_log;
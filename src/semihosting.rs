@@ -0,0 +1,397 @@
+//! ARM semihosting interface.
+//!
+//! Semihosting lets the program use the I/O facilities of the host through the
+//! debugger, or through an emulator like QEMU with the `-semihosting` option.
+//! Each operation is a `bkpt 0xAB` instruction, which halts the processor
+//! until the host completes the request. This makes semihosting slow, but it
+//! requires no trace pins.
+//!
+//! **Warning:** a semihosting call without a debugger attached escalates to a
+//! HardFault.
+//!
+//! With the `std` feature, the operations are passed to the handler set with
+//! [`Sim::set_semihosting`](crate::sim::Sim::set_semihosting), and fail if
+//! there is no handler. [`exit`] exits the host process.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use drone_cortexm::semihosting::{self, File, OpenMode};
+//!
+//! semihosting::write_stdout(b"Hello, world!\n");
+//! let file = File::open("output.bin", OpenMode::WriteBinary).unwrap();
+//! file.write(&[1, 2, 3]).unwrap();
+//! file.close().unwrap();
+//! semihosting::exit(0);
+//! ```
+
+#![cfg_attr(feature = "std", allow(unreachable_code, unused_variables))]
+
+use core::{
+    fmt,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// `SYS_OPEN` operation number.
+pub const SYS_OPEN: u32 = 0x01;
+/// `SYS_CLOSE` operation number.
+pub const SYS_CLOSE: u32 = 0x02;
+/// `SYS_WRITE` operation number.
+pub const SYS_WRITE: u32 = 0x05;
+/// `SYS_READ` operation number.
+pub const SYS_READ: u32 = 0x06;
+/// `SYS_SEEK` operation number.
+pub const SYS_SEEK: u32 = 0x0A;
+/// `SYS_FLEN` operation number.
+pub const SYS_FLEN: u32 = 0x0C;
+/// `SYS_CLOCK` operation number.
+pub const SYS_CLOCK: u32 = 0x10;
+/// `SYS_TIME` operation number.
+pub const SYS_TIME: u32 = 0x11;
+/// `SYS_ERRNO` operation number.
+pub const SYS_ERRNO: u32 = 0x13;
+/// `SYS_EXIT` operation number.
+pub const SYS_EXIT: u32 = 0x18;
+/// `SYS_EXIT_EXTENDED` operation number.
+pub const SYS_EXIT_EXTENDED: u32 = 0x20;
+
+/// Maximum length of a path in bytes accepted by [`File::open`].
+pub const MAX_PATH_LEN: usize = 255;
+
+const ENAMETOOLONG: u32 = 36;
+const ADP_STOPPED_APPLICATION_EXIT: usize = 0x20026;
+const CONSOLE: &[u8] = b":tt\0";
+const CLOSED: usize = usize::MAX;
+
+static STDOUT: AtomicUsize = AtomicUsize::new(CLOSED);
+static STDERR: AtomicUsize = AtomicUsize::new(CLOSED);
+
+/// A file opened on the host.
+///
+/// The file is not closed on drop, use [`File::close`] instead.
+#[derive(Debug)]
+pub struct File {
+    handle: usize,
+}
+
+/// File access mode, equivalent to the `mode` argument of ISO C `fopen`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(usize)]
+pub enum OpenMode {
+    /// `"r"`
+    Read = 0,
+    /// `"rb"`
+    ReadBinary = 1,
+    /// `"r+"`
+    ReadWrite = 2,
+    /// `"r+b"`
+    ReadWriteBinary = 3,
+    /// `"w"`
+    Write = 4,
+    /// `"wb"`
+    WriteBinary = 5,
+    /// `"w+"`
+    WriteRead = 6,
+    /// `"w+b"`
+    WriteReadBinary = 7,
+    /// `"a"`
+    Append = 8,
+    /// `"ab"`
+    AppendBinary = 9,
+    /// `"a+"`
+    AppendRead = 10,
+    /// `"a+b"`
+    AppendReadBinary = 11,
+}
+
+/// Error returned from a semihosting operation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Error {
+    errno: u32,
+}
+
+/// Makes a raw semihosting call with the operation number `op` and the
+/// parameter `param`, which is usually the address of a parameter block.
+///
+/// # Safety
+///
+/// `param` must be valid for the operation `op`.
+#[inline]
+pub unsafe fn call(op: u32, param: usize) -> usize {
+    #[cfg(feature = "std")]
+    return crate::sim::semihosting_call(op, param);
+    let result: usize;
+    unsafe {
+        llvm_asm!("bkpt 0xAB"
+            : "={r0}"(result)
+            : "{r0}"(op), "{r1}"(param)
+            : "memory"
+            : "volatile"
+        );
+    }
+    result
+}
+
+#[allow(clippy::len_without_is_empty)]
+impl File {
+    /// Opens the file at `path` on the host.
+    ///
+    /// The special path `":tt"` opens the host console: the standard output
+    /// with a write mode, the standard error with an append mode.
+    ///
+    /// The path is NUL-terminated in a buffer on the stack.
+    ///
+    /// # Errors
+    ///
+    /// If `path` is longer than [`MAX_PATH_LEN`] bytes, in which case the
+    /// error number is `ENAMETOOLONG`, or if the host fails to open the file.
+    pub fn open(path: &str, mode: OpenMode) -> Result<Self, Error> {
+        if path.len() > MAX_PATH_LEN {
+            return Err(Error { errno: ENAMETOOLONG });
+        }
+        let mut name = [0; MAX_PATH_LEN + 1];
+        name[..path.len()].copy_from_slice(path.as_bytes());
+        open(&name[..=path.len()], mode).map(|handle| Self { handle })
+    }
+
+    /// Writes `bytes` to the file, returning the number of written bytes.
+    ///
+    /// # Errors
+    ///
+    /// If no bytes were written.
+    pub fn write(&self, bytes: &[u8]) -> Result<usize, Error> {
+        write(self.handle, bytes)
+    }
+
+    /// Reads bytes from the file into `buffer`, returning the number of read
+    /// bytes. Zero means the end of the file.
+    ///
+    /// # Errors
+    ///
+    /// If the host fails to read the file.
+    pub fn read(&self, buffer: &mut [u8]) -> Result<usize, Error> {
+        let params = [self.handle, buffer.as_mut_ptr() as usize, buffer.len()];
+        let unread = unsafe { call(SYS_READ, params.as_ptr() as usize) };
+        if unread > buffer.len() { Err(Error::last()) } else { Ok(buffer.len() - unread) }
+    }
+
+    /// Seeks to the absolute byte position `position` in the file.
+    ///
+    /// # Errors
+    ///
+    /// If the host fails to seek the file.
+    pub fn seek(&self, position: usize) -> Result<(), Error> {
+        let params = [self.handle, position];
+        check(unsafe { call(SYS_SEEK, params.as_ptr() as usize) }).map(drop)
+    }
+
+    /// Returns the length of the file in bytes.
+    ///
+    /// # Errors
+    ///
+    /// If the host fails to get the length.
+    pub fn len(&self) -> Result<usize, Error> {
+        let params = [self.handle];
+        check(unsafe { call(SYS_FLEN, params.as_ptr() as usize) })
+    }
+
+    /// Closes the file.
+    ///
+    /// # Errors
+    ///
+    /// If the host fails to close the file.
+    pub fn close(self) -> Result<(), Error> {
+        close(self.handle)
+    }
+}
+
+impl Error {
+    /// Returns the host `errno` value.
+    pub fn errno(self) -> u32 {
+        self.errno
+    }
+
+    fn last() -> Self {
+        Self { errno: unsafe { call(SYS_ERRNO, 0) } as u32 }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Semihosting operation failed with errno {}.", self.errno)
+    }
+}
+
+/// Writes `bytes` to the host standard output.
+///
+/// The console is opened on the first call.
+#[inline]
+pub fn write_stdout(bytes: &[u8]) {
+    write_console(&STDOUT, OpenMode::Write, bytes);
+}
+
+/// Writes `bytes` to the host standard error.
+///
+/// The console is opened on the first call.
+#[inline]
+pub fn write_stderr(bytes: &[u8]) {
+    write_console(&STDERR, OpenMode::Append, bytes);
+}
+
+/// Writes `bytes` of the log port `port` to the host console: port 0 to the
+/// standard output, port 1 to the standard error. Other ports are ignored.
+#[doc(hidden)]
+#[inline]
+pub fn write_port(port: u8, bytes: &[u8]) {
+    match port {
+        0 => write_stdout(bytes),
+        1 => write_stderr(bytes),
+        _ => {}
+    }
+}
+
+/// Returns the number of centiseconds since the execution started.
+///
+/// # Errors
+///
+/// If the host doesn't support the clock.
+#[inline]
+pub fn clock() -> Result<u32, Error> {
+    check(unsafe { call(SYS_CLOCK, 0) }).map(|clock| clock as u32)
+}
+
+/// Returns the number of seconds since 00:00 January 1, 1970.
+///
+/// # Errors
+///
+/// If the host doesn't support the time.
+#[inline]
+pub fn time() -> Result<u32, Error> {
+    check(unsafe { call(SYS_TIME, 0) }).map(|time| time as u32)
+}
+
+/// Terminates the execution with the exit status `status`.
+///
+/// A non-zero status requires `SYS_EXIT_EXTENDED` support from the host.
+/// Otherwise the host reports an abnormal exit, which QEMU turns into the exit
+/// status 1.
+#[allow(clippy::cast_possible_wrap, clippy::empty_loop)]
+pub fn exit(status: u32) -> ! {
+    #[cfg(feature = "std")]
+    std::process::exit(status as i32);
+    let params = [ADP_STOPPED_APPLICATION_EXIT, status as usize];
+    unsafe {
+        call(SYS_EXIT_EXTENDED, params.as_ptr() as usize);
+        let reason = if status == 0 { ADP_STOPPED_APPLICATION_EXIT } else { 0 };
+        call(SYS_EXIT, reason);
+    }
+    loop {}
+}
+
+fn open(name: &[u8], mode: OpenMode) -> Result<usize, Error> {
+    let params = [name.as_ptr() as usize, mode as usize, name.len() - 1];
+    check(unsafe { call(SYS_OPEN, params.as_ptr() as usize) })
+}
+
+fn close(handle: usize) -> Result<(), Error> {
+    let params = [handle];
+    check(unsafe { call(SYS_CLOSE, params.as_ptr() as usize) }).map(drop)
+}
+
+fn write(handle: usize, bytes: &[u8]) -> Result<usize, Error> {
+    let params = [handle, bytes.as_ptr() as usize, bytes.len()];
+    let unwritten = unsafe { call(SYS_WRITE, params.as_ptr() as usize) };
+    if unwritten == bytes.len() && !bytes.is_empty() || unwritten > bytes.len() {
+        Err(Error::last())
+    } else {
+        Ok(bytes.len() - unwritten)
+    }
+}
+
+/// Writes `bytes` to the console handle stored in `console`, opening the
+/// console first if needed.
+///
+/// A preempting thread can open the console concurrently. Only the first
+/// stored handle is kept, and the other one is closed.
+fn write_console(console: &AtomicUsize, mode: OpenMode, bytes: &[u8]) {
+    let mut handle = console.load(Ordering::Acquire);
+    if handle == CLOSED {
+        let opened = match open(CONSOLE, mode) {
+            Ok(opened) => opened,
+            Err(_) => return,
+        };
+        let stored = console.compare_exchange(CLOSED, opened, Ordering::AcqRel, Ordering::Acquire);
+        handle = match stored {
+            Ok(_) => opened,
+            Err(current) => {
+                let _ = close(opened);
+                current
+            }
+        };
+    }
+    let _ = write(handle, bytes);
+}
+
+/// Forgets the console handles opened by the previous test.
+#[cfg(feature = "std")]
+pub(crate) fn reset_consoles() {
+    STDOUT.store(CLOSED, Ordering::Release);
+    STDERR.store(CLOSED, Ordering::Release);
+}
+
+#[allow(clippy::cast_possible_wrap)]
+fn check(result: usize) -> Result<usize, Error> {
+    if result as isize == -1 { Err(Error::last()) } else { Ok(result) }
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! semihosting_set_log {
+    () => {
+        const _: () = {
+            #[no_mangle]
+            extern "C" fn drone_log_is_enabled(port: u8) -> bool {
+                port < 2
+            }
+
+            #[no_mangle]
+            extern "C" fn drone_log_write_bytes(port: u8, buffer: *const u8, count: usize) {
+                let bytes = unsafe { ::core::slice::from_raw_parts(buffer, count) };
+                $crate::semihosting::write_port(port, bytes);
+            }
+
+            #[no_mangle]
+            extern "C" fn drone_log_write_u8(port: u8, value: u8) {
+                $crate::semihosting::write_port(port, &value.to_le_bytes());
+            }
+
+            #[no_mangle]
+            extern "C" fn drone_log_write_u16(port: u8, value: u16) {
+                $crate::semihosting::write_port(port, &value.to_le_bytes());
+            }
+
+            #[no_mangle]
+            extern "C" fn drone_log_write_u32(port: u8, value: u32) {
+                $crate::semihosting::write_port(port, &value.to_le_bytes());
+            }
+
+            #[no_mangle]
+            extern "C" fn drone_log_flush() {}
+        };
+    };
+}
+
+/// Sets semihosting as default logger.
+///
+/// The log port 0 is written to the host standard output, the log port 1 to
+/// the host standard error. Other ports are disabled.
+///
+/// # Examples
+///
+/// ```
+/// use drone_cortexm::semihosting;
+///
+/// semihosting::set_log!();
+/// ```
+#[doc(inline)]
+pub use crate::semihosting_set_log as set_log;
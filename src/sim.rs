@@ -0,0 +1,195 @@
+//! Host-side simulator of the core memory-mapped register space.
+//!
+//! This module is available only with the `std` feature. It maps a block of
+//! plain memory at the address of the ARM® Cortex®-M Private Peripheral Bus
+//! (`0xE000_0000..0xE010_0000`) inside the host process. After that, register
+//! tokens from `drone_core::reg`, as well as the raw register addresses used in
+//! [`thr`](crate::thr), [`swo`](crate::swo) and [`drv`](crate::drv), access the
+//! simulated memory instead of crashing the process. This allows unit-testing
+//! driver code on a Linux host.
+//!
+//! The simulated registers have no side effects: writing to a register only
+//! changes the memory cell. The only exception is ITM stimulus ports, writes to
+//! which are captured per-port and can be retrieved with [`Sim::itm_output`].
+//!
+//! Semihosting calls are passed to the handler set with
+//! [`Sim::set_semihosting`].
+//!
+//! Tests should acquire the simulator with [`Sim::lock`] before touching any
+//! register. The lock serializes tests that run in parallel and resets the
+//! register space to all zeros.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use drone_cortexm::{sim::Sim, swo};
+//!
+//! let sim = Sim::lock();
+//! // Preload the ITM Trace Control and Trace Enable registers.
+//! sim.write::<u32>(0xE000_0E80, 1);
+//! sim.write::<u32>(0xE000_0E00, 1 << 3);
+//! assert!(swo::is_port_enabled(3));
+//! swo::Port::new(3).write_bytes(b"hello");
+//! assert_eq!(sim.itm_output(3), b"hello");
+//! ```
+
+use crate::swo::PORTS_COUNT;
+use std::{
+    mem::size_of,
+    ptr::{read_volatile, write_bytes, write_volatile},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex, Once,
+    },
+    thread,
+};
+
+/// Start address of the simulated register space.
+pub const BASE: usize = 0xE000_0000;
+
+/// Size of the simulated register space.
+pub const SIZE: usize = 0x0010_0000;
+
+static MAP: Once = Once::new();
+static LOCKED: AtomicBool = AtomicBool::new(false);
+static mut ITM_OUTPUT: Option<Mutex<Vec<Vec<u8>>>> = None;
+static mut SEMIHOSTING: Option<fn(u32, usize) -> usize> = None;
+
+/// Exclusive access to the simulated register space.
+///
+/// The lock is released when this value is dropped.
+pub struct Sim {
+    _priv: (),
+}
+
+impl Sim {
+    /// Acquires exclusive access to the simulated register space, mapping it on
+    /// the first call.
+    ///
+    /// Blocks while another thread holds the lock. The register space and the
+    /// captured ITM output are cleared on each acquisition.
+    ///
+    /// # Panics
+    ///
+    /// If the address range of the register space is already occupied in the
+    /// host process.
+    pub fn lock() -> Self {
+        init();
+        while LOCKED.compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed).is_err()
+        {
+            thread::yield_now();
+        }
+        let sim = Self { _priv: () };
+        sim.reset();
+        sim
+    }
+
+    /// Fills the whole register space with zeros, clears the captured ITM
+    /// output, and removes the semihosting handler.
+    pub fn reset(&self) {
+        unsafe { write_bytes(BASE as *mut u8, 0, SIZE) };
+        for port in itm_output().lock().unwrap().iter_mut() {
+            port.clear();
+        }
+        unsafe { SEMIHOSTING = None };
+        crate::semihosting::reset_consoles();
+    }
+
+    /// Reads the value of type `T` at the register `address`.
+    ///
+    /// # Panics
+    ///
+    /// If `address` is outside of the simulated register space or misaligned.
+    pub fn read<T: Copy>(&self, address: usize) -> T {
+        check_address::<T>(address);
+        unsafe { read_volatile(address as *const T) }
+    }
+
+    /// Writes the value of type `T` to the register `address`.
+    ///
+    /// # Panics
+    ///
+    /// If `address` is outside of the simulated register space or misaligned.
+    pub fn write<T: Copy>(&self, address: usize, value: T) {
+        check_address::<T>(address);
+        unsafe { write_volatile(address as *mut T, value) };
+    }
+
+    /// Returns all bytes written to the ITM stimulus port number `port` since
+    /// the last reset.
+    ///
+    /// # Panics
+    ///
+    /// If `port` is more than or equal to [`PORTS_COUNT`].
+    pub fn itm_output(&self, port: u8) -> Vec<u8> {
+        assert!(port < PORTS_COUNT);
+        itm_output().lock().unwrap()[usize::from(port)].clone()
+    }
+
+    /// Sets the host side of the semihosting interface. Each
+    /// [`semihosting::call`](crate::semihosting::call) invokes `handler` with
+    /// the operation number and the parameter, and returns its result.
+    ///
+    /// Without a handler, every call returns `-1`.
+    pub fn set_semihosting(&self, handler: fn(u32, usize) -> usize) {
+        unsafe { SEMIHOSTING = Some(handler) };
+    }
+}
+
+impl Drop for Sim {
+    fn drop(&mut self) {
+        LOCKED.store(false, Ordering::Release);
+    }
+}
+
+/// Maps the simulated register space into the host process.
+///
+/// This function is idempotent. It is called implicitly by [`Sim::lock`].
+///
+/// # Panics
+///
+/// If the address range of the register space is already occupied in the host
+/// process.
+pub fn init() {
+    MAP.call_once(|| unsafe {
+        let ptr = libc::mmap(
+            BASE as *mut libc::c_void,
+            SIZE,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+            -1,
+            0,
+        );
+        if ptr as usize != BASE {
+            if ptr != libc::MAP_FAILED {
+                libc::munmap(ptr, SIZE);
+            }
+            panic!("Unable to map the simulated register space at {:#010X}", BASE);
+        }
+        ITM_OUTPUT = Some(Mutex::new(vec![Vec::new(); usize::from(PORTS_COUNT)]));
+    });
+}
+
+pub(crate) fn itm_write(address: usize, bytes: &[u8]) {
+    init();
+    let port = address.wrapping_sub(BASE) >> 2;
+    assert!(port < usize::from(PORTS_COUNT), "ITM stimulus port address out of range");
+    itm_output().lock().unwrap()[port].extend_from_slice(bytes);
+}
+
+pub(crate) fn semihosting_call(op: u32, param: usize) -> usize {
+    unsafe { SEMIHOSTING }.map_or(usize::MAX, |handler| handler(op, param))
+}
+
+fn itm_output() -> &'static Mutex<Vec<Vec<u8>>> {
+    unsafe { ITM_OUTPUT.as_ref().unwrap() }
+}
+
+fn check_address<T>(address: usize) {
+    assert!(
+        address >= BASE && address + size_of::<T>() <= BASE + SIZE,
+        "Address {:#010X} is outside of the simulated register space",
+        address
+    );
+    assert_eq!(address % size_of::<T>(), 0, "Misaligned register access at {:#010X}", address);
+}
@@ -52,6 +52,9 @@
 //! supervisor, [`Switch::switch_context`] and [`Switch::switch_back`] functions
 //! become available to switch the program stack.
 //!
+//! Services in the [`syscall`] module let unprivileged fibers trigger threads,
+//! control granted interrupts, read the cycle counter, and write to SWO.
+//!
 //! ```no_run
 //! # #![feature(new_uninit)]
 //! use drone_cortexm::sv::{Switch, SwitchBackService, SwitchContextService};
@@ -91,9 +94,14 @@
 
 #![cfg_attr(feature = "std", allow(unreachable_code, unused_variables))]
 
+pub mod syscall;
+
 mod switch;
 
-pub use self::switch::{Switch, SwitchBackService, SwitchContextService};
+pub use self::{
+    switch::{Switch, SwitchBackService, SwitchContextService},
+    syscall::{IntDisableService, IntEnableService, LogService, TimeService, TriggerService},
+};
 
 use core::{intrinsics::unreachable, mem::size_of};
 
@@ -114,6 +122,16 @@ pub trait SvCall<T: SvService>: Supervisor {
     unsafe fn call(service: &mut T);
 }
 
+/// A supervisor call with a known service number.
+///
+/// This trait is implemented by the [`sv!`](crate::sv!) macro, and is
+/// required by the system calls in [`syscall`], which issue the `SVC num`
+/// instruction directly.
+pub trait SvCallNum<T: SvService>: SvCall<T> {
+    /// The number `num` in the `SVC num` instruction for the service `T`.
+    const NUM: u8;
+}
+
 /// Generic supervisor service.
 pub trait SvService: Sized + Send + 'static {
     /// Called when `SVC num` instruction was invoked and `num` corresponds to
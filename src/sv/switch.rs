@@ -1,7 +1,10 @@
 #![cfg_attr(feature = "std", allow(unreachable_code, unused_variables))]
 
-use crate::sv::{SvCall, SvService};
-use core::{intrinsics::unreachable, mem::size_of};
+use crate::{
+    sv::{SvCall, SvService},
+    thr::stack::MAIN_STACK_GUARDED,
+};
+use core::{intrinsics::unreachable, mem::size_of, sync::atomic::Ordering};
 
 /// A service to switch to a process stack.
 ///
@@ -80,11 +83,12 @@ impl SvService for SwitchContextService {
         2:
             movw     r0, #0xED9C
             movt     r0, #0xE000
+            mov      r4, #0
+            str      r4, [r0, #-4]
             ldmia    r3!, {r4-r11}
             stmia    r0, {r4-r11}
-            ldmia    r3!, {r4-r11}
-            stmia    r0, {r4-r11}
-            mov      r3, #5
+            ldr      r3, [r0, #-8]
+            orr      r3, r3, #5
             str      r3, [r0, #-8]
             b        1b
         3:
@@ -121,11 +125,12 @@ impl SvService for SwitchContextService {
         2:
             movw     r0, #0xED9C
             movt     r0, #0xE000
+            mov      r4, #0
+            str      r4, [r0, #-4]
             ldmia    r3!, {r4-r11}
             stmia    r0, {r4-r11}
-            ldmia    r3!, {r4-r11}
-            stmia    r0, {r4-r11}
-            mov      r3, #5
+            ldr      r3, [r0, #-8]
+            orr      r3, r3, #5
             str      r3, [r0, #-8]
             b        1b
         3:
@@ -149,12 +154,25 @@ impl SvService for SwitchBackService {
         #[cfg(feature = "std")]
         return unimplemented!();
         let Self { data_ptr, data_size } = *self;
+        // Unless the main stack guard or the MPU driver is in use the whole
+        // MPU is disabled, otherwise only the fiber regions 0 to 3 are.
+        let keep_mpu_enabled = u32::from(keep_mpu_enabled());
         #[cfg(feature = "floating-point-unit")]
         llvm_asm!("
             movw     r2, #0xED94
             movt     r2, #0xE000
-            mov      r3, #0
+            cbnz     r3, 4f
             str      r3, [r2]
+            b        6f
+        4:
+            mov      r3, #0
+            mov      r12, #3
+        5:
+            str      r12, [r2, #4]
+            str      r3, [r2, #12]
+            subs     r12, r12, #1
+            bpl      5b
+        6:
             mrs      r3, control
             mrs      r12, psp
             tst      lr, #0x10
@@ -204,7 +222,7 @@ impl SvService for SwitchBackService {
             msr      control, r3
             bx       lr
         "   :
-            : "{r0}"(data_ptr), "{r1}"(data_size)
+            : "{r0}"(data_ptr), "{r1}"(data_size), "{r3}"(keep_mpu_enabled)
             : "cc", "memory"
             : "volatile"
         );
@@ -212,8 +230,18 @@ impl SvService for SwitchBackService {
         llvm_asm!("
             movw     r2, #0xED94
             movt     r2, #0xE000
-            mov      r3, #0
+            cbnz     r3, 4f
             str      r3, [r2]
+            b        6f
+        4:
+            mov      r3, #0
+            mov      r12, #3
+        5:
+            str      r12, [r2, #4]
+            str      r3, [r2, #12]
+            subs     r12, r12, #1
+            bpl      5b
+        6:
             mrs      r3, control
             mrs      r12, psp
             stmdb    r12!, {r3, r4-r11, lr}
@@ -253,7 +281,7 @@ impl SvService for SwitchBackService {
             msr      control, r3
             bx       lr
         "   :
-            : "{r0}"(data_ptr), "{r1}"(data_size)
+            : "{r0}"(data_ptr), "{r1}"(data_size), "{r3}"(keep_mpu_enabled)
             : "cc", "memory"
             : "volatile"
         );
@@ -279,3 +307,11 @@ where
         }
     }
 }
+
+fn keep_mpu_enabled() -> bool {
+    #[cfg(all(feature = "memory-protection-unit", not(armv8m)))]
+    if crate::drv::mpu::ENABLED.load(Ordering::Relaxed) {
+        return true;
+    }
+    MAIN_STACK_GUARDED.load(Ordering::Relaxed)
+}
@@ -0,0 +1,330 @@
+//! Supervisor services for unprivileged fibers.
+//!
+//! Unprivileged code can't access NVIC, SCB, DWT, or ITM. The services in
+//! this module perform such operations on behalf of an unprivileged
+//! [`FiberProc`](crate::fib::FiberProc). Each service is a zero-sized type,
+//! which can be attached to a supervisor with the [`sv!`](crate::sv!) macro.
+//! Arguments and results are passed in the registers of the exception frame,
+//! so the services never dereference pointers supplied by the caller.
+//!
+//! An interrupt can be controlled through the services only after privileged
+//! code has granted it with [`grant_int`], proving the ownership of the
+//! interrupt token.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! # #![feature(const_fn_fn_ptr_basics)]
+//! # #![feature(marker_trait_attr)]
+//! use drone_cortexm::{
+//!     fib, sv,
+//!     sv::{
+//!         syscall, IntDisableService, IntEnableService, LogService, SwitchBackService,
+//!         SwitchContextService, TimeService, TriggerService,
+//!     },
+//!     thr,
+//!     thr::prelude::*,
+//! };
+//!
+//! sv! {
+//!     supervisor => pub Sv;
+//!     array => SERVICES;
+//!     services => {
+//!         SwitchContextService;
+//!         SwitchBackService;
+//!         TriggerService;
+//!         IntEnableService;
+//!         IntDisableService;
+//!         TimeService;
+//!         LogService;
+//!     }
+//! }
+//!
+//! thr! {
+//!     thread => pub Thr {};
+//!     local => pub ThrLocal {};
+//!     vtable => pub Vtable;
+//!     index => pub Thrs;
+//!     init => pub ThrsInit;
+//!     threads => {
+//!         interrupts => {
+//!             5: pub rcc;
+//!         };
+//!     };
+//! }
+//!
+//! # fn main() {
+//! let thr = unsafe { Thrs::take() };
+//! syscall::grant_int(thr.rcc);
+//! let rcc = thr.rcc;
+//! let fiber = fib::new_proc_unprivileged::<Sv, (), (), (), _>(0x400, move |(), _| {
+//!     let start = syscall::cycle_count::<Sv>();
+//!     syscall::enable_int::<Sv, _>(rcc).unwrap();
+//!     syscall::trigger::<Sv, _>(rcc).unwrap();
+//!     let _ = syscall::log::<Sv>(0, b"triggered");
+//!     let _ = syscall::cycle_count::<Sv>().wrapping_sub(start);
+//! });
+//! # }
+//! ```
+
+#![cfg_attr(feature = "std", allow(unreachable_code, unused_variables, unused_mut))]
+
+use crate::{
+    map::reg::dwt,
+    reg::prelude::*,
+    sv::{SvCallNum, SvService},
+    swo::{self, Port, PORTS_COUNT},
+    thr::IntToken,
+};
+use core::{
+    fmt,
+    ptr::write_volatile,
+    sync::atomic::{AtomicU32, Ordering},
+};
+use drone_core::token::Token;
+
+const NVIC_ISER: usize = 0xE000_E100;
+const NVIC_ICER: usize = 0xE000_E180;
+const NVIC_STIR: usize = 0xE000_EF00;
+
+const OK: u32 = 0;
+const DENIED: u32 = 1;
+
+/// Maximum number of bytes transferred by a single [`LogService`] call.
+const LOG_CHUNK: usize = 8;
+
+static GRANTED: [AtomicU32; 16] = [
+    AtomicU32::new(0),
+    AtomicU32::new(0),
+    AtomicU32::new(0),
+    AtomicU32::new(0),
+    AtomicU32::new(0),
+    AtomicU32::new(0),
+    AtomicU32::new(0),
+    AtomicU32::new(0),
+    AtomicU32::new(0),
+    AtomicU32::new(0),
+    AtomicU32::new(0),
+    AtomicU32::new(0),
+    AtomicU32::new(0),
+    AtomicU32::new(0),
+    AtomicU32::new(0),
+    AtomicU32::new(0),
+];
+
+/// A service to trigger an interrupt thread.
+///
+/// See [`trigger`] for details.
+pub struct TriggerService;
+
+/// A service to enable an interrupt.
+///
+/// See [`enable_int`] for details.
+pub struct IntEnableService;
+
+/// A service to disable an interrupt.
+///
+/// See [`disable_int`] for details.
+pub struct IntDisableService;
+
+/// A service to read the DWT cycle counter.
+///
+/// See [`cycle_count`] for details.
+pub struct TimeService;
+
+/// A service to write bytes to an ITM stimulus port.
+///
+/// See [`log`] for details.
+pub struct LogService;
+
+/// The supervisor service rejected the call.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Denied;
+
+/// Allows unprivileged code to control the interrupt `int` through the
+/// supervisor services.
+///
+/// This function must be called from privileged code. The grant table is
+/// placed in RAM, which must not be accessible by unprivileged fibers.
+#[allow(clippy::needless_pass_by_value)]
+#[inline]
+pub fn grant_int<T: IntToken>(_int: T) {
+    GRANTED[T::INT_NUM >> 5].fetch_or(1 << (T::INT_NUM & 0b1_1111), Ordering::Relaxed);
+}
+
+/// Revokes the permission given by [`grant_int`].
+#[allow(clippy::needless_pass_by_value)]
+#[inline]
+pub fn revoke_int<T: IntToken>(_int: T) {
+    GRANTED[T::INT_NUM >> 5].fetch_and(!(1 << (T::INT_NUM & 0b1_1111)), Ordering::Relaxed);
+}
+
+/// Triggers the interrupt thread `int`, like
+/// [`ThrExec::trigger`](crate::thr::ThrExec::trigger) does.
+///
+/// # Errors
+///
+/// If the interrupt is not granted with [`grant_int`].
+#[allow(clippy::needless_pass_by_value)]
+#[inline]
+pub fn trigger<Sv: SvCallNum<TriggerService>, T: IntToken>(_int: T) -> Result<(), Denied> {
+    result(unsafe { syscall::<Sv, TriggerService>(T::INT_NUM as u32, 0, 0, 0) })
+}
+
+/// Enables the interrupt `int` within NVIC.
+///
+/// # Errors
+///
+/// If the interrupt is not granted with [`grant_int`].
+#[allow(clippy::needless_pass_by_value)]
+#[inline]
+pub fn enable_int<Sv: SvCallNum<IntEnableService>, T: IntToken>(_int: T) -> Result<(), Denied> {
+    result(unsafe { syscall::<Sv, IntEnableService>(T::INT_NUM as u32, 0, 0, 0) })
+}
+
+/// Disables the interrupt `int` within NVIC.
+///
+/// # Errors
+///
+/// If the interrupt is not granted with [`grant_int`].
+#[allow(clippy::needless_pass_by_value)]
+#[inline]
+pub fn disable_int<Sv: SvCallNum<IntDisableService>, T: IntToken>(_int: T) -> Result<(), Denied> {
+    result(unsafe { syscall::<Sv, IntDisableService>(T::INT_NUM as u32, 0, 0, 0) })
+}
+
+/// Returns the current value of the DWT cycle counter.
+///
+/// The counter should be enabled by privileged code, e.g. with
+/// [`Dwt`](crate::drv::dwt::Dwt).
+#[inline]
+pub fn cycle_count<Sv: SvCallNum<TimeService>>() -> u32 {
+    unsafe { syscall::<Sv, TimeService>(0, 0, 0, 0) }
+}
+
+/// Writes `bytes` to the ITM stimulus port number `port`.
+///
+/// The bytes are transferred in chunks of up to 8 bytes per supervisor call,
+/// so the output may be interleaved with concurrent writes to the same port.
+/// The call is a no-op if the port is not enabled.
+///
+/// # Errors
+///
+/// If `port` is more than or equal to [`PORTS_COUNT`].
+#[inline]
+pub fn log<Sv: SvCallNum<LogService>>(port: u8, bytes: &[u8]) -> Result<(), Denied> {
+    for chunk in bytes.chunks(LOG_CHUNK) {
+        let mut words = [0; 2];
+        for (i, byte) in chunk.iter().enumerate() {
+            words[i >> 2] |= u32::from(*byte) << ((i & 0b11) << 3);
+        }
+        let len = chunk.len() as u32;
+        result(unsafe { syscall::<Sv, LogService>(u32::from(port), len, words[0], words[1]) })?;
+    }
+    Ok(())
+}
+
+impl SvService for TriggerService {
+    unsafe extern "C" fn handler(&mut self) {
+        unsafe {
+            with_frame(self, |frame| {
+                if !is_granted(frame[0]) {
+                    return DENIED;
+                }
+                write_volatile(NVIC_STIR as *mut u32, frame[0]);
+                OK
+            });
+        }
+    }
+}
+
+impl SvService for IntEnableService {
+    unsafe extern "C" fn handler(&mut self) {
+        unsafe { with_frame(self, |frame| nvic_write(NVIC_ISER, frame[0])) };
+    }
+}
+
+impl SvService for IntDisableService {
+    unsafe extern "C" fn handler(&mut self) {
+        unsafe { with_frame(self, |frame| nvic_write(NVIC_ICER, frame[0])) };
+    }
+}
+
+impl SvService for TimeService {
+    unsafe extern "C" fn handler(&mut self) {
+        unsafe { with_frame(self, |_| dwt::Cyccnt::<Urt>::take().load().cyccnt()) };
+    }
+}
+
+impl SvService for LogService {
+    unsafe extern "C" fn handler(&mut self) {
+        unsafe {
+            with_frame(self, |frame| {
+                let (port, len) = (frame[0], frame[1] as usize);
+                if port >= u32::from(PORTS_COUNT) || len > LOG_CHUNK {
+                    return DENIED;
+                }
+                if swo::is_port_enabled(port as usize) {
+                    let mut bytes = [0; LOG_CHUNK];
+                    for (i, byte) in bytes.iter_mut().enumerate().take(len) {
+                        *byte = (frame[2 + (i >> 2)] >> ((i & 0b11) << 3)) as u8;
+                    }
+                    Port::new(port as u8).write_bytes(&bytes[..len]);
+                }
+                OK
+            });
+        }
+    }
+}
+
+impl fmt::Display for Denied {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "supervisor call denied")
+    }
+}
+
+/// Calls `SVC num` with the arguments in `r0`-`r3`, and returns `r0`.
+#[inline(always)]
+unsafe fn syscall<Sv: SvCallNum<T>, T: SvService>(mut r0: u32, r1: u32, r2: u32, r3: u32) -> u32 {
+    #[cfg(feature = "std")]
+    return unimplemented!();
+    unsafe {
+        llvm_asm!("
+            svc $4
+        "   : "+{r0}"(r0)
+            : "{r1}"(r1), "{r2}"(r2), "{r3}"(r3), "i"(Sv::NUM)
+            : "memory"
+            : "volatile"
+        );
+    }
+    r0
+}
+
+/// Passes the stacked `r0`-`r3` to `f`, and stores the result to the stacked
+/// `r0`. A zero-sized service receives the exception frame as `self`.
+#[allow(clippy::cast_ptr_alignment)]
+unsafe fn with_frame<T: SvService, F: FnOnce(&[u32; 4]) -> u32>(service: &mut T, f: F) {
+    let frame = service as *mut T as *mut [u32; 4];
+    unsafe { (*frame)[0] = f(&*frame) };
+}
+
+fn result(code: u32) -> Result<(), Denied> {
+    if code == OK { Ok(()) } else { Err(Denied) }
+}
+
+fn is_granted(int_num: u32) -> bool {
+    let int_num = int_num as usize;
+    GRANTED
+        .get(int_num >> 5)
+        .map_or(false, |word| word.load(Ordering::Relaxed) & 1 << (int_num & 0b1_1111) != 0)
+}
+
+unsafe fn nvic_write(base: usize, int_num: u32) -> u32 {
+    if !is_granted(int_num) {
+        return DENIED;
+    }
+    unsafe {
+        write_volatile((base as *mut u32).add(int_num as usize >> 5), 1 << (int_num & 0b1_1111));
+    }
+    OK
+}
@@ -0,0 +1,365 @@
+//! Non-blocking buffered SWO output.
+//!
+//! Writing to an ITM stimulus port spins while the stimulus FIFO is full, so
+//! a log call from a high-priority thread can stall for a long time at a low
+//! SWO baud rate. [`Buffer`] puts a lock-free ring buffer in front of the
+//! ports: writers only copy the bytes into the buffer, and a low-priority
+//! thread later drains it to the ports with [`Buffer::drain`].
+//!
+//! Any number of threads can write to the buffer concurrently. A message that
+//! doesn't fit into the free space is handled according to [`DropPolicy`].
+//!
+//! # Examples
+//!
+//! ```no_run
+//! # #![feature(const_fn_fn_ptr_basics)]
+//! # #![feature(marker_trait_attr)]
+//! use drone_cortexm::{
+//!     fib::{self, FiberState},
+//!     swo::buffer::{Buffer, DrainThread, DropPolicy},
+//!     thr,
+//!     thr::prelude::*,
+//! };
+//!
+//! thr! {
+//!     thread => pub Thr {};
+//!     local => pub ThrLocal {};
+//!     vtable => pub Vtable;
+//!     index => pub Thrs;
+//!     init => pub ThrsInit;
+//!     threads => {
+//!         exceptions => {
+//!             pub pend_sv priority = 0xF0;
+//!         };
+//!     };
+//! }
+//!
+//! static SWO_BUFFER: Buffer<[u8; 1024]> =
+//!     Buffer::new([0; 1024]).policy(DropPolicy::DropNewest).drain_thread(DrainThread::PEND_SV);
+//!
+//! # fn main() {
+//! let thr = thr::init(unsafe { ThrsInit::take() });
+//! thr.pend_sv.add_fn(|| -> FiberState<(), ()> {
+//!     SWO_BUFFER.drain();
+//!     fib::Yielded(())
+//! });
+//! SWO_BUFFER.write(0, b"hello\n");
+//! # }
+//! ```
+//!
+//! The buffer can be set as the default logger with
+//! [`swo::set_log!`](crate::swo::set_log!):
+//!
+//! ```
+//! # #![feature(proc_macro_hygiene)]
+//! use drone_cortexm::{cortexm_reg_tokens, swo, swo::buffer::Buffer};
+//!
+//! cortexm_reg_tokens! {
+//!     index => Regs;
+//!     exclude => {
+//!         dwt_cyccnt,
+//!         itm_tpr, itm_tcr, itm_lar,
+//!         tpiu_acpr, tpiu_sppr, tpiu_ffcr,
+//!     }
+//! }
+//!
+//! static SWO_BUFFER: Buffer<[u8; 256]> = Buffer::new([0; 256]);
+//!
+//! swo::set_log!(buffer => SWO_BUFFER);
+//! ```
+
+use super::{is_port_enabled, Port};
+use crate::{
+    map::reg::scb,
+    reg::prelude::*,
+    thr::{wake::WakeInt, IntToken},
+    util::assert,
+};
+use core::{
+    cell::UnsafeCell,
+    slice,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+};
+use drone_core::token::Token;
+
+/// Size of the record header: the port number and the payload length.
+const HEADER_SIZE: usize = 3;
+
+/// Lock-free ring buffer for the ITM stimulus ports.
+///
+/// The buffer storage `S` is a byte array `[u8; N]`. A `&Buffer<[u8; N]>`
+/// reference can be coerced to `&Buffer<[u8]>` to erase the storage size.
+pub struct Buffer<S: ?Sized> {
+    reserved: AtomicUsize,
+    committed: AtomicUsize,
+    consumed: AtomicUsize,
+    writers: AtomicUsize,
+    draining: AtomicBool,
+    dropped: AtomicUsize,
+    policy: DropPolicy,
+    drain_thread: DrainThread,
+    capacity: usize,
+    storage: UnsafeCell<S>,
+}
+
+/// What to do with a message that doesn't fit into the [`Buffer`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DropPolicy {
+    /// Drop the message and increment the overflow counter.
+    DropNewest,
+    /// Drain the buffer if possible, and write the message directly to the
+    /// port, blocking the writer. The message can be reordered with the
+    /// buffered messages if the buffer is being drained by a preempted
+    /// thread.
+    WriteThrough,
+}
+
+/// The thread that drains the [`Buffer`], which is triggered after every
+/// write.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DrainThread(DrainTrigger);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum DrainTrigger {
+    Manual,
+    PendSv,
+    Int(usize),
+}
+
+unsafe impl<S: ?Sized + Send> Sync for Buffer<S> {}
+
+impl<const N: usize> Buffer<[u8; N]> {
+    /// Creates a new buffer backed by the byte array `storage`. The buffer
+    /// drops messages on overflow, and is drained manually.
+    ///
+    /// # Panics
+    ///
+    /// If the size of `storage` is not a power of two, or is less than four
+    /// bytes.
+    #[inline]
+    pub const fn new(storage: [u8; N]) -> Self {
+        let capacity = N;
+        assert(capacity.is_power_of_two() && capacity > HEADER_SIZE);
+        Self {
+            reserved: AtomicUsize::new(0),
+            committed: AtomicUsize::new(0),
+            consumed: AtomicUsize::new(0),
+            writers: AtomicUsize::new(0),
+            draining: AtomicBool::new(false),
+            dropped: AtomicUsize::new(0),
+            policy: DropPolicy::DropNewest,
+            drain_thread: DrainThread::MANUAL,
+            capacity,
+            storage: UnsafeCell::new(storage),
+        }
+    }
+
+    /// Sets the overflow policy.
+    #[inline]
+    pub const fn policy(mut self, policy: DropPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Sets the thread to trigger after writes.
+    #[inline]
+    pub const fn drain_thread(mut self, drain_thread: DrainThread) -> Self {
+        self.drain_thread = drain_thread;
+        self
+    }
+}
+
+impl<S: ?Sized> Buffer<S> {
+    /// Returns the size of the storage in bytes.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns `true` if there are no messages to drain.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.committed.load(Ordering::Acquire) == self.consumed.load(Ordering::Acquire)
+    }
+
+    /// Returns the number of messages dropped on overflow.
+    #[inline]
+    pub fn dropped(&self) -> usize {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Queues `bytes` for the ITM stimulus port number `port`, and triggers
+    /// the drain thread.
+    ///
+    /// Each message takes three bytes of the buffer in addition to `bytes`.
+    /// Returns `false` if the message didn't fit into the buffer, in which
+    /// case it is handled according to the [`DropPolicy`].
+    pub fn write(&self, port: u8, bytes: &[u8]) -> bool {
+        let mut buffered = true;
+        for chunk in bytes.chunks(usize::from(u16::MAX)) {
+            buffered &= self.write_message(port, chunk);
+        }
+        buffered
+    }
+
+    /// Writes all queued messages to the ITM stimulus ports. Messages to
+    /// disabled ports are discarded.
+    ///
+    /// Returns `false` if the buffer is already being drained by a preempted
+    /// thread.
+    pub fn drain(&self) -> bool {
+        if self.draining.swap(true, Ordering::Acquire) {
+            return false;
+        }
+        let mut consumed = self.consumed.load(Ordering::Relaxed);
+        loop {
+            let committed = self.committed.load(Ordering::Acquire);
+            if consumed == committed {
+                break;
+            }
+            let mut header = [0; HEADER_SIZE];
+            let (first, second) = unsafe { self.slices(consumed, HEADER_SIZE) };
+            header[..first.len()].copy_from_slice(first);
+            header[first.len()..].copy_from_slice(second);
+            let [port, len @ ..] = header;
+            let len = usize::from(u16::from_le_bytes(len));
+            if is_port_enabled(usize::from(port)) {
+                let (first, second) =
+                    unsafe { self.slices(consumed.wrapping_add(HEADER_SIZE), len) };
+                Port::new(port).write_bytes(first).write_bytes(second);
+            }
+            consumed = consumed.wrapping_add(HEADER_SIZE + len);
+            self.consumed.store(consumed, Ordering::Release);
+        }
+        self.draining.store(false, Ordering::Release);
+        true
+    }
+
+    /// Triggers the drain thread. The buffer is not drained if the drain
+    /// thread is [`DrainThread::Manual`].
+    #[inline]
+    pub fn trigger(&self) {
+        self.drain_thread.trigger();
+    }
+
+    /// Drains the buffer and blocks until all pending packets are
+    /// transmitted.
+    ///
+    /// The buffer is drained synchronously in the calling thread, which spins
+    /// while the stimulus FIFO is full. Use [`Buffer::trigger`] to defer the
+    /// draining to the drain thread.
+    pub fn flush(&self) {
+        self.drain();
+        super::flush();
+    }
+
+    fn write_message(&self, port: u8, bytes: &[u8]) -> bool {
+        let len = HEADER_SIZE + bytes.len();
+        self.writers.fetch_add(1, Ordering::Acquire);
+        let mut start = self.reserved.load(Ordering::Relaxed);
+        let buffered = loop {
+            if start.wrapping_sub(self.consumed.load(Ordering::Acquire)) + len > self.capacity {
+                break false;
+            }
+            match self.reserved.compare_exchange_weak(
+                start,
+                start.wrapping_add(len),
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => break true,
+                Err(reserved) => start = reserved,
+            }
+        };
+        if buffered {
+            let [len_lo, len_hi] = (bytes.len() as u16).to_le_bytes();
+            unsafe {
+                self.copy(start, &[port, len_lo, len_hi]);
+                self.copy(start.wrapping_add(HEADER_SIZE), bytes);
+            }
+        }
+        self.commit();
+        if !buffered {
+            match self.policy {
+                DropPolicy::DropNewest => {
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                }
+                DropPolicy::WriteThrough => {
+                    self.drain();
+                    Port::new(port).write_bytes(bytes);
+                }
+            }
+        }
+        buffered
+    }
+
+    /// Publishes the reserved space to the drain thread.
+    ///
+    /// Writers preempting each other complete in the LIFO order, so the
+    /// reserved space is published only by the outermost writer, when all
+    /// nested writers have finished copying.
+    fn commit(&self) {
+        if self.writers.fetch_sub(1, Ordering::AcqRel) != 1 {
+            return;
+        }
+        let mut committed = self.committed.load(Ordering::Relaxed);
+        loop {
+            let reserved = self.reserved.load(Ordering::Acquire);
+            if reserved == committed {
+                break;
+            }
+            match self.committed.compare_exchange_weak(
+                committed,
+                reserved,
+                Ordering::Release,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => committed = reserved,
+                Err(current) => committed = current,
+            }
+        }
+        self.drain_thread.trigger();
+    }
+
+    unsafe fn copy(&self, index: usize, bytes: &[u8]) {
+        let storage = self.storage.get() as *mut u8;
+        for (i, &byte) in bytes.iter().enumerate() {
+            unsafe { storage.add(index.wrapping_add(i) & self.capacity - 1).write(byte) };
+        }
+    }
+
+    unsafe fn slices(&self, index: usize, len: usize) -> (&[u8], &[u8]) {
+        let storage = self.storage.get() as *const u8;
+        let start = index & self.capacity - 1;
+        let first = len.min(self.capacity - start);
+        unsafe {
+            (
+                slice::from_raw_parts(storage.add(start), first),
+                slice::from_raw_parts(storage, len - first),
+            )
+        }
+    }
+}
+
+impl DrainThread {
+    /// No thread is triggered. The buffer is drained manually.
+    pub const MANUAL: Self = Self(DrainTrigger::Manual);
+    /// The PendSV exception.
+    pub const PEND_SV: Self = Self(DrainTrigger::PendSv);
+
+    /// The interrupt thread of the token type `T`.
+    #[inline]
+    pub const fn int<T: IntToken>() -> Self {
+        Self(DrainTrigger::Int(T::INT_NUM))
+    }
+
+    fn trigger(self) {
+        match self.0 {
+            DrainTrigger::Manual => {}
+            DrainTrigger::PendSv => {
+                unsafe { scb::Icsr::<Urt>::take() }.store(|r| r.set_pendsvset());
+            }
+            DrainTrigger::Int(int_num) => WakeInt::new(int_num).wake(),
+        }
+    }
+}
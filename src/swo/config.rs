@@ -0,0 +1,238 @@
+use crate::{
+    map::reg::{dwt, itm, scb},
+    reg::prelude::*,
+    util::assert,
+};
+use core::fmt;
+use drone_core::token::Token;
+
+const ITM_LAR_UNLOCK: u32 = 0xC5AC_CE55;
+
+/// ITM and DWT trace configuration.
+///
+/// The configuration is applied on top of the ITM setup made by the debug
+/// probe, which enables ITM and the stimulus ports. All features not
+/// requested through the builder methods are turned off by
+/// [`apply`](Config::apply).
+///
+/// # Examples
+///
+/// ```no_run
+/// use drone_cortexm::swo::{Config, GlobalTimestamp, SyncPeriod, TimestampPrescaler};
+///
+/// Config::new()
+///     .local_timestamps(TimestampPrescaler::Div4)
+///     .global_timestamps(GlobalTimestamp::Cycles8192)
+///     .sync_packets(SyncPeriod::Cycles16M)
+///     .exception_trace()
+///     .pc_sampling(16384)
+///     .apply()
+///     .expect("trace is not supported");
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Config {
+    local_timestamps: Option<TimestampPrescaler>,
+    global_timestamps: Option<GlobalTimestamp>,
+    sync_packets: Option<SyncPeriod>,
+    exception_trace: bool,
+    pc_sampling: Option<PcSampling>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct PcSampling {
+    cyctap: bool,
+    postpreset: u32,
+}
+
+/// Prescaler of the local timestamp counter.
+#[allow(missing_docs)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u32)]
+pub enum TimestampPrescaler {
+    Div1 = 0,
+    Div4 = 1,
+    Div16 = 2,
+    Div64 = 3,
+}
+
+/// Frequency of the global timestamp packets.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u32)]
+pub enum GlobalTimestamp {
+    /// A packet every 128 cycles.
+    Cycles128 = 1,
+    /// A packet every 8192 cycles.
+    Cycles8192 = 2,
+    /// A packet after every packet, if the output FIFO is empty.
+    EveryPacket = 3,
+}
+
+/// Period of the synchronization packets, tapped from the DWT cycle counter.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u32)]
+pub enum SyncPeriod {
+    /// A packet every 2^24 cycles.
+    Cycles16M = 1,
+    /// A packet every 2^26 cycles.
+    Cycles64M = 2,
+    /// A packet every 2^28 cycles.
+    Cycles256M = 3,
+}
+
+/// Error returned from [`Config::apply`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConfigError {
+    /// The DWT cycle counter, which is required for PC sampling and periodic
+    /// synchronization packets, is not implemented.
+    NoCycleCounter,
+    /// The DWT trace packets, which are required for exception tracing and PC
+    /// sampling, are not implemented.
+    NoTracePackets,
+}
+
+impl Config {
+    /// Creates a new configuration with all trace features turned off.
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            local_timestamps: None,
+            global_timestamps: None,
+            sync_packets: None,
+            exception_trace: false,
+            pc_sampling: None,
+        }
+    }
+
+    /// Enables local timestamp packets, counting the processor cycles divided
+    /// by `prescaler`.
+    #[inline]
+    pub const fn local_timestamps(mut self, prescaler: TimestampPrescaler) -> Self {
+        self.local_timestamps = Some(prescaler);
+        self
+    }
+
+    /// Enables global timestamp packets with the frequency `frequency`.
+    #[inline]
+    pub const fn global_timestamps(mut self, frequency: GlobalTimestamp) -> Self {
+        self.global_timestamps = Some(frequency);
+        self
+    }
+
+    /// Enables periodic synchronization packets with the period `period`.
+    #[inline]
+    pub const fn sync_packets(mut self, period: SyncPeriod) -> Self {
+        self.sync_packets = Some(period);
+        self
+    }
+
+    /// Enables exception entry, exit, and return packets.
+    #[inline]
+    pub const fn exception_trace(mut self) -> Self {
+        self.exception_trace = true;
+        self
+    }
+
+    /// Enables periodic PC sample packets, one per `interval` cycles.
+    ///
+    /// # Panics
+    ///
+    /// If `interval` is not a multiple of 64 in the range `64..=1024`, or a
+    /// multiple of 1024 in the range `1024..=16384`.
+    #[inline]
+    pub const fn pc_sampling(mut self, interval: u32) -> Self {
+        let (cyctap, unit) = if interval <= 1024 { (false, 64) } else { (true, 1024) };
+        let count = interval / unit;
+        assert(interval % unit == 0 && count != 0 && count <= 16);
+        self.pc_sampling = Some(PcSampling { cyctap, postpreset: count - 1 });
+        self
+    }
+
+    /// Writes the configuration to ITM and DWT.
+    ///
+    /// This function sets `DEMCR.TRCENA`, unlocks ITM, and enables the DWT
+    /// cycle counter if a requested feature depends on it.
+    ///
+    /// # Errors
+    ///
+    /// If a requested feature is not implemented by the DWT unit. Only
+    /// `DEMCR.TRCENA` is written in this case.
+    pub fn apply(&self) -> Result<(), ConfigError> {
+        let mut scb_demcr = unsafe { scb::Demcr::<Urt>::take() };
+        let mut dwt_ctrl = unsafe { dwt::Ctrl::<Urt>::take() };
+        let mut itm_lar = unsafe { itm::Lar::<Urt>::take() };
+        let mut itm_tcr = unsafe { itm::Tcr::<Urt>::take() };
+        let cycle_counter = self.sync_packets.is_some() || self.pc_sampling.is_some();
+        let trace_packets = self.exception_trace || self.pc_sampling.is_some();
+        scb_demcr.modify(|r| r.set_trcena());
+        let ctrl = dwt_ctrl.load();
+        if cycle_counter && ctrl.nocyccnt() {
+            return Err(ConfigError::NoCycleCounter);
+        }
+        if trace_packets && ctrl.notrcpkt() {
+            return Err(ConfigError::NoTracePackets);
+        }
+        // Sampling and tracing are enabled only after the counters are set up.
+        dwt_ctrl.modify(|r| {
+            r.clear_pcsampleena().clear_exctrcena().write_synctap(0);
+            r.clear_cyctap().write_postpreset(0);
+            if cycle_counter {
+                r.set_cyccntena();
+            }
+            if let Some(period) = self.sync_packets {
+                r.write_synctap(period as u32);
+            }
+            if let Some(PcSampling { cyctap, postpreset }) = self.pc_sampling {
+                if cyctap {
+                    r.set_cyctap();
+                }
+                r.write_postpreset(postpreset);
+            }
+            r
+        });
+        dwt_ctrl.modify(|r| {
+            if self.exception_trace {
+                r.set_exctrcena();
+            }
+            if self.pc_sampling.is_some() {
+                r.set_pcsampleena();
+            }
+            r
+        });
+        itm_lar.store(|r| r.write_unlock(ITM_LAR_UNLOCK));
+        itm_tcr.modify(|r| {
+            r.write_trace_bus_id(1);
+            r.clear_tsena().write_ts_prescale(0).write_gtsfreq(0);
+            r.clear_syncena().clear_txena();
+            if let Some(prescaler) = self.local_timestamps {
+                r.set_tsena().write_ts_prescale(prescaler as u32);
+            }
+            if let Some(frequency) = self.global_timestamps {
+                r.write_gtsfreq(frequency as u32);
+            }
+            if self.sync_packets.is_some() {
+                r.set_syncena();
+            }
+            if trace_packets {
+                r.set_txena();
+            }
+            r
+        });
+        Ok(())
+    }
+}
+
+impl Default for Config {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoCycleCounter => write!(f, "DWT cycle counter is not implemented."),
+            Self::NoTracePackets => write!(f, "DWT trace packets are not implemented."),
+        }
+    }
+}
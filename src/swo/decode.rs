@@ -0,0 +1,603 @@
+//! Host-side decoder of the SWO byte stream.
+//!
+//! [`Decoder`] splits a raw SWO byte stream, as captured by a debug probe,
+//! into ITM and DWT packets. [`Demux`] additionally collects the payload of
+//! instrumentation packets, which are produced by [`Port`](super::Port), into
+//! separate byte streams for each of [`PORTS_COUNT`] ports. [`MessageDecoder`]
+//! rebuilds the messages written by [`log!`](super::log!) from such a port
+//! stream.
+//!
+//! # Examples
+//!
+//! ```
+//! use drone_cortexm::swo::decode::{Demux, Packet};
+//!
+//! let mut demux = Demux::new();
+//! let packets = demux.feed(&[0x03, b'H', b'e', b'l', b'l', 0x01, b'o', 0x70]);
+//! assert_eq!(packets, [Packet::Overflow]);
+//! assert_eq!(demux.port(0), b"Hello");
+//! ```
+
+use super::{deferred, PORTS_COUNT};
+use std::{collections::HashMap, fmt, mem};
+
+/// Decoded SWO packet.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Packet {
+    /// Synchronization packet.
+    Sync,
+    /// Overflow packet. Some packets were lost.
+    Overflow,
+    /// Software source packet, written to an ITM stimulus port.
+    Instrumentation {
+        /// ITM stimulus port number.
+        port: u8,
+        /// The number of payload bytes: 1, 2, or 4.
+        size: u8,
+        /// The payload in little-endian order.
+        value: u32,
+    },
+    /// Local timestamp packet.
+    LocalTimestamp {
+        /// The number of timestamp clock cycles since the previous local
+        /// timestamp.
+        delta: u32,
+        /// Relation of the timestamp to the corresponding packet.
+        relation: TimestampRelation,
+    },
+    /// Global timestamp packet.
+    ///
+    /// The high-order bits are taken from the last global timestamp packet of
+    /// the second format, which is not reported separately.
+    GlobalTimestamp {
+        /// The value of the global timestamp counter.
+        timestamp: u64,
+        /// The high-order bits have changed since the last report.
+        wrap: bool,
+        /// The system has asserted the clock change input.
+        clock_change: bool,
+    },
+    /// Extension packet.
+    Extension {
+        /// The packet is generated by hardware.
+        hardware: bool,
+        /// Extension information bits.
+        info: u32,
+    },
+    /// DWT event counter packet. Each set bit corresponds to an overflowed
+    /// counter.
+    EventCounter(u8),
+    /// DWT exception trace packet.
+    ExceptionTrace {
+        /// Exception number.
+        number: u16,
+        /// Exception event.
+        function: ExceptionFunction,
+    },
+    /// DWT periodic PC sample packet. `None` if the processor is sleeping.
+    PcSample(Option<u32>),
+    /// DWT data trace packet.
+    DataTrace {
+        /// DWT comparator number.
+        comparator: u8,
+        /// Kind of the traced value.
+        kind: DataTraceKind,
+        /// The number of payload bytes: 1, 2, or 4.
+        size: u8,
+        /// Traced value.
+        value: u32,
+    },
+    /// Hardware source packet with an unknown discriminator.
+    Hardware {
+        /// Packet type discriminator.
+        discriminator: u8,
+        /// The number of payload bytes: 1, 2, or 4.
+        size: u8,
+        /// The payload in little-endian order.
+        value: u32,
+    },
+    /// Reserved or malformed header byte.
+    Unknown(u8),
+}
+
+/// Relation of a local timestamp to the corresponding packet.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimestampRelation {
+    /// The timestamp is synchronous to the packet.
+    Sync,
+    /// The timestamp is delayed relative to the packet.
+    TimestampDelayed,
+    /// The packet is delayed relative to the event.
+    PacketDelayed,
+    /// Both the timestamp and the packet are delayed.
+    BothDelayed,
+}
+
+/// Exception trace event.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExceptionFunction {
+    /// The exception is entered.
+    Entered,
+    /// The exception is exited.
+    Exited,
+    /// The processor has returned to the exception.
+    Returned,
+}
+
+/// Kind of a DWT data trace value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DataTraceKind {
+    /// PC value of the matched instruction.
+    Pc,
+    /// Offset of the matched data address.
+    Address,
+    /// Read data value.
+    Read,
+    /// Written data value.
+    Write,
+}
+
+/// Table of the format strings interned by [`log!`](super::log!), keyed by
+/// their addresses.
+#[derive(Clone, Debug, Default)]
+pub struct FormatTable {
+    formats: HashMap<u32, String>,
+}
+
+/// Streaming decoder of [`log!`](super::log!) messages.
+#[derive(Debug)]
+pub struct MessageDecoder {
+    table: FormatTable,
+    buffer: Vec<u8>,
+}
+
+/// Error returned from [`MessageDecoder::feed`].
+///
+/// The decoder can't find the next message boundary after an error, so the
+/// rest of the buffered stream is discarded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MessageError {
+    /// The format string address is not in the table.
+    UnknownFormat(u32),
+    /// The argument tag is not known.
+    InvalidTag(u8),
+    /// The argument value is not valid for its tag or its format spec.
+    InvalidArgument,
+}
+
+/// Streaming SWO packet decoder.
+#[derive(Debug, Default)]
+pub struct Decoder {
+    buffer: Vec<u8>,
+    global_timestamp: u64,
+}
+
+/// SWO packet decoder, which demultiplexes the ITM stimulus ports.
+#[derive(Debug)]
+pub struct Demux {
+    decoder: Decoder,
+    ports: Vec<Vec<u8>>,
+}
+
+impl Decoder {
+    /// Creates a new decoder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Decodes the next chunk of the SWO byte stream, and returns all
+    /// completed packets.
+    ///
+    /// An incomplete packet at the end of `bytes` is buffered until the next
+    /// call.
+    pub fn feed(&mut self, bytes: &[u8]) -> Vec<Packet> {
+        self.buffer.extend_from_slice(bytes);
+        let mut packets = Vec::new();
+        let mut offset = 0;
+        while let Some((packet, len)) = self.parse(offset) {
+            packets.extend(packet);
+            offset += len;
+        }
+        self.buffer.drain(..offset);
+        packets
+    }
+
+    /// Returns the number of buffered bytes of an incomplete packet.
+    pub fn pending(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Parses a packet at `offset` of the buffer. Returns `None` if the packet
+    /// is incomplete, or `Some((None, len))` if the packet only updates the
+    /// decoder state.
+    fn parse(&mut self, offset: usize) -> Option<(Option<Packet>, usize)> {
+        let bytes = &self.buffer[offset..];
+        let header = *bytes.first()?;
+        let (packet, len) = match header {
+            0x00 => {
+                let zeros = bytes.iter().take_while(|&&byte| byte == 0).count();
+                match *bytes.get(zeros)? {
+                    0x80 if zeros >= 5 => (Packet::Sync, zeros + 1),
+                    _ => (Packet::Unknown(header), 1),
+                }
+            }
+            0x70 => (Packet::Overflow, 1),
+            0x94 => {
+                let (value, len) = continued(&bytes[1..], 4)?;
+                let (bits, wrap, clock_change) = if len == 4 {
+                    let last = bytes[len];
+                    (26, last & 1 << 6 != 0, last & 1 << 5 != 0)
+                } else {
+                    (7 * len as u32, false, false)
+                };
+                let mask = (1 << bits) - 1;
+                self.global_timestamp = self.global_timestamp & !mask | value & mask;
+                let timestamp = self.global_timestamp;
+                (Packet::GlobalTimestamp { timestamp, wrap, clock_change }, len + 1)
+            }
+            0xB4 => {
+                let (value, len) = continued(&bytes[1..], 6)?;
+                self.global_timestamp = self.global_timestamp & 0x3FF_FFFF | value << 26;
+                return Some((None, len + 1));
+            }
+            _ if header & 0x0F == 0 => {
+                if header & 0xC0 == 0xC0 {
+                    let (delta, len) = continued(&bytes[1..], 4)?;
+                    let relation = match header >> 4 & 0b11 {
+                        0 => TimestampRelation::Sync,
+                        1 => TimestampRelation::TimestampDelayed,
+                        2 => TimestampRelation::PacketDelayed,
+                        _ => TimestampRelation::BothDelayed,
+                    };
+                    (Packet::LocalTimestamp { delta: delta as u32, relation }, len + 1)
+                } else if header & 0x80 == 0 {
+                    let delta = u32::from(header >> 4);
+                    let relation = TimestampRelation::Sync;
+                    (Packet::LocalTimestamp { delta, relation }, 1)
+                } else {
+                    (Packet::Unknown(header), 1)
+                }
+            }
+            _ if header & 0x0B == 0x08 => {
+                let hardware = header & 1 << 2 != 0;
+                let mut info = u32::from(header >> 4 & 0b111);
+                let mut len = 1;
+                if header & 0x80 != 0 {
+                    let (value, payload_len) = continued(&bytes[1..], 4)?;
+                    info |= (value as u32) << 3;
+                    len += payload_len;
+                }
+                (Packet::Extension { hardware, info }, len)
+            }
+            _ if header & 0b11 != 0 => {
+                let size = [0, 1, 2, 4][usize::from(header & 0b11)];
+                let payload = bytes.get(1..=usize::from(size))?;
+                let value = little_endian(payload);
+                let packet = if header & 1 << 2 == 0 {
+                    Packet::Instrumentation { port: header >> 3, size, value }
+                } else {
+                    hardware_packet(header >> 3, size, value)
+                };
+                (packet, usize::from(size) + 1)
+            }
+            _ => (Packet::Unknown(header), 1),
+        };
+        Some((Some(packet), len))
+    }
+}
+
+impl Demux {
+    /// Creates a new demultiplexer.
+    pub fn new() -> Self {
+        Self { decoder: Decoder::new(), ports: vec![Vec::new(); usize::from(PORTS_COUNT)] }
+    }
+
+    /// Decodes the next chunk of the SWO byte stream. The payload of
+    /// instrumentation packets is appended to the respective port streams,
+    /// and all other completed packets are returned.
+    pub fn feed(&mut self, bytes: &[u8]) -> Vec<Packet> {
+        let mut packets = self.decoder.feed(bytes);
+        let ports = &mut self.ports;
+        packets.retain(|packet| match *packet {
+            Packet::Instrumentation { port, size, value } => {
+                ports[usize::from(port)]
+                    .extend_from_slice(&value.to_le_bytes()[..usize::from(size)]);
+                false
+            }
+            _ => true,
+        });
+        packets
+    }
+
+    /// Returns the collected byte stream of the ITM stimulus port `port`.
+    ///
+    /// # Panics
+    ///
+    /// If `port` is more than or equal to [`PORTS_COUNT`].
+    pub fn port(&self, port: u8) -> &[u8] {
+        &self.ports[usize::from(port)]
+    }
+
+    /// Takes the collected byte stream of the ITM stimulus port `port`,
+    /// leaving it empty.
+    ///
+    /// # Panics
+    ///
+    /// If `port` is more than or equal to [`PORTS_COUNT`].
+    pub fn take_port(&mut self, port: u8) -> Vec<u8> {
+        mem::take(&mut self.ports[usize::from(port)])
+    }
+}
+
+impl Default for Demux {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FormatTable {
+    /// Creates an empty table.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a table from the contents `bytes` of the `.drone_log` section
+    /// loaded at `address`.
+    pub fn from_section(address: u32, bytes: &[u8]) -> Self {
+        let mut table = Self::new();
+        let mut offset = 0;
+        for format in bytes.split(|&byte| byte == 0) {
+            if !format.is_empty() {
+                table.insert(address + offset, String::from_utf8_lossy(format).into_owned());
+            }
+            offset += format.len() as u32 + 1;
+        }
+        table
+    }
+
+    /// Inserts the format string `format` at the address `address`.
+    pub fn insert(&mut self, address: u32, format: impl Into<String>) {
+        self.formats.insert(address, format.into());
+    }
+
+    /// Returns the format string at the address `address`.
+    pub fn get(&self, address: u32) -> Option<&str> {
+        self.formats.get(&address).map(String::as_str)
+    }
+}
+
+impl MessageDecoder {
+    /// Creates a new decoder with the format strings from `table`.
+    pub fn new(table: FormatTable) -> Self {
+        Self { table, buffer: Vec::new() }
+    }
+
+    /// Decodes the next chunk of the port byte stream, and returns all
+    /// completed messages.
+    ///
+    /// An incomplete message at the end of `bytes` is buffered until the next
+    /// call.
+    pub fn feed(&mut self, bytes: &[u8]) -> Vec<Result<String, MessageError>> {
+        self.buffer.extend_from_slice(bytes);
+        let mut messages = Vec::new();
+        let mut offset = 0;
+        while let Some((message, len)) = self.parse(&self.buffer[offset..]) {
+            let failed = message.is_err();
+            messages.push(message);
+            if failed {
+                offset = self.buffer.len();
+                break;
+            }
+            offset += len;
+        }
+        self.buffer.drain(..offset);
+        messages
+    }
+
+    /// Returns the number of buffered bytes of an incomplete message.
+    pub fn pending(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Parses a message at the start of `bytes`. Returns `None` if the
+    /// message is incomplete.
+    fn parse(&self, bytes: &[u8]) -> Option<(Result<String, MessageError>, usize)> {
+        let address = little_endian(take(bytes, 0, 4)?);
+        let format = match self.table.get(address) {
+            Some(format) => format,
+            None => return Some((Err(MessageError::UnknownFormat(address)), 4)),
+        };
+        let mut message = String::new();
+        let mut offset = 4;
+        let mut chars = format.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' => {
+                    let mut spec = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('{') if spec.is_empty() => {
+                                message.push('{');
+                                break;
+                            }
+                            Some('}') | None => {
+                                let (arg, len) = match parse_arg(&bytes[offset..])? {
+                                    Ok(arg) => arg,
+                                    Err(err) => return Some((Err(err), offset)),
+                                };
+                                offset += len;
+                                match arg.format(&spec) {
+                                    Some(arg) => message.push_str(&arg),
+                                    None => {
+                                        return Some((Err(MessageError::InvalidArgument), offset));
+                                    }
+                                }
+                                break;
+                            }
+                            Some(c) => spec.push(c),
+                        }
+                    }
+                }
+                '}' => {
+                    chars.next();
+                    message.push('}');
+                }
+                _ => message.push(c),
+            }
+        }
+        Some((Ok(message), offset))
+    }
+}
+
+impl fmt::Display for MessageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownFormat(address) => {
+                write!(f, "Unknown format string at {:#010X}.", address)
+            }
+            Self::InvalidTag(tag) => write!(f, "Invalid argument tag {}.", tag),
+            Self::InvalidArgument => write!(f, "Invalid argument value."),
+        }
+    }
+}
+
+/// Decoded argument of a [`log!`](super::log!) message.
+enum Arg {
+    Unsigned(u32),
+    Signed(i32, u32),
+    Bool(bool),
+    Char(char),
+    Float(f32),
+    Str(String),
+}
+
+impl Arg {
+    /// Formats the argument according to the format spec `spec`. Returns
+    /// `None` if the spec is not applicable to the argument.
+    fn format(&self, spec: &str) -> Option<String> {
+        Some(match self {
+            Self::Unsigned(value) => match spec {
+                "" | ":?" => format!("{}", value),
+                ":x" => format!("{:x}", value),
+                ":X" => format!("{:X}", value),
+                ":b" => format!("{:b}", value),
+                _ => return None,
+            },
+            Self::Signed(value, bits) => match spec {
+                "" | ":?" => format!("{}", value),
+                ":x" => format!("{:x}", bits),
+                ":X" => format!("{:X}", bits),
+                ":b" => format!("{:b}", bits),
+                _ => return None,
+            },
+            Self::Bool(value) => match spec {
+                "" | ":?" => format!("{}", value),
+                _ => return None,
+            },
+            Self::Char(value) => match spec {
+                "" => format!("{}", value),
+                ":?" => format!("{:?}", value),
+                _ => return None,
+            },
+            Self::Float(value) => match spec {
+                "" => format!("{}", value),
+                ":?" => format!("{:?}", value),
+                _ => return None,
+            },
+            Self::Str(value) => match spec {
+                "" => value.clone(),
+                ":?" => format!("{:?}", value),
+                _ => return None,
+            },
+        })
+    }
+}
+
+/// Parses a tagged argument at the start of `bytes`. Returns `None` if the
+/// argument is incomplete.
+#[allow(clippy::cast_possible_wrap)]
+fn parse_arg(bytes: &[u8]) -> Option<Result<(Arg, usize), MessageError>> {
+    let tag = *bytes.first()?;
+    let size = match tag {
+        deferred::TAG_U8 | deferred::TAG_I8 | deferred::TAG_BOOL => 1,
+        deferred::TAG_U16 | deferred::TAG_I16 => 2,
+        deferred::TAG_U32 | deferred::TAG_I32 | deferred::TAG_CHAR | deferred::TAG_F32 => 4,
+        deferred::TAG_STR => 2,
+        _ => return Some(Err(MessageError::InvalidTag(tag))),
+    };
+    let value = little_endian(take(bytes, 1, size)?);
+    let arg = match tag {
+        deferred::TAG_U8 | deferred::TAG_U16 | deferred::TAG_U32 => Arg::Unsigned(value),
+        deferred::TAG_I8 => Arg::Signed(i32::from(value as u8 as i8), value),
+        deferred::TAG_I16 => Arg::Signed(i32::from(value as u16 as i16), value),
+        deferred::TAG_I32 => Arg::Signed(value as i32, value),
+        deferred::TAG_BOOL if value <= 1 => Arg::Bool(value != 0),
+        deferred::TAG_CHAR => match char::from_u32(value) {
+            Some(value) => Arg::Char(value),
+            None => return Some(Err(MessageError::InvalidArgument)),
+        },
+        deferred::TAG_F32 => Arg::Float(f32::from_bits(value)),
+        deferred::TAG_STR => {
+            let string = take(bytes, 1 + size, value as usize)?;
+            let string = String::from_utf8_lossy(string).into_owned();
+            return Some(Ok((Arg::Str(string), 1 + size + value as usize)));
+        }
+        _ => return Some(Err(MessageError::InvalidArgument)),
+    };
+    Some(Ok((arg, 1 + size)))
+}
+
+/// Returns `len` bytes of `bytes` at `offset`, or `None` if there are not
+/// enough bytes.
+fn take(bytes: &[u8], offset: usize, len: usize) -> Option<&[u8]> {
+    bytes.get(offset..offset + len)
+}
+
+fn hardware_packet(discriminator: u8, size: u8, value: u32) -> Packet {
+    match discriminator {
+        0 if size == 1 => Packet::EventCounter(value as u8),
+        1 if size == 2 => {
+            let number = (value & 0x1FF) as u16;
+            match value >> 12 & 0b11 {
+                1 => Packet::ExceptionTrace { number, function: ExceptionFunction::Entered },
+                2 => Packet::ExceptionTrace { number, function: ExceptionFunction::Exited },
+                3 => Packet::ExceptionTrace { number, function: ExceptionFunction::Returned },
+                _ => Packet::Hardware { discriminator, size, value },
+            }
+        }
+        2 if size == 4 => Packet::PcSample(Some(value)),
+        2 if size == 1 => Packet::PcSample(None),
+        8..=23 => {
+            let comparator = discriminator >> 1 & 0b11;
+            let kind = match (discriminator & 0x10 != 0, discriminator & 1 != 0) {
+                (false, false) => DataTraceKind::Pc,
+                (false, true) => DataTraceKind::Address,
+                (true, false) => DataTraceKind::Read,
+                (true, true) => DataTraceKind::Write,
+            };
+            Packet::DataTrace { comparator, kind, size, value }
+        }
+        _ => Packet::Hardware { discriminator, size, value },
+    }
+}
+
+/// Composes a value from up to four bytes in little-endian order.
+fn little_endian(bytes: &[u8]) -> u32 {
+    bytes.iter().enumerate().fold(0, |value, (i, &byte)| value | u32::from(byte) << (i << 3))
+}
+
+/// Reads up to `max` continued payload bytes, 7 bits of the value per byte.
+/// The last byte of the maximum length is included in full.
+fn continued(bytes: &[u8], max: usize) -> Option<(u64, usize)> {
+    let mut value = 0;
+    for (i, &byte) in bytes.iter().take(max).enumerate() {
+        if i == max - 1 {
+            return Some((value | u64::from(byte) << 7 * i, i + 1));
+        }
+        value |= u64::from(byte & 0x7F) << 7 * i;
+        if byte & 0x80 == 0 {
+            return Some((value, i + 1));
+        }
+    }
+    None
+}
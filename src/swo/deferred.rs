@@ -0,0 +1,160 @@
+//! Deferred-formatting log messages.
+//!
+//! The [`log!`](super::log!) macro interns its format string at compile time
+//! into the `.drone_log` section, and sends only the address of the string
+//! followed by the raw arguments to an ITM stimulus port. The formatting is
+//! done on the host by [`MessageDecoder`](super::decode::MessageDecoder).
+//!
+//! The format strings aren't needed at run-time, so the section should be
+//! excluded from the flash image. E.g. the following can be added to the
+//! linker script:
+//!
+//! ```text
+//! SECTIONS
+//! {
+//!   .drone_log (INFO) : { KEEP(*(.drone_log)) }
+//! }
+//! ```
+//!
+//! # Encoding
+//!
+//! A message starts with the address of its NUL-terminated format string as a
+//! 32-bit word. Each argument follows as a one-byte tag and the value in
+//! little-endian order:
+//!
+//! | Tag | Type            | Value                                   |
+//! |-----|-----------------|-----------------------------------------|
+//! | 1   | `u8`            | 1 byte                                  |
+//! | 2   | `u16`           | 2 bytes                                 |
+//! | 3   | `u32`, `usize`  | 4 bytes                                 |
+//! | 4   | `i8`            | 1 byte                                  |
+//! | 5   | `i16`           | 2 bytes                                 |
+//! | 6   | `i32`, `isize`  | 4 bytes                                 |
+//! | 7   | `bool`          | 1 byte                                  |
+//! | 8   | `char`          | 4 bytes                                 |
+//! | 9   | `f32`           | 4 bytes of the IEEE 754 representation  |
+//! | 10  | `str`           | 2 bytes of the length, then UTF-8 bytes |
+//!
+//! A `str` argument is truncated to [`MAX_STR_LEN`] bytes.
+//!
+//! A message is written within a critical section, so messages from
+//! different threads to the same port are not interleaved. All interrupts are
+//! masked while the message is written, and writing a word to a port spins
+//! until the ITM FIFO has room. Thus the interrupt latency grows with the
+//! message size and the SWO baud rate: a message with a maximum-length string
+//! is about 80 bytes, which takes about 0.4 ms at 2 Mbaud.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use drone_cortexm::swo;
+//!
+//! let (x, y) = (12_u16, -7_i32);
+//! swo::log!(2, "position: x = {}, y = {}, flags = {:b}", x, y, 0b101_u8);
+//! ```
+
+use super::{is_port_enabled, Port};
+use crate::thr::priority::critical_section;
+
+/// Tag of a `u8` argument.
+pub const TAG_U8: u8 = 1;
+/// Tag of a `u16` argument.
+pub const TAG_U16: u8 = 2;
+/// Tag of a `u32` argument.
+pub const TAG_U32: u8 = 3;
+/// Tag of an `i8` argument.
+pub const TAG_I8: u8 = 4;
+/// Tag of an `i16` argument.
+pub const TAG_I16: u8 = 5;
+/// Tag of an `i32` argument.
+pub const TAG_I32: u8 = 6;
+/// Tag of a `bool` argument.
+pub const TAG_BOOL: u8 = 7;
+/// Tag of a `char` argument.
+pub const TAG_CHAR: u8 = 8;
+/// Tag of an `f32` argument.
+pub const TAG_F32: u8 = 9;
+/// Tag of a `str` argument.
+pub const TAG_STR: u8 = 10;
+
+/// Maximum number of bytes of a `str` argument. Longer strings are truncated
+/// to keep the critical section of [`log!`](super::log!) short.
+pub const MAX_STR_LEN: usize = 64;
+
+/// A value that can be an argument of [`log!`](super::log!).
+pub trait Arg {
+    /// Writes the tag and the value to the ITM stimulus port.
+    fn write(&self, port: Port);
+}
+
+/// Writes a message with the format string `format` to the ITM stimulus port
+/// number `port`. The arguments are written by `f`.
+///
+/// The call is a no-op if the port is not enabled.
+#[doc(hidden)]
+#[inline]
+pub fn write<F: FnOnce(Port)>(port: u8, format: &'static [u8], f: F) {
+    if !is_port_enabled(usize::from(port)) {
+        return;
+    }
+    let port = Port::new(port);
+    critical_section(0, || {
+        port.write(format.as_ptr() as u32);
+        f(port);
+    });
+}
+
+macro_rules! impl_arg {
+    ($($ty:ty => $tag:ident as $repr:ty,)*) => {
+        $(
+            impl Arg for $ty {
+                #[allow(clippy::cast_sign_loss, clippy::unnecessary_cast)]
+                #[inline]
+                fn write(&self, port: Port) {
+                    port.write($tag).write(*self as $repr);
+                }
+            }
+        )*
+    };
+}
+
+impl_arg! {
+    u8 => TAG_U8 as u8,
+    u16 => TAG_U16 as u16,
+    u32 => TAG_U32 as u32,
+    usize => TAG_U32 as u32,
+    i8 => TAG_I8 as u8,
+    i16 => TAG_I16 as u16,
+    i32 => TAG_I32 as u32,
+    isize => TAG_I32 as u32,
+    bool => TAG_BOOL as u8,
+    char => TAG_CHAR as u32,
+}
+
+impl Arg for f32 {
+    #[inline]
+    fn write(&self, port: Port) {
+        port.write(TAG_F32).write(self.to_bits());
+    }
+}
+
+impl Arg for str {
+    /// Writes the tag, the length, and the bytes of the string. The string is
+    /// truncated to [`MAX_STR_LEN`] bytes at a character boundary.
+    #[inline]
+    fn write(&self, port: Port) {
+        let mut len = self.len().min(MAX_STR_LEN);
+        while !self.is_char_boundary(len) {
+            len -= 1;
+        }
+        let bytes = &self.as_bytes()[..len];
+        port.write(TAG_STR).write(bytes.len() as u16).write_bytes(bytes);
+    }
+}
+
+impl<T: Arg + ?Sized> Arg for &T {
+    #[inline]
+    fn write(&self, port: Port) {
+        (**self).write(port);
+    }
+}
@@ -3,16 +3,33 @@
 //! This module provides interface for ITM (Instrumentation Trace Macrocell)
 //! output through SWO (Single Wire Output) pin, and optionally the respective
 //! implementation for `drone_core::log` facade (via `set_log!` macro).
+//!
+//! [`buffer::Buffer`] puts a lock-free ring buffer in front of the ports, so
+//! that logging never blocks interrupt threads.
+//!
+//! The [`log!`] macro writes deferred-formatting messages, which carry only the
+//! address of an interned format string and the raw arguments. See the
+//! [`deferred`] module for details.
+//!
+//! With the `std` feature, the [`decode`] module provides a host-side decoder
+//! of the captured SWO byte stream.
 
-#![cfg_attr(feature = "std", allow(unreachable_code, unused_variables))]
+pub mod buffer;
+#[cfg(feature = "std")]
+pub mod decode;
+pub mod deferred;
 
+mod config;
 mod port;
 
-pub use self::port::Port;
+pub use self::{
+    config::{Config, ConfigError, GlobalTimestamp, SyncPeriod, TimestampPrescaler},
+    port::Port,
+};
 
 use crate::{
+    drv::dwt::delay_cycles,
     map::reg::{dwt, itm, tpiu},
-    processor,
     reg::prelude::*,
 };
 use core::ptr::read_volatile;
@@ -28,8 +45,6 @@ const ITM_TCR: usize = 0xE000_0E80;
 /// output.
 #[inline]
 pub fn is_enabled() -> bool {
-    #[cfg(feature = "std")]
-    return unimplemented!();
     unsafe { read_volatile(ITM_TCR as *const u32) & 1 != 0 }
 }
 
@@ -37,13 +52,14 @@ pub fn is_enabled() -> bool {
 /// of ITM port number `port`.
 #[inline]
 pub fn is_port_enabled(port: usize) -> bool {
-    #[cfg(feature = "std")]
-    return unimplemented!();
     unsafe { read_volatile(ITM_TER as *const u32) & 1 << port != 0 }
 }
 
 /// Blocks until all pending packets are transmitted.
 ///
+/// After the ITM is idle, the function waits for the TPIU to shift out its
+/// FIFO. The wait is measured with the DWT cycle counter if it is enabled.
+///
 /// This function is a no-op if no debug probe is connected and listening.
 #[inline(always)]
 pub fn flush() {
@@ -52,7 +68,7 @@ pub fn flush() {
         let tcr = unsafe { itm::Tcr::<Urt>::take() };
         while tcr.load().busy() {}
         let acpr = unsafe { tpiu::Acpr::<Urt>::take() };
-        processor::spin(acpr.load().swoscaler() * 64);
+        delay_cycles(acpr.load().swoscaler() * 64);
     }
     if is_enabled() {
         flush();
@@ -60,16 +76,33 @@ pub fn flush() {
 }
 
 /// Generates an ITM synchronization packet.
+///
+/// This function sets the DWT cycle counter to its maximum value, so it
+/// disturbs measurements made with [`Dwt`](crate::drv::dwt::Dwt). The counter
+/// is accessed through an unsynchronized `dwt_cyccnt` register token, so the
+/// token must be excluded from the register index. If the token is owned by
+/// other code, use [`sync_with`] instead.
 #[inline]
 pub fn sync() {
-    #[cfg(feature = "std")]
-    return unimplemented!();
     let mut cyccnt = unsafe { dwt::Cyccnt::<Urt>::take() };
     cyccnt.store(|r| r.write_cyccnt(0xFFFF_FFFF));
 }
 
+/// Generates an ITM synchronization packet using the `dwt_cyccnt` register
+/// token.
+///
+/// If the counter is owned by the [`Dwt`](crate::drv::dwt::Dwt) driver, use
+/// [`Dwt::sync_itm`](crate::drv::dwt::Dwt::sync_itm).
+#[inline]
+pub fn sync_with(cyccnt: &dwt::Cyccnt<Crt>) {
+    cyccnt.store(|r| r.write_cyccnt(0xFFFF_FFFF));
+}
+
 /// Updates the SWO prescaler register.
 ///
+/// Timestamps, exception tracing, and PC sampling are configured separately
+/// with [`Config`].
+///
 /// # Examples
 ///
 /// ```no_run
@@ -96,17 +129,44 @@ pub fn sync() {
 /// ```
 #[inline]
 pub fn update_prescaler(swoscaler: u32) {
-    #[cfg(feature = "std")]
-    return unimplemented!();
     let mut acpr = unsafe { tpiu::Acpr::<Urt>::take() };
     acpr.store(|r| r.write_swoscaler(swoscaler));
     sync();
 }
 
+/// Updates the SWO prescaler register, and generates an ITM synchronization
+/// packet with [`sync_with`].
+#[inline]
+pub fn update_prescaler_with(swoscaler: u32, cyccnt: &dwt::Cyccnt<Crt>) {
+    let mut acpr = unsafe { tpiu::Acpr::<Urt>::take() };
+    acpr.store(|r| r.write_swoscaler(swoscaler));
+    sync_with(cyccnt);
+}
+
 #[doc(hidden)]
 #[macro_export]
 macro_rules! swo_set_log {
     () => {
+        $crate::swo_set_log! {
+            @hooks(port, bytes, value) {
+                $crate::swo::Port::new(port).write_bytes(bytes);
+                $crate::swo::Port::new(port).write(value);
+                $crate::swo::flush();
+            }
+        }
+    };
+    (buffer => $buffer:path $(,)?) => {
+        $crate::swo_set_log! {
+            @hooks(port, bytes, value) {
+                $buffer.write(port, bytes);
+                $buffer.write(port, &value.to_le_bytes());
+                $buffer.trigger();
+            }
+        }
+    };
+    (
+        @hooks($p:ident, $b:ident, $v:ident) { $write_bytes:expr; $write_value:expr; $flush:expr; }
+    ) => {
         const _: () = {
             $crate::reg::assert_taken!("dwt_cyccnt");
             $crate::reg::assert_taken!("itm_tpr");
@@ -122,29 +182,29 @@ macro_rules! swo_set_log {
             }
 
             #[no_mangle]
-            extern "C" fn drone_log_write_bytes(port: u8, buffer: *const u8, count: usize) {
-                let bytes = unsafe { ::core::slice::from_raw_parts(buffer, count) };
-                $crate::swo::Port::new(port).write_bytes(bytes);
+            extern "C" fn drone_log_write_bytes($p: u8, buffer: *const u8, count: usize) {
+                let $b = unsafe { ::core::slice::from_raw_parts(buffer, count) };
+                $write_bytes;
             }
 
             #[no_mangle]
-            extern "C" fn drone_log_write_u8(port: u8, value: u8) {
-                $crate::swo::Port::new(port).write(value);
+            extern "C" fn drone_log_write_u8($p: u8, $v: u8) {
+                $write_value;
             }
 
             #[no_mangle]
-            extern "C" fn drone_log_write_u16(port: u8, value: u16) {
-                $crate::swo::Port::new(port).write(value);
+            extern "C" fn drone_log_write_u16($p: u8, $v: u16) {
+                $write_value;
             }
 
             #[no_mangle]
-            extern "C" fn drone_log_write_u32(port: u8, value: u32) {
-                $crate::swo::Port::new(port).write(value);
+            extern "C" fn drone_log_write_u32($p: u8, $v: u32) {
+                $write_value;
             }
 
             #[no_mangle]
             extern "C" fn drone_log_flush() {
-                $crate::swo::flush();
+                $flush;
             }
         };
     };
@@ -152,6 +212,11 @@ macro_rules! swo_set_log {
 
 /// Sets SWO as default logger.
 ///
+/// With `buffer => BUFFER`, the log output is queued to the static
+/// [`buffer::Buffer`] `BUFFER` instead of being written directly to the ITM
+/// stimulus ports. Flushing the log only triggers the drain thread of the
+/// buffer, so it never blocks the calling thread.
+///
 /// # Examples
 ///
 /// ```
@@ -171,3 +236,24 @@ macro_rules! swo_set_log {
 /// ```
 #[doc(inline)]
 pub use crate::swo_set_log as set_log;
+
+/// Writes a deferred-formatting message to an ITM stimulus port.
+///
+/// The first argument is the port number, followed by a format string literal
+/// and the arguments. Placeholders are `{}`, `{:?}`, `{:x}`, `{:X}`, and
+/// `{:b}`; the arguments must implement [`deferred::Arg`]. The format string is
+/// not formatted on the device, but interned into the `.drone_log` section and
+/// rebuilt on the host with
+/// [`MessageDecoder`](crate::swo::decode::MessageDecoder).
+///
+/// The call is a no-op if the port is not enabled.
+///
+/// # Examples
+///
+/// ```no_run
+/// use drone_cortexm::swo;
+///
+/// swo::log!(2, "ADC{} = {:X}", 1_u8, 0x0FA0_u16);
+/// ```
+#[doc(inline)]
+pub use drone_cortexm_macros::swo_log as log;
@@ -86,7 +86,7 @@ impl Write for Port {
 impl PortWrite for u8 {
     fn port_write(address: usize, value: Self) {
         #[cfg(feature = "std")]
-        return unimplemented!();
+        return crate::sim::itm_write(address, &value.to_le_bytes());
         unsafe {
             llvm_asm!("
             0:
@@ -108,7 +108,7 @@ impl PortWrite for u8 {
 impl PortWrite for u16 {
     fn port_write(address: usize, value: Self) {
         #[cfg(feature = "std")]
-        return unimplemented!();
+        return crate::sim::itm_write(address, &value.to_le_bytes());
         unsafe {
             llvm_asm!("
             0:
@@ -130,7 +130,7 @@ impl PortWrite for u16 {
 impl PortWrite for u32 {
     fn port_write(address: usize, value: Self) {
         #[cfg(feature = "std")]
-        return unimplemented!();
+        return crate::sim::itm_write(address, &value.to_le_bytes());
         unsafe {
             llvm_asm!("
             0:
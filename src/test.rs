@@ -0,0 +1,224 @@
+//! On-target test harness.
+//!
+//! The [`test`](macro@crate::test) attribute turns an inline module into a
+//! test suite. Functions of the module marked with `#[test]` become test
+//! cases, which are collected into the `TESTS` static, and a `run` function is
+//! added to the module. `run` executes the test cases one by one, reports the
+//! results in the libtest format to the host standard output through
+//! [semihosting](crate::semihosting), and exits with the status 0 if all tests
+//! passed. Test cases marked with `#[ignore]` are reported, but not run.
+//!
+//! A test fails by panicking or faulting. In both cases the test image exits
+//! with the status [`FAILURE_STATUS`], and the remaining tests are not run.
+//! The panic message reaches the host if the semihosting logger is set with
+//! [`semihosting::set_log!`](crate::semihosting::set_log!). Faults should be
+//! handled by [`fault::handler`](crate::fault::handler) with [`report_fault`]
+//! as the hook.
+//!
+//! The attribute accepts an optional context type. The context is passed to
+//! `run`, and each test case takes it by reference. It is the place for the
+//! thread and register tokens.
+//!
+//! # Examples
+//!
+//! A test image for QEMU:
+//!
+//! ```no_run
+//! # #![feature(const_fn_fn_ptr_basics)]
+//! # #![feature(marker_trait_attr)]
+//! use drone_cortexm::{
+//!     fault, semihosting, sv,
+//!     sv::{sv_handler, SwitchBackService, SwitchContextService},
+//!     thr,
+//!     thr::prelude::*,
+//! };
+//!
+//! thr! {
+//!     thread => pub Thr {};
+//!     local => pub ThrLocal {};
+//!     vtable => pub Vtable;
+//!     index => pub Thrs;
+//!     init => pub ThrsInit;
+//!     supervisor => Sv;
+//!     threads => {
+//!         exceptions => {
+//!             pub naked(fault::handler) hard_fault;
+//!             pub naked(sv_handler::<Sv>) sv_call;
+//!         };
+//!         interrupts => {
+//!             5: pub uart0;
+//!         };
+//!     };
+//! }
+//!
+//! sv! {
+//!     supervisor => pub Sv;
+//!     array => pub SERVICES;
+//!     services => {
+//!         SwitchContextService;
+//!         SwitchBackService;
+//!     }
+//! }
+//!
+//! // Send the panic messages to the host.
+//! semihosting::set_log!();
+//! // Fail the running test on a fault.
+//! fault::set_handler!(drone_cortexm::test::report_fault);
+//!
+//! #[drone_cortexm::test(Thrs)]
+//! mod tests {
+//!     use super::{Sv, Thrs};
+//!     use core::{
+//!         pin::Pin,
+//!         sync::atomic::{AtomicBool, Ordering},
+//!     };
+//!     use drone_cortexm::{
+//!         fib::{self, Fiber},
+//!         processor,
+//!         thr::prelude::*,
+//!     };
+//!
+//!     #[test]
+//!     fn proc_yield() {
+//!         let mut fiber = unsafe {
+//!             fib::new_proc_unchecked::<Sv, u32, u32, u32, _>(0x400, |input, yielder| {
+//!                 yielder.proc_yield(input + 1) * 2
+//!             })
+//!         };
+//!         assert!(matches!(Pin::new(&mut fiber).resume(1), fib::Yielded(2)));
+//!         assert!(matches!(Pin::new(&mut fiber).resume(3), fib::Complete(6)));
+//!     }
+//!
+//!     #[test]
+//!     fn interrupt_preempts(thr: &Thrs) {
+//!         static FIRED: AtomicBool = AtomicBool::new(false);
+//!         thr.uart0.add_fn(|| {
+//!             FIRED.store(true, Ordering::SeqCst);
+//!             fib::Complete(())
+//!         });
+//!         thr.uart0.enable_int();
+//!         thr.uart0.set_pending_int();
+//!         processor::barrier();
+//!         assert!(FIRED.load(Ordering::SeqCst));
+//!     }
+//! }
+//!
+//! #[no_mangle]
+//! pub static VTABLE: Vtable = Vtable::new(reset);
+//!
+//! unsafe extern "C" fn reset() -> ! {
+//!     // Initialize the memory as usual, then run the suite.
+//!     tests::run(thr::init(unsafe { ThrsInit::take() }))
+//! }
+//! # fn main() {}
+//! ```
+//!
+//! The image is run with:
+//!
+//! ```shell
+//! $ qemu-system-arm -machine lm3s6965evb -cpu cortex-m3 -nographic \
+//!     -semihosting-config enable=on,target=native -kernel test-image
+//! ```
+
+use crate::{fault::Fault, processor, rt, semihosting};
+use core::{
+    fmt::{self, Write},
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+};
+
+/// Exit status of a failed test suite.
+pub const FAILURE_STATUS: u32 = 101;
+
+static RUNNING: AtomicBool = AtomicBool::new(false);
+static PASSED: AtomicUsize = AtomicUsize::new(0);
+static IGNORED: AtomicUsize = AtomicUsize::new(0);
+
+/// A test case of a suite defined with the [`test`](macro@crate::test)
+/// attribute.
+pub struct TestCase<C> {
+    /// The full path of the test function.
+    pub name: &'static str,
+    /// Whether the test is marked with `#[ignore]`.
+    pub ignore: bool,
+    /// Calls the test function with the context.
+    pub run: fn(&C),
+}
+
+struct Stdout;
+
+/// Runs `tests` with `context`, and exits with the result.
+///
+/// This is the body of the `run` function generated by the
+/// [`test`](macro@crate::test) attribute.
+#[allow(clippy::needless_pass_by_value)]
+pub fn run<C>(tests: &[TestCase<C>], context: C) -> ! {
+    semihosting::exit(execute(tests, &context))
+}
+
+/// Runs `tests` with `context`, and returns the exit status.
+///
+/// A failed test doesn't return, see [the module level
+/// documentation](self). This function also makes a self reset, e.g. from a
+/// panic, fail the running test.
+pub fn execute<C>(tests: &[TestCase<C>], context: &C) -> u32 {
+    rt::set_self_reset_hook(fail);
+    PASSED.store(0, Ordering::Relaxed);
+    IGNORED.store(0, Ordering::Relaxed);
+    let plural = if tests.len() == 1 { "" } else { "s" };
+    print(format_args!("\nrunning {} test{}\n", tests.len(), plural));
+    for test in tests {
+        if test.ignore {
+            print(format_args!("test {} ... ignored\n", test.name));
+            IGNORED.fetch_add(1, Ordering::Relaxed);
+            continue;
+        }
+        print(format_args!("test {} ... ", test.name));
+        RUNNING.store(true, Ordering::SeqCst);
+        (test.run)(context);
+        RUNNING.store(false, Ordering::SeqCst);
+        print(format_args!("ok\n"));
+        PASSED.fetch_add(1, Ordering::Relaxed);
+    }
+    summary("ok", 0);
+    0
+}
+
+/// Reports the fault, and fails the running test. Resets the processor if no
+/// test is running.
+///
+/// This is a hook for [`fault::set_handler!`](crate::fault::set_handler!).
+pub fn report_fault(fault: &Fault) -> ! {
+    let _ = writeln!(Stdout, "{}", fault);
+    fail();
+    processor::self_reset()
+}
+
+/// Fails the running test, and exits. Returns if no test is running.
+pub(crate) fn fail() {
+    if RUNNING.swap(false, Ordering::SeqCst) {
+        print(format_args!("FAILED\n"));
+        summary("FAILED", 1);
+        semihosting::exit(FAILURE_STATUS);
+    }
+}
+
+fn summary(result: &str, failed: usize) {
+    print(format_args!(
+        "\ntest result: {}. {} passed; {} failed; {} ignored\n\n",
+        result,
+        PASSED.load(Ordering::Relaxed),
+        failed,
+        IGNORED.load(Ordering::Relaxed),
+    ));
+}
+
+fn print(args: fmt::Arguments<'_>) {
+    let _ = Stdout.write_fmt(args);
+}
+
+impl Write for Stdout {
+    fn write_str(&mut self, string: &str) -> fmt::Result {
+        semihosting::write_stdout(string.as_bytes());
+        Ok(())
+    }
+}
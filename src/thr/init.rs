@@ -1,6 +1,10 @@
 #![cfg_attr(feature = "std", allow(unreachable_code, unused_mut))]
 
-use crate::{map::reg::scb, reg::prelude::*, thr::ThrTokens};
+use crate::{
+    map::reg::scb,
+    reg::prelude::*,
+    thr::{priority::ThrPriority, ThrTokens},
+};
 use drone_core::token::Token;
 
 /// Threads initialization token.
@@ -11,6 +15,10 @@ use drone_core::token::Token;
 pub unsafe trait ThrsInitToken: Token {
     /// The set of thread tokens.
     type ThrTokens: ThrTokens;
+
+    /// Thread priorities declared with `priority = N`, which are programmed
+    /// by [`init_extended`].
+    const PRIORITIES: &'static [ThrPriority] = &[];
 }
 
 /// A set of register tokens returned by [`init_extended`].
@@ -74,6 +82,9 @@ pub fn init_extended<T: ThrsInitToken>(_token: T) -> (T::ThrTokens, ThrInitExten
     unsafe {
         mpu::reset();
     }
+    for priority in T::PRIORITIES {
+        priority.apply();
+    }
     drop(stkalign);
     drop(nonbasethrdena);
     (unsafe { T::ThrTokens::take() }, ThrInitExtended {
@@ -126,10 +137,11 @@ pub fn init<T: ThrsInitToken>(token: T) -> T::ThrTokens {
 }
 
 #[cfg(feature = "memory-protection-unit")]
-mod mpu {
+pub(super) mod mpu {
     use crate::{map::reg::mpu, reg::prelude::*};
     use drone_core::token::Token;
 
+    #[cfg(not(armv8m))]
     static MPU_RESET_TABLE: [u32; 16] = [
         rbar_reset(0),
         0,
@@ -149,10 +161,9 @@ mod mpu {
         0,
     ];
 
+    #[cfg(not(armv8m))]
     #[allow(unused_assignments, unused_variables)]
     pub(super) unsafe fn reset() {
-        #[cfg(feature = "std")]
-        return unimplemented!();
         let mpu_type = unsafe { mpu::Type::<Srt>::take() };
         let mpu_ctrl = unsafe { mpu::Ctrl::<Srt>::take() };
         let mut table_ptr = &MPU_RESET_TABLE;
@@ -160,6 +171,17 @@ mod mpu {
             return;
         }
         mpu_ctrl.reset();
+        #[cfg(feature = "std")]
+        {
+            // Simulate the RBAR/RASR alias registers.
+            for chunk in MPU_RESET_TABLE.chunks(8) {
+                let rbar = mpu::Rbar::<Srt>::ADDRESS as *mut u32;
+                for (i, &word) in chunk.iter().enumerate() {
+                    unsafe { core::ptr::write_volatile(rbar.add(i), word) };
+                }
+            }
+            return;
+        }
         llvm_asm!("
             ldmia $0!, {r5, r6, r8, r9, r10, r11, r12, r14}
             stmia $1, {r5, r6, r8, r9, r10, r11, r12, r14}
@@ -172,8 +194,41 @@ mod mpu {
         );
     }
 
+    #[cfg(not(armv8m))]
     #[allow(clippy::cast_lossless)]
     const fn rbar_reset(region: u8) -> u32 {
         1 << 4 | region as u32 & 0b1111
     }
+
+    #[cfg(armv8m)]
+    pub(super) unsafe fn reset() {
+        let mpu_type = unsafe { mpu::Type::<Srt>::take() };
+        let mpu_ctrl = unsafe { mpu::Ctrl::<Srt>::take() };
+        let mpu_rnr = unsafe { mpu::Rnr::<Srt>::take() };
+        let mpu_rlar = unsafe { mpu::Rlar::<Srt>::take() };
+        let regions = mpu_type.load().dregion();
+        if regions == 0 {
+            return;
+        }
+        mpu_ctrl.reset();
+        for region in 0..regions {
+            mpu_rnr.store(|r| r.write_region(region));
+            mpu_rlar.reset();
+        }
+        mpu_rnr.reset();
+        unsafe { set_guard_attr() };
+    }
+
+    /// Memory attributes of the stack guard regions: normal memory, outer and
+    /// inner write-back non-transient, read and write allocate. This matches
+    /// the default memory map for SRAM.
+    #[cfg(armv8m)]
+    const GUARD_ATTR: u32 = 0xFF;
+
+    /// Programs attribute 0 of MAIR0, which is referenced by the stack guard
+    /// regions.
+    #[cfg(armv8m)]
+    pub(in crate::thr) unsafe fn set_guard_attr() {
+        unsafe { mpu::Mair0::<Srt>::take() }.modify(|r| r.write_attr0(GUARD_ATTR));
+    }
 }
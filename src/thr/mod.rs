@@ -42,14 +42,16 @@
 //!             // `sv_call_handler` directly to the vector table.
 //!             /// System service call.
 //!             pub naked(sv_call_handler) sv_call;
+//!             // Define a regular thread for the SYS_TICK exception with the priority
+//!             // 0x80. The priority is programmed by `thr::init`.
 //!             /// System tick timer.
-//!             pub sys_tick;
+//!             pub sys_tick priority = 0x80;
 //!         };
 //!         // Threads for interrupts.
 //!         interrupts => {
 //!             // Define a regular thread for the interrupt #5 with name `rcc`.
 //!             /// RCC global interrupt.
-//!             5: pub rcc;
+//!             5: pub rcc priority = 0x40;
 //!             // Define an outer thread for the interrupt #18 with name `adc1`. This creates a
 //!             // thread token structure `Adc1`, a field `adc1` in the `Thrs` structure, and an
 //!             // element in the array of `Thr`. But unlike a regular thread, this outer thread
@@ -88,15 +90,22 @@
 //! * `debug` - Monitor.
 //! * `pend_sv` - Pendable request for system service.
 //! * `sys_tick` - System tick timer.
+//!
+//! All of them except `nmi` and `hard_fault` have a configurable priority,
+//! which can be set with `priority = N` after the thread name, and at run-time
+//! with [`ThrSysPriority`].
 //! ```
 
 pub mod prelude;
+pub mod priority;
+pub mod profile;
 
 mod exec;
 mod init;
 mod nvic;
 mod root;
-mod wake;
+pub(crate) mod stack;
+pub(crate) mod wake;
 
 #[doc(no_inline)]
 pub use drone_core::thr::*;
@@ -105,7 +114,9 @@ pub use self::{
     exec::{ExecOutput, ThrExec},
     init::{init, init_extended, ThrInitExtended, ThrsInitToken},
     nvic::{NvicBlock, NvicIabr, NvicIcer, NvicIcpr, NvicIser, NvicIspr, ThrNvic},
+    priority::ThrSysPriority,
     root::{FutureRootExt, StreamRootExt, StreamRootWait},
+    stack::{guard_main_stack, MainStack},
 };
 
 use crate::sv::Supervisor;
@@ -18,5 +18,8 @@ pub use crate::thr::IntToken;
 #[doc(no_inline)]
 pub use crate::{
     fib::ThrFiberProc as _,
-    thr::{FutureRootExt as _, StreamRootExt as _, ThrExec as _, ThrNvic as _},
+    thr::{
+        profile::ThrProfiler as _, FutureRootExt as _, StreamRootExt as _, ThrExec as _,
+        ThrNvic as _, ThrSysPriority as _,
+    },
 };
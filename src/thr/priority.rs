@@ -0,0 +1,349 @@
+//! Priority management.
+//!
+//! Cortex-M priorities are 8-bit values, where a lower value means a higher
+//! urgency. Only the most significant bits are implemented, the number of
+//! which is device-specific. The priority value is split into the group
+//! priority, which determines preemption, and the subpriority, which only
+//! orders pending exceptions of the same group priority. The split is
+//! configured with [`set_priority_grouping`].
+//!
+//! [`critical_section`] masks all exceptions up to a priority ceiling with
+//! the `BASEPRI` register, and [`Resource`] builds the Stack Resource Policy
+//! on top of it: data shared between threads is locked at the ceiling computed
+//! from the priorities of the threads that use it.
+//!
+//! Thread priorities can also be declared in the [`thr!`](crate::thr!) macro
+//! with `priority = N` after the thread name. [`thr::init`](crate::thr::init)
+//! programs such priorities before returning the thread tokens.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! # #![feature(const_fn_fn_ptr_basics)]
+//! # #![feature(marker_trait_attr)]
+//! use drone_cortexm::{
+//!     thr,
+//!     thr::{
+//!         prelude::*,
+//!         priority::{self, PriorityGrouping, Resource},
+//!     },
+//! };
+//!
+//! thr! {
+//!     thread => pub Thr {};
+//!     local => pub ThrLocal {};
+//!     vtable => pub Vtable;
+//!     index => pub Thrs;
+//!     init => pub ThrsInit;
+//!     threads => {
+//!         exceptions => {
+//!             pub sys_tick priority = 0xC0;
+//!         };
+//!         interrupts => {
+//!             5: pub rcc;
+//!             18: pub adc1;
+//!         };
+//!     };
+//! }
+//!
+//! # fn main() {
+//! let thr = thr::init(unsafe { ThrsInit::take() });
+//! let grouping = PriorityGrouping::Group4Sub4;
+//! priority::set_priority_grouping(grouping);
+//! thr.rcc.set_priority(grouping.priority(2, 0));
+//! thr.adc1.set_priority(grouping.priority(1, 0));
+//!
+//! // The ceiling is the priority of `adc1`.
+//! let counter = Resource::new(0_u32).user(thr.rcc).user(thr.adc1).sys_user(thr.sys_tick);
+//! counter.lock(|counter| *counter += 1);
+//! # }
+//! ```
+
+#![cfg_attr(feature = "std", allow(unreachable_code, unused_variables))]
+
+use crate::{
+    map::reg::scb,
+    reg::prelude::*,
+    thr::{IntToken, ThrNvic},
+    util::assert,
+};
+use core::{
+    cell::UnsafeCell,
+    ptr::{read_volatile, write_volatile},
+    sync::atomic::{AtomicBool, Ordering},
+};
+use drone_core::{thr::ThrToken, token::Token};
+
+const SCB_SHPR: usize = 0xE000_ED18;
+const NVIC_IPR: usize = 0xE000_E400;
+
+/// Split of the priority value into the group priority and the subpriority.
+///
+/// The variant names count the bits of the 8-bit priority value. The
+/// unimplemented least significant bits are taken from the subpriority.
+#[allow(missing_docs)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum PriorityGrouping {
+    Group7Sub1 = 0,
+    Group6Sub2 = 1,
+    Group5Sub3 = 2,
+    Group4Sub4 = 3,
+    Group3Sub5 = 4,
+    Group2Sub6 = 5,
+    Group1Sub7 = 6,
+    Group0Sub8 = 7,
+}
+
+/// System exceptions with a configurable priority.
+#[allow(missing_docs)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum SysException {
+    MemManage = 4,
+    BusFault = 5,
+    UsageFault = 6,
+    #[cfg(feature = "security-extension")]
+    SecureFault = 7,
+    SvCall = 11,
+    DebugMonitor = 12,
+    PendSv = 14,
+    SysTick = 15,
+}
+
+/// A thread priority declared in the [`thr!`](crate::thr!) macro.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ThrPriority {
+    /// Priority of a system exception.
+    Sys(SysException, u8),
+    /// Priority of an interrupt with the given number.
+    Int(usize, u8),
+}
+
+/// A system exception thread token with a configurable priority.
+pub trait ThrSysPriority: ThrToken {
+    /// The system exception of the thread.
+    const SYS_EXCEPTION: SysException;
+
+    /// Reads the priority of the exception.
+    #[inline]
+    fn priority(&self) -> u8 {
+        sys_priority(Self::SYS_EXCEPTION)
+    }
+
+    /// Writes the priority of the exception.
+    #[inline]
+    fn set_priority(&self, priority: u8) {
+        set_sys_priority(Self::SYS_EXCEPTION, priority);
+    }
+}
+
+/// Data shared between threads, protected by the Stack Resource Policy.
+///
+/// The resource is locked by raising the execution priority to the ceiling,
+/// which is the highest priority of the threads using the resource. A thread
+/// holding the lock can't be preempted by any other user of the resource.
+pub struct Resource<T> {
+    data: UnsafeCell<T>,
+    ceiling: u8,
+    locked: AtomicBool,
+}
+
+unsafe impl<T: Send> Sync for Resource<T> {}
+
+impl PriorityGrouping {
+    /// Returns the number of bits of the group priority.
+    pub const fn group_bits(self) -> u32 {
+        7 - self as u32
+    }
+
+    /// Composes a priority value from `group` priority and `sub` priority.
+    ///
+    /// # Panics
+    ///
+    /// If `group` or `sub` doesn't fit into the respective number of bits.
+    pub const fn priority(self, group: u8, sub: u8) -> u8 {
+        let shift = self as u32 + 1;
+        assert((group as u32) < 1 << self.group_bits() && (sub as u32) < 1 << shift);
+        ((group as u32) << shift | sub as u32) as u8
+    }
+
+    /// Returns the group priority of the priority value `priority`.
+    pub const fn group(self, priority: u8) -> u8 {
+        ((priority as u32) >> self as u32 + 1) as u8
+    }
+}
+
+/// Configures the priority grouping.
+#[inline]
+pub fn set_priority_grouping(grouping: PriorityGrouping) {
+    unsafe { scb::Aircr::<Urt>::take() }
+        .store(|r| r.write_vectkey(0x05FA).write_prigroup(grouping as u32));
+}
+
+/// Returns the current priority grouping.
+#[inline]
+pub fn priority_grouping() -> PriorityGrouping {
+    match unsafe { scb::Aircr::<Urt>::take() }.load().prigroup() {
+        0 => PriorityGrouping::Group7Sub1,
+        1 => PriorityGrouping::Group6Sub2,
+        2 => PriorityGrouping::Group5Sub3,
+        3 => PriorityGrouping::Group4Sub4,
+        4 => PriorityGrouping::Group3Sub5,
+        5 => PriorityGrouping::Group2Sub6,
+        6 => PriorityGrouping::Group1Sub7,
+        _ => PriorityGrouping::Group0Sub8,
+    }
+}
+
+/// Reads the priority of the system exception `exception`.
+#[inline]
+pub fn sys_priority(exception: SysException) -> u8 {
+    unsafe { read_volatile((SCB_SHPR as *const u8).add(exception as usize - 4)) }
+}
+
+/// Writes the priority of the system exception `exception`.
+#[inline]
+pub fn set_sys_priority(exception: SysException, priority: u8) {
+    unsafe { write_volatile((SCB_SHPR as *mut u8).add(exception as usize - 4), priority) };
+}
+
+impl ThrPriority {
+    /// Writes the priority to `SHPR*` or `NVIC_IPR` register.
+    #[inline]
+    pub fn apply(self) {
+        match self {
+            Self::Sys(exception, priority) => set_sys_priority(exception, priority),
+            Self::Int(num, priority) => unsafe {
+                write_volatile((NVIC_IPR as *mut u8).add(num), priority);
+            },
+        }
+    }
+}
+
+/// Runs the closure `f` with all exceptions of the priority `ceiling` or lower
+/// masked.
+///
+/// The previous value of `BASEPRI` is restored afterwards, so critical
+/// sections can be nested. A `ceiling` of zero masks all exceptions with
+/// configurable priority through `PRIMASK`, because writing zero to `BASEPRI`
+/// disables the masking.
+#[inline]
+pub fn critical_section<F: FnOnce() -> R, R>(ceiling: u8, f: F) -> R {
+    #[cfg(feature = "std")]
+    return f();
+    if ceiling == 0 {
+        let primask: u32;
+        unsafe {
+            llvm_asm!("
+                mrs $0, primask
+                cpsid i
+            "   : "=r"(primask)
+                :
+                : "memory"
+                : "volatile"
+            );
+        }
+        let result = f();
+        if primask & 1 == 0 {
+            unsafe { llvm_asm!("cpsie i" :::: "memory" : "volatile") };
+        }
+        result
+    } else {
+        let basepri: u32;
+        unsafe {
+            llvm_asm!("
+                mrs $0, basepri
+                msr basepri_max, $1
+            "   : "=&r"(basepri)
+                : "r"(u32::from(ceiling))
+                : "memory"
+                : "volatile"
+            );
+        }
+        let result = f();
+        unsafe { llvm_asm!("msr basepri, $0" :: "r"(basepri) : "memory" : "volatile") };
+        result
+    }
+}
+
+impl<T> Resource<T> {
+    /// Creates a new resource without users. The users must be added with
+    /// [`user`](Resource::user) before the resource is shared.
+    ///
+    /// The initial ceiling is `0xFF`, the lowest priority, so a resource
+    /// without users masks almost no exceptions when locked.
+    #[inline]
+    pub const fn new(data: T) -> Self {
+        Self::with_ceiling(data, 0xFF)
+    }
+
+    /// Creates a new resource with the priority ceiling `ceiling`.
+    ///
+    /// The ceiling must be higher than or equal to the priority of every thread
+    /// using the resource, i.e. numerically lower or equal.
+    #[inline]
+    pub const fn with_ceiling(data: T, ceiling: u8) -> Self {
+        Self { data: UnsafeCell::new(data), ceiling, locked: AtomicBool::new(false) }
+    }
+
+    /// Adds the interrupt thread `thr` to the users of the resource, raising
+    /// the ceiling to the current priority of the thread if needed.
+    ///
+    /// Thread priorities must be configured before the users are added.
+    #[allow(clippy::needless_pass_by_value)]
+    #[inline]
+    pub fn user<I: IntToken>(mut self, thr: I) -> Self {
+        self.ceiling = self.ceiling.min(thr.priority());
+        self
+    }
+
+    /// Adds the system exception thread `thr` to the users of the resource,
+    /// raising the ceiling to the current priority of the thread if needed.
+    ///
+    /// Thread priorities must be configured before the users are added.
+    #[allow(clippy::needless_pass_by_value)]
+    #[inline]
+    pub fn sys_user<S: ThrSysPriority>(mut self, thr: S) -> Self {
+        self.ceiling = self.ceiling.min(thr.priority());
+        self
+    }
+
+    /// Returns the priority ceiling.
+    #[inline]
+    pub fn ceiling(&self) -> u8 {
+        self.ceiling
+    }
+
+    /// Locks the resource and runs the closure `f` with a mutable reference to
+    /// the data.
+    ///
+    /// The users are not checked: locking the resource from a thread with a
+    /// higher priority than the ceiling is not protected against preemption.
+    ///
+    /// # Panics
+    ///
+    /// If the resource is already locked, i.e. on a nested lock of the same
+    /// resource.
+    #[inline]
+    pub fn lock<F: FnOnce(&mut T) -> R, R>(&self, f: F) -> R {
+        critical_section(self.ceiling, || {
+            assert!(!self.locked.swap(true, Ordering::Acquire), "resource is already locked");
+            let result = f(unsafe { &mut *self.data.get() });
+            self.locked.store(false, Ordering::Release);
+            result
+        })
+    }
+
+    /// Returns a mutable reference to the data.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
+
+    /// Consumes the resource, returning the data.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.data.into_inner()
+    }
+}
@@ -0,0 +1,205 @@
+//! Thread CPU-usage profiler.
+//!
+//! The profiler is enabled by the `profiler` key of the [`thr!`](crate::thr!)
+//! macro. In this mode every generated handler reads the DWT cycle counter on
+//! entry and exit, and accumulates the number of cycles spent in the thread.
+//! Cycles spent in preempting threads are excluded from the preempted thread,
+//! and counted separately as preemptions.
+//!
+//! The cycle counter must be enabled beforehand, e.g. with
+//! [`Dwt`](crate::drv::dwt::Dwt). Naked threads are not profiled.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! # #![feature(const_fn_fn_ptr_basics)]
+//! # #![feature(marker_trait_attr)]
+//! use drone_cortexm::{thr, thr::prelude::*};
+//!
+//! thr! {
+//!     thread => pub Thr {};
+//!     local => pub ThrLocal {};
+//!     vtable => pub Vtable;
+//!     index => pub Thrs;
+//!     init => pub ThrsInit;
+//!     /// Thread statistics.
+//!     profiler => pub Profiler;
+//!     threads => {
+//!         exceptions => {
+//!             pub sys_tick;
+//!         };
+//!         interrupts => {
+//!             5: pub rcc;
+//!         };
+//!     };
+//! }
+//!
+//! # fn main() {
+//! let thr = unsafe { Thrs::take() };
+//! let profiler = unsafe { Profiler::take() };
+//! let stats = profiler.stats(thr.rcc);
+//! let _ = stats.max_cycles;
+//! // Send the statistics of all threads to the ITM stimulus port #2.
+//! profiler.stream(2);
+//! # }
+//! ```
+
+use crate::{
+    map::reg::dwt,
+    reg::prelude::*,
+    swo::{self, Port},
+    thr::priority::critical_section,
+};
+use core::cell::UnsafeCell;
+use drone_core::{thr::ThrToken, token::Token};
+
+const NONE: usize = usize::MAX;
+
+static mut CURRENT: usize = NONE;
+static mut NESTED: u32 = 0;
+
+/// Statistics of a single thread.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ThrStats {
+    /// The number of handler invocations.
+    pub calls: u32,
+    /// The number of times the thread was preempted by another profiled
+    /// thread.
+    pub preemptions: u32,
+    /// The minimum number of cycles spent in a single invocation.
+    pub min_cycles: u32,
+    /// The maximum number of cycles spent in a single invocation.
+    pub max_cycles: u32,
+    /// The total number of cycles spent in the thread.
+    pub total_cycles: u64,
+}
+
+/// Counters of a single thread.
+#[derive(Default)]
+pub struct ThrCounters(UnsafeCell<ThrStats>);
+
+unsafe impl Sync for ThrCounters {}
+
+/// A token for the thread statistics collected by the profiler.
+///
+/// # Safety
+///
+/// Must be defined only by the [`thr!`](crate::thr!) macro.
+pub unsafe trait ThrProfiler: Token {
+    /// Returns the counters of all threads indexed by
+    /// [`ThrToken::THR_IDX`].
+    fn counters(&self) -> &'static [ThrCounters];
+
+    /// Returns the statistics of the thread `thr`.
+    #[allow(clippy::needless_pass_by_value)]
+    #[inline]
+    fn stats<T: ThrToken>(&self, _thr: T) -> ThrStats {
+        self.counters()[T::THR_IDX].snapshot()
+    }
+
+    /// Clears the statistics of all threads.
+    #[inline]
+    fn reset(&self) {
+        for counters in self.counters() {
+            counters.reset();
+        }
+    }
+
+    /// Writes the statistics of all invoked threads to the ITM stimulus port
+    /// number `port`.
+    ///
+    /// Each thread is written as seven 32-bit words: the thread index,
+    /// `calls`, `preemptions`, `min_cycles`, `max_cycles`, and the low and
+    /// high halves of `total_cycles`. The call is a no-op if the port is not
+    /// enabled.
+    fn stream(&self, port: u8) {
+        if !swo::is_port_enabled(port as usize) {
+            return;
+        }
+        let port = Port::new(port);
+        for (thr_idx, counters) in self.counters().iter().enumerate() {
+            let stats = counters.snapshot();
+            if stats.calls == 0 {
+                continue;
+            }
+            port.write(thr_idx as u32)
+                .write(stats.calls)
+                .write(stats.preemptions)
+                .write(stats.min_cycles)
+                .write(stats.max_cycles)
+                .write(stats.total_cycles as u32)
+                .write((stats.total_cycles >> 32) as u32);
+        }
+    }
+}
+
+impl ThrStats {
+    /// Returns the average number of cycles spent in a single invocation.
+    pub fn average_cycles(&self) -> u32 {
+        if self.calls == 0 { 0 } else { (self.total_cycles / u64::from(self.calls)) as u32 }
+    }
+}
+
+impl ThrCounters {
+    /// Creates zeroed counters.
+    #[inline]
+    pub const fn new() -> Self {
+        Self(UnsafeCell::new(ThrStats {
+            calls: 0,
+            preemptions: 0,
+            min_cycles: 0,
+            max_cycles: 0,
+            total_cycles: 0,
+        }))
+    }
+
+    /// Returns a copy of the counters.
+    #[inline]
+    pub fn snapshot(&self) -> ThrStats {
+        critical_section(0, || unsafe { *self.0.get() })
+    }
+
+    /// Clears the counters.
+    #[inline]
+    pub fn reset(&self) {
+        critical_section(0, || unsafe { *self.0.get() = ThrStats::default() });
+    }
+
+    fn record(&self, cycles: u32) {
+        let stats = unsafe { &mut *self.0.get() };
+        if stats.calls == 0 || cycles < stats.min_cycles {
+            stats.min_cycles = cycles;
+        }
+        stats.max_cycles = stats.max_cycles.max(cycles);
+        stats.total_cycles += u64::from(cycles);
+        stats.calls = stats.calls.wrapping_add(1);
+    }
+}
+
+/// Runs the thread handler `f` of the thread number `thr_idx`, recording the
+/// spent cycles to `counters`.
+#[doc(hidden)]
+#[inline]
+pub fn run<F: FnOnce()>(counters: &'static [ThrCounters], thr_idx: usize, f: F) {
+    let (start, outer, nested) = critical_section(0, || unsafe {
+        let (outer, nested) = (CURRENT, NESTED);
+        if let Some(outer) = counters.get(outer) {
+            let stats = &mut *outer.0.get();
+            stats.preemptions = stats.preemptions.wrapping_add(1);
+        }
+        CURRENT = thr_idx;
+        NESTED = 0;
+        (cycle_count(), outer, nested)
+    });
+    f();
+    critical_section(0, || unsafe {
+        let elapsed = cycle_count().wrapping_sub(start);
+        counters[thr_idx].record(elapsed.saturating_sub(NESTED));
+        CURRENT = outer;
+        NESTED = nested.wrapping_add(elapsed);
+    });
+}
+
+fn cycle_count() -> u32 {
+    unsafe { dwt::Cyccnt::<Urt>::take() }.load().cyccnt()
+}
@@ -0,0 +1,152 @@
+#![cfg_attr(feature = "std", allow(unreachable_code))]
+
+use core::{
+    ptr::{read_volatile, write_volatile},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+/// The byte pattern of painted stack memory.
+pub(crate) const PAINT: u8 = 0xCD;
+
+/// Whether the main stack guard region is in effect. Switching back from a
+/// [`FiberProc`](crate::fib::FiberProc) stack keeps the MPU enabled if this
+/// flag is set.
+pub(crate) static MAIN_STACK_GUARDED: AtomicBool = AtomicBool::new(false);
+
+/// The main stack overflow detection set up by [`guard_main_stack`].
+pub struct MainStack {
+    bottom: *mut u8,
+    top: *mut u8,
+    guarded: bool,
+}
+
+/// Sets up overflow detection for the main stack, which is shared by all
+/// interrupt threads.
+///
+/// The unused part of the stack between `bottom` and the current stack pointer
+/// is painted with a known pattern, which allows querying the stack high-water
+/// mark with [`MainStack::high_water_mark`]. If the `memory-protection-unit`
+/// feature is enabled and the MPU is present, MPU region 7 is additionally
+/// configured as a no-access guard at the bottom of the stack, so an overflow
+/// raises a MemManage fault instead of silently corrupting adjacent memory.
+///
+/// This function should be called after [`thr::init`](crate::thr::init),
+/// which resets the MPU configuration. The guard region is kept in effect
+/// while switching to and from [`FiberProc`](crate::fib::FiberProc) stacks:
+/// switching back disables only the fiber guard regions instead of the whole
+/// MPU.
+///
+/// # Safety
+///
+/// * `bottom` and `top` must be the lowest and the highest addresses of the
+///   main stack.
+/// * Must be called from the main stack.
+pub unsafe fn guard_main_stack(bottom: *mut u8, top: *mut u8) -> MainStack {
+    #[cfg(feature = "memory-protection-unit")]
+    let guard_end = unsafe { mpu::guard(bottom) };
+    #[cfg(not(feature = "memory-protection-unit"))]
+    let guard_end = None;
+    let guarded = guard_end.is_some();
+    MAIN_STACK_GUARDED.store(guarded, Ordering::Relaxed);
+    let bottom = guard_end.unwrap_or(bottom);
+    let stack_ptr = stack_ptr().unwrap_or(top);
+    if stack_ptr > bottom {
+        unsafe { paint(bottom, stack_ptr as usize - bottom as usize) };
+    }
+    MainStack { bottom, top, guarded }
+}
+
+impl MainStack {
+    /// Returns `true` if the stack is protected by an MPU guard region.
+    #[inline]
+    pub fn is_guarded(&self) -> bool {
+        self.guarded
+    }
+
+    /// Returns the maximum number of bytes of the stack that have been used
+    /// since [`guard_main_stack`] call.
+    pub fn high_water_mark(&self) -> usize {
+        let size = self.top as usize - self.bottom as usize;
+        size - unsafe { unused(self.bottom, size) }
+    }
+}
+
+unsafe impl Send for MainStack {}
+unsafe impl Sync for MainStack {}
+
+/// Fills `size` bytes starting at `bottom` with [`PAINT`].
+pub(crate) unsafe fn paint(bottom: *mut u8, size: usize) {
+    for offset in 0..size {
+        unsafe { write_volatile(bottom.add(offset), PAINT) };
+    }
+}
+
+/// Returns the number of bytes starting at `bottom` that still contain
+/// [`PAINT`].
+pub(crate) unsafe fn unused(bottom: *const u8, size: usize) -> usize {
+    (0..size).take_while(|&offset| unsafe { read_volatile(bottom.add(offset)) } == PAINT).count()
+}
+
+fn stack_ptr() -> Option<*mut u8> {
+    #[cfg(feature = "std")]
+    return None;
+    let stack_ptr: *mut u8;
+    unsafe { llvm_asm!("mrs $0, msp" : "=r"(stack_ptr) ::: "volatile") };
+    Some(stack_ptr)
+}
+
+#[cfg(feature = "memory-protection-unit")]
+mod mpu {
+    use crate::{map::reg::mpu, reg::prelude::*};
+    use drone_core::token::Token;
+
+    const GUARD_REGION: u32 = 7;
+    const GUARD_SIZE: u32 = 5;
+
+    /// Configures the guard region at `bottom`, and returns the end of the
+    /// guard region.
+    #[cfg(not(armv8m))]
+    pub(super) unsafe fn guard(bottom: *mut u8) -> Option<*mut u8> {
+        let mpu_type = unsafe { mpu::Type::<Srt>::take() };
+        if mpu_type.load().dregion() == 0 {
+            return None;
+        }
+        let guard_size = 1 << GUARD_SIZE + 1;
+        let guard_ptr = (bottom as usize + guard_size - 1) & !(guard_size - 1);
+        unsafe {
+            mpu::Rbar::<Srt>::take().store(|r| {
+                r.write_addr(guard_ptr as u32 >> 5).set_valid().write_region(GUARD_REGION)
+            });
+            mpu::Rasr::<Srt>::take()
+                .store(|r| r.write_ap(0b000).write_size(GUARD_SIZE).set_enable());
+            mpu::Ctrl::<Srt>::take().modify(|r| r.set_enable().set_privdefena());
+        }
+        Some((guard_ptr + guard_size) as *mut u8)
+    }
+
+    /// Configures the guard region at `bottom`, and returns the end of the
+    /// guard region.
+    #[cfg(armv8m)]
+    pub(super) unsafe fn guard(bottom: *mut u8) -> Option<*mut u8> {
+        let mpu_type = unsafe { mpu::Type::<Srt>::take() };
+        if mpu_type.load().dregion() == 0 {
+            return None;
+        }
+        // PMSAv8 regions are aligned to 32 bytes, and have no no-access
+        // permission. The guard is read-only for privileged code, which faults
+        // on stack pushes.
+        let guard_size = 1 << GUARD_SIZE;
+        let guard_ptr = (bottom as usize + guard_size - 1) & !(guard_size - 1);
+        unsafe {
+            mpu::Rnr::<Srt>::take().store(|r| r.write_region(GUARD_REGION));
+            mpu::Rbar::<Srt>::take()
+                .store(|r| r.write_base(guard_ptr as u32 >> 5).write_ap(0b10).set_xn());
+            crate::thr::init::mpu::set_guard_attr();
+            mpu::Rlar::<Srt>::take().store(|r| {
+                r.write_limit((guard_ptr + guard_size - 1) as u32 >> 5).write_attrindx(0).set_en()
+            });
+            mpu::Ctrl::<Srt>::take().store(|r| r.set_enable().set_privdefena());
+        }
+        Some((guard_ptr + guard_size) as *mut u8)
+    }
+}
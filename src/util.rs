@@ -0,0 +1,7 @@
+//! Crate-private helpers.
+
+/// Fails const evaluation, or panics at run-time, if `cond` is `false`.
+#[allow(clippy::no_effect, clippy::unnecessary_operation)]
+pub(crate) const fn assert(cond: bool) {
+    [()][!cond as usize];
+}
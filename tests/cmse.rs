@@ -0,0 +1,85 @@
+#![cfg(feature = "security-extension")]
+#![feature(naked_functions)]
+#![feature(prelude_import)]
+
+#[prelude_import]
+#[allow(unused_imports)]
+use drone_core::prelude::*;
+
+#[cfg(feature = "std")]
+use drone_core::token::Token;
+use drone_cortexm::{cmse, cmse_veneer_asm};
+#[cfg(feature = "std")]
+use drone_cortexm::{
+    cortexm_reg_tokens,
+    drv::sau::{Sau, SauRegion},
+    map::periph::sau::periph_sau,
+    sim::Sim,
+};
+
+#[cfg(feature = "std")]
+cortexm_reg_tokens! {
+    index => Regs;
+}
+
+#[cfg(feature = "std")]
+const SAU_CTRL: usize = 0xE000_EDD0;
+#[cfg(feature = "std")]
+const SAU_TYPE: usize = 0xE000_EDD4;
+#[cfg(feature = "std")]
+const SAU_RNR: usize = 0xE000_EDD8;
+#[cfg(feature = "std")]
+const SAU_RBAR: usize = 0xE000_EDDC;
+#[cfg(feature = "std")]
+const SAU_RLAR: usize = 0xE000_EDE0;
+
+extern "C" fn read_counter(index: u32) -> u32 {
+    index * 2
+}
+
+cmse::nonsecure_entry! {
+    /// Reads a counter owned by the Secure image.
+    pub fn nsc_read_counter => read_counter;
+}
+
+#[test]
+fn veneer() {
+    let veneer = cmse_veneer_asm!().lines().map(str::trim).collect::<Vec<_>>();
+    assert_eq!(veneer, [
+        "sg",
+        "push {r4, lr}",
+        "movw r12, :lower16:${0:c}",
+        "movt r12, :upper16:${0:c}",
+        "blx r12",
+        "pop {r4, lr}",
+        "mov r1, #0",
+        "mov r2, #0",
+        "mov r3, #0",
+        "mov r12, #0",
+        "msr apsr_nzcvq, r1",
+        "bxns lr",
+    ]);
+    assert_eq!(read_counter(21), 42);
+    let _: unsafe extern "C" fn() = nsc_read_counter;
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn sau_regions() {
+    let sim = Sim::lock();
+    let reg = unsafe { Regs::take() };
+    sim.write::<u32>(SAU_TYPE, 8);
+    let mut sau = Sau::new(periph_sau!(reg));
+    assert_eq!(sau.region_count(), 8);
+    sau.set_region(2, &SauRegion::new(0x0803_FE00, 0x0803_FFFF).non_secure_callable());
+    assert_eq!(sim.read::<u32>(SAU_RNR), 2);
+    assert_eq!(sim.read::<u32>(SAU_RBAR), 0x0803_FE00);
+    assert_eq!(sim.read::<u32>(SAU_RLAR), 0x0803_FFE0 | 0b11);
+    sau.enable();
+    assert_eq!(sim.read::<u32>(SAU_CTRL), 1);
+    sau.clear_region(2);
+    assert_eq!(sim.read::<u32>(SAU_RLAR), 0);
+    sau.disable(true);
+    assert_eq!(sim.read::<u32>(SAU_CTRL), 0b10);
+    drop(sau.free());
+}
@@ -0,0 +1,43 @@
+#![cfg(feature = "std")]
+#![feature(prelude_import)]
+
+#[prelude_import]
+#[allow(unused_imports)]
+use drone_core::prelude::*;
+
+use drone_core::token::Token;
+use drone_cortexm::{
+    cortexm_reg_tokens,
+    drv::dwt::{Dwt, Instant},
+    map::periph::dwt::periph_dwt,
+    sim::Sim,
+};
+
+cortexm_reg_tokens! {
+    index => Regs;
+    exclude => {
+        scb_ccr,
+        mpu_type, mpu_ctrl, mpu_rnr, mpu_rbar, mpu_rasr,
+    }
+}
+
+const DWT_CTRL: usize = 0xE000_1000;
+const DWT_CYCCNT: usize = 0xE000_1004;
+const SCB_DEMCR: usize = 0xE000_EDFC;
+
+#[test]
+fn dwt_cycle_counter() {
+    let sim = Sim::lock();
+    let reg = unsafe { Regs::take() };
+    let dwt = Dwt::new(periph_dwt!(reg));
+    assert_eq!(sim.read::<u32>(SCB_DEMCR), 1 << 24);
+    assert_eq!(sim.read::<u32>(DWT_CTRL), 1);
+    sim.write::<u32>(DWT_CYCCNT, 0xFFFF_FFF0);
+    let start = dwt.now();
+    assert_eq!(start, Instant::from_cycles(0xFFFF_FFF0));
+    sim.write::<u32>(DWT_CYCCNT, 0x10);
+    assert_eq!(dwt.elapsed(start), 0x20);
+    assert!(dwt.now().is_after(start));
+    assert!(!start.is_after(dwt.now()));
+    assert_eq!(start + 0x20, dwt.now());
+}
@@ -0,0 +1,118 @@
+#![feature(prelude_import)]
+
+#[prelude_import]
+#[allow(unused_imports)]
+use drone_core::prelude::*;
+
+use drone_cortexm::fault::{decode, BusCause, FaultReport, MemManageCause, UsageCause};
+#[cfg(feature = "security-extension")]
+use drone_cortexm::fault::{decode_secure, SecureCause};
+#[cfg(feature = "std")]
+use drone_cortexm::{
+    fault::{ExceptionFrame, Fault},
+    sim::Sim,
+};
+
+#[cfg(feature = "std")]
+const SCB_BFAR: usize = 0xE000_ED38;
+#[cfg(feature = "std")]
+const SCB_CFSR: usize = 0xE000_ED28;
+#[cfg(feature = "std")]
+const SCB_HFSR: usize = 0xE000_ED2C;
+#[cfg(feature = "std")]
+const SCB_MMFAR: usize = 0xE000_ED34;
+
+#[test]
+fn decode_mem_manage() {
+    assert_eq!(decode(0, 1 << 1 | 1 << 7, 0x2000_0000, 0), FaultReport::MemManage {
+        cause: MemManageCause::DataAccess,
+        address: Some(0x2000_0000),
+    });
+    assert_eq!(decode(1 << 30, 1 << 0, 0x2000_0000, 0), FaultReport::MemManage {
+        cause: MemManageCause::InstructionAccess,
+        address: None,
+    });
+}
+
+#[test]
+fn decode_bus() {
+    assert_eq!(decode(0, 1 << 9 | 1 << 15, 0, 0x4000_0000), FaultReport::Bus {
+        cause: BusCause::Precise,
+        address: Some(0x4000_0000),
+    });
+    assert_eq!(decode(0, 1 << 10, 0, 0x4000_0000), FaultReport::Bus {
+        cause: BusCause::Imprecise,
+        address: None,
+    });
+}
+
+#[test]
+fn decode_usage() {
+    assert_eq!(decode(1 << 30, 1 << 25, 0, 0), FaultReport::Usage {
+        cause: UsageCause::DivideByZero
+    });
+    assert_eq!(decode(0, 1 << 16, 0, 0), FaultReport::Usage {
+        cause: UsageCause::UndefinedInstruction
+    });
+}
+
+#[test]
+fn decode_hard_fault() {
+    assert_eq!(decode(1 << 1, 0, 0, 0), FaultReport::VectorTable);
+    assert_eq!(decode(1 << 31, 0, 0, 0), FaultReport::DebugEvent);
+    assert_eq!(decode(0, 0, 0, 0), FaultReport::Unknown);
+}
+
+#[cfg(feature = "security-extension")]
+#[test]
+fn decode_secure_fault() {
+    assert_eq!(decode_secure(1 << 3 | 1 << 6, 0x3000_0000), Some(FaultReport::Secure {
+        cause: SecureCause::AttributionUnitViolation,
+        address: Some(0x3000_0000),
+    }));
+    assert_eq!(decode_secure(1 << 0, 0x3000_0000), Some(FaultReport::Secure {
+        cause: SecureCause::InvalidEntryPoint,
+        address: None,
+    }));
+    assert_eq!(decode_secure(0, 0), None);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn fault_capture() {
+    let sim = Sim::lock();
+    let frame = ExceptionFrame {
+        r0: 0,
+        r1: 0,
+        r2: 0,
+        r3: 0,
+        r12: 0,
+        lr: 0x0800_0101,
+        pc: 0x0800_0200,
+        xpsr: 0x0100_0000,
+    };
+    // Precise BusFault with a valid address.
+    sim.write::<u32>(SCB_CFSR, 1 << 15 | 1 << 9);
+    sim.write::<u32>(SCB_BFAR, 0x4000_1000);
+    let fault = Fault::capture(frame, 0xFFFF_FFF9);
+    assert_eq!(fault.report, FaultReport::Bus {
+        cause: BusCause::Precise,
+        address: Some(0x4000_1000)
+    });
+    assert!(!fault.escalated);
+    assert_eq!(fault.cfsr, 1 << 15 | 1 << 9);
+    assert_eq!(fault.bfar, 0x4000_1000);
+    assert_eq!(fault.exc_return, 0xFFFF_FFF9);
+    // MemManage fault escalated to HardFault, without a valid address.
+    sim.write::<u32>(SCB_HFSR, 1 << 30);
+    sim.write::<u32>(SCB_CFSR, 1 << 1);
+    sim.write::<u32>(SCB_MMFAR, 0x2000_0000);
+    let fault = Fault::capture(frame, 0xFFFF_FFFD);
+    assert_eq!(fault.report, FaultReport::MemManage {
+        cause: MemManageCause::DataAccess,
+        address: None
+    });
+    assert!(fault.escalated);
+    assert_eq!(fault.hfsr, 1 << 30);
+    assert_eq!(fault.mmfar, 0x2000_0000);
+}
@@ -0,0 +1,43 @@
+#![cfg(feature = "std")]
+#![feature(prelude_import)]
+
+#[prelude_import]
+#[allow(unused_imports)]
+use drone_core::prelude::*;
+
+use drone_cortexm::{
+    fault::{record, ExceptionFrame, Fault, FaultReport, UsageCause},
+    sim::Sim,
+};
+
+const SCB_CFSR: usize = 0xE000_ED28;
+const SCB_HFSR: usize = 0xE000_ED2C;
+
+#[test]
+fn fault_crash_record() {
+    let sim = Sim::lock();
+    record::clear();
+    assert_eq!(record::take(), None);
+    sim.write::<u32>(SCB_HFSR, 1 << 30);
+    sim.write::<u32>(SCB_CFSR, 1 << 25);
+    let frame = ExceptionFrame {
+        r0: 1,
+        r1: 2,
+        r2: 3,
+        r3: 4,
+        r12: 12,
+        lr: 0x0800_0101,
+        pc: 0x0800_0200,
+        xpsr: 0x0100_0025,
+    };
+    let fault = Fault::capture(frame, 0xFFFF_FFFD);
+    assert_eq!(fault.report, FaultReport::Usage { cause: UsageCause::DivideByZero });
+    assert!(fault.escalated);
+    record::save(&fault);
+    let crash = record::take().unwrap();
+    assert_eq!(crash.frame, frame);
+    assert_eq!(crash.exc_return, 0xFFFF_FFFD);
+    assert_eq!(crash.vectactive, 0x25);
+    assert_eq!(crash.report(), fault.report);
+    assert_eq!(record::take(), None);
+}
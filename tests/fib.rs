@@ -0,0 +1,33 @@
+#![feature(const_fn_fn_ptr_basics)]
+#![feature(prelude_import)]
+
+#[prelude_import]
+#[allow(unused_imports)]
+use drone_core::prelude::*;
+
+use drone_cortexm::{
+    fib, sv,
+    sv::{SwitchBackService, SwitchContextService},
+};
+
+sv! {
+    supervisor => pub Sv;
+    array => pub SERVICES;
+    services => {
+        SwitchContextService;
+        SwitchBackService;
+    }
+}
+
+#[test]
+fn proc_stack_high_water_mark() {
+    let mut fiber = unsafe { fib::new_proc_unchecked::<Sv, (), (), (), _>(0x100, |(), _| {}) };
+    assert_eq!(fiber.stack_high_water_mark(), None);
+    fiber.paint_stack();
+    let initial = fiber.stack_high_water_mark().unwrap();
+    // Only the initial frame is used.
+    assert!(initial > 0);
+    assert!(initial < 0x100);
+    fiber.paint_stack();
+    assert_eq!(fiber.stack_high_water_mark(), Some(initial));
+}
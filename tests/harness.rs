@@ -0,0 +1,61 @@
+#![feature(prelude_import)]
+
+#[prelude_import]
+#[allow(unused_imports)]
+use drone_core::prelude::*;
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use drone_cortexm::test::execute;
+
+static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+#[drone_cortexm::test]
+mod plain {
+    use super::CALLS;
+    use core::sync::atomic::Ordering;
+
+    #[test]
+    fn first() {
+        CALLS.fetch_add(1, Ordering::SeqCst);
+    }
+
+    #[test]
+    #[ignore]
+    fn ignored() {
+        unreachable!();
+    }
+
+    #[test]
+    fn second() {
+        CALLS.fetch_add(10, Ordering::SeqCst);
+    }
+}
+
+#[drone_cortexm::test(u32)]
+mod with_context {
+    #[test]
+    fn context(context: &u32) {
+        assert_eq!(*context, 42);
+    }
+
+    #[test]
+    fn no_context() {}
+}
+
+#[test]
+fn suite() {
+    let cases = plain::TESTS.iter().map(|case| (case.name, case.ignore)).collect::<Vec<_>>();
+    assert_eq!(cases, [
+        ("harness::plain::first", false),
+        ("harness::plain::ignored", true),
+        ("harness::plain::second", false),
+    ]);
+    assert_eq!(execute(plain::TESTS, &()), 0);
+    assert_eq!(CALLS.load(Ordering::SeqCst), 11);
+}
+
+#[test]
+fn suite_with_context() {
+    assert_eq!(with_context::TESTS.len(), 2);
+    assert_eq!(execute(with_context::TESTS, &42), 0);
+}
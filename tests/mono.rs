@@ -0,0 +1,81 @@
+#![cfg(feature = "std")]
+#![feature(const_fn_fn_ptr_basics)]
+#![feature(prelude_import)]
+
+#[prelude_import]
+#[allow(unused_imports)]
+use drone_core::prelude::*;
+
+use core::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use drone_core::token::Token;
+use drone_cortexm::{
+    cortexm_reg_tokens,
+    drv::{
+        mono::{Duration, Instant, Mono},
+        sys_tick::SysTick,
+        timer::Elapsed,
+    },
+    map::periph::sys_tick::periph_sys_tick,
+    sim::Sim,
+    thr,
+    thr::prelude::*,
+};
+use futures::{future, task::noop_waker_ref};
+
+cortexm_reg_tokens! {
+    index => Regs;
+    exclude => {
+        scb_ccr,
+        mpu_type, mpu_ctrl, mpu_rnr, mpu_rbar, mpu_rasr,
+    }
+}
+
+thr! {
+    thread => pub Thr {};
+    local => pub ThrLocal {};
+    vtable => pub Vtable;
+    index => pub Thrs;
+    init => pub ThrsInit;
+    threads => {
+        interrupts => {
+            38: pub usart2;
+        };
+    };
+}
+
+const STK_CTRL: usize = 0xE000_E010;
+const STK_LOAD: usize = 0xE000_E014;
+const STK_VAL: usize = 0xE000_E018;
+
+#[test]
+fn mono_sleep() {
+    let sim = Sim::lock();
+    let reg = unsafe { Regs::take() };
+    let thr = unsafe { Thrs::take() };
+    let mono = Mono::new(SysTick::new(periph_sys_tick!(reg), thr.usart2));
+    assert_eq!(sim.read::<u32>(STK_LOAD), 0x00FF_FFFF);
+    assert_eq!(sim.read::<u32>(STK_CTRL) & 0b11, 0b11);
+    assert_eq!(mono.now(), Instant::from_ticks(0));
+    sim.write::<u32>(STK_VAL, 0x00FF_FFFF - 99);
+    assert_eq!(mono.now(), Instant::from_ticks(100));
+    let mut elapsed = mono.deadline(Instant::from_ticks(50), future::pending::<()>());
+    assert!(matches!(poll(&mut elapsed), Poll::Ready(Err(Elapsed))));
+    let mut ready = mono.timeout(Duration::from_ticks(10), future::ready(5));
+    assert!(matches!(poll(&mut ready), Poll::Ready(Ok(5))));
+    let mut sleep = mono.clone().sleep(Duration::from_ticks(1000));
+    assert!(poll(&mut sleep).is_pending());
+    unsafe { thr::thread_resume::<Thr>(Usart2::THR_IDX) };
+    // The counter is reprogrammed to the deadline.
+    assert_eq!(sim.read::<u32>(STK_LOAD), 999);
+    assert_eq!(sim.read::<u32>(STK_VAL), 0);
+    assert!(poll(&mut sleep).is_pending());
+    // The counter has reached zero.
+    sim.write::<u32>(STK_CTRL, 1 << 16 | 0b11);
+    unsafe { thr::thread_resume::<Thr>(Usart2::THR_IDX) };
+    assert!(poll(&mut sleep).is_ready());
+    assert_eq!(sim.read::<u32>(STK_LOAD), 0x00FF_FFFF);
+}
@@ -0,0 +1,177 @@
+#![cfg(feature = "memory-protection-unit")]
+#![feature(const_fn_fn_ptr_basics)]
+#![feature(prelude_import)]
+
+#[prelude_import]
+#[allow(unused_imports)]
+use drone_core::prelude::*;
+
+#[cfg(feature = "std")]
+use drone_core::token::Token;
+#[cfg(feature = "std")]
+use drone_cortexm::{
+    cortexm_reg_tokens, drv::mpu::Mpu, map::periph::mpu::periph_mpu, sim::Sim, thr,
+};
+use drone_cortexm::{
+    drv::mpu::{Access, MemAttr, MpuRegion, RegionSize},
+    fib, sv,
+    sv::{SwitchBackService, SwitchContextService},
+};
+
+#[cfg(feature = "std")]
+cortexm_reg_tokens! {
+    index => Regs;
+}
+
+#[cfg(feature = "std")]
+const MPU_TYPE: usize = 0xE000_ED90;
+#[cfg(feature = "std")]
+const MPU_CTRL: usize = 0xE000_ED94;
+#[cfg(feature = "std")]
+const MPU_RNR: usize = 0xE000_ED98;
+#[cfg(feature = "std")]
+const MPU_RBAR: usize = 0xE000_ED9C;
+#[cfg(feature = "std")]
+const MPU_RASR: usize = 0xE000_EDA0;
+
+sv! {
+    supervisor => pub Sv;
+    array => pub SERVICES;
+    services => {
+        SwitchContextService;
+        SwitchBackService;
+    }
+}
+
+const FLASH: MpuRegion = MpuRegion::new(0x0800_0000, RegionSize::M1)
+    .access(Access::ReadOnly)
+    .attributes(MemAttr::NORMAL_WT);
+
+const RAM_WINDOW: MpuRegion = MpuRegion::new(0x2000_4000, RegionSize::K4)
+    .access(Access::FullAccess)
+    .attributes(MemAttr::NORMAL_WB_WA)
+    .execute_never();
+
+#[test]
+fn region_bits() {
+    assert_eq!(FLASH.rbar(1), 0x0800_0011);
+    assert_eq!(FLASH.rasr(), 0b110 << 24 | 1 << 17 | 19 << 1 | 1);
+    let region = MpuRegion::new(0x2000_0400, RegionSize::K1)
+        .access(Access::FullAccess)
+        .attributes(MemAttr::NORMAL_NON_CACHEABLE)
+        .shareable()
+        .execute_never()
+        .disable_subregions(0b1000_0001);
+    assert_eq!(region.base(), 0x2000_0400);
+    assert_eq!(
+        region.rasr(),
+        1 << 28 | 0b011 << 24 | 0b001 << 19 | 1 << 18 | 0b1000_0001 << 8 | 9 << 1 | 1
+    );
+}
+
+#[test]
+#[should_panic]
+fn region_misaligned() {
+    MpuRegion::new(0x2000_0200, RegionSize::K1);
+}
+
+#[test]
+#[should_panic]
+fn region_subregions_too_small() {
+    MpuRegion::new(0x2000_0000, RegionSize::B128).disable_subregions(1);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn driver() {
+    let sim = Sim::lock();
+    let reg = unsafe { Regs::take() };
+    sim.write::<u32>(MPU_TYPE, 8 << 8);
+    let mut mpu = Mpu::new(periph_mpu!(reg));
+    assert_eq!(mpu.region_count(), 8);
+    mpu.set_region(4, &RAM_WINDOW);
+    assert_eq!(sim.read::<u32>(MPU_RBAR), RAM_WINDOW.rbar(4));
+    assert_eq!(sim.read::<u32>(MPU_RASR), RAM_WINDOW.rasr());
+    mpu.enable(true, false);
+    assert_eq!(sim.read::<u32>(MPU_CTRL), 0b101);
+    mpu.clear_region(4);
+    assert_eq!(sim.read::<u32>(MPU_RNR), 4);
+    assert_eq!(sim.read::<u32>(MPU_RASR), 0);
+    mpu.disable();
+    assert_eq!(sim.read::<u32>(MPU_CTRL), 0);
+    drop(mpu.free());
+}
+
+#[cfg(feature = "std")]
+#[test]
+#[should_panic]
+fn driver_fiber_region() {
+    let sim = Sim::lock();
+    let reg = unsafe { Regs::take() };
+    sim.write::<u32>(MPU_TYPE, 8 << 8);
+    Mpu::new(periph_mpu!(reg)).set_region(3, &RAM_WINDOW);
+}
+
+#[cfg(feature = "std")]
+#[test]
+#[should_panic]
+fn driver_main_stack_guard_region() {
+    let sim = Sim::lock();
+    let reg = unsafe { Regs::take() };
+    sim.write::<u32>(MPU_TYPE, 8 << 8);
+    Mpu::new(periph_mpu!(reg)).clear_region(7);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn driver_main_stack_guard() {
+    let sim = Sim::lock();
+    let reg = unsafe { Regs::take() };
+    sim.write::<u32>(MPU_TYPE, 8 << 8);
+    let mut mpu = Mpu::new(periph_mpu!(reg));
+    mpu.enable(true, true);
+    assert_eq!(sim.read::<u32>(MPU_CTRL), 0b111);
+    let mut stack = [0_u8; 256];
+    let bottom = stack.as_mut_ptr();
+    let main_stack = unsafe { thr::guard_main_stack(bottom, bottom.add(stack.len())) };
+    assert!(main_stack.is_guarded());
+    assert_eq!(sim.read::<u32>(MPU_RBAR) & 0b1_1111, 0b1_0111);
+    assert_eq!(sim.read::<u32>(MPU_CTRL), 0b111);
+}
+
+#[test]
+fn fiber_regions() {
+    let mut fiber = fib::new_proc_unprivileged::<Sv, (), (), (), _>(0x200, |(), _| {});
+    let [guard, region1, region2, region3] = fiber.mpu_regions();
+    // The guard is a 64-byte no-access region.
+    assert_eq!(guard.0 & 0x3F, 1 << 4);
+    assert_eq!(guard.1, 5 << 1 | 1);
+    assert_eq!(region1, (1 << 4 | 1, 0));
+    assert_eq!(region2, (1 << 4 | 2, 0));
+    assert_eq!(region3, (1 << 4 | 3, 0));
+    fiber.set_mpu_region(1, &RAM_WINDOW);
+    fiber.set_mpu_region(3, &FLASH);
+    assert_eq!(fiber.mpu_regions(), [
+        guard,
+        (RAM_WINDOW.rbar(1), RAM_WINDOW.rasr()),
+        region2,
+        (FLASH.rbar(3), FLASH.rasr()),
+    ]);
+    fiber.clear_mpu_region(1);
+    assert_eq!(fiber.mpu_regions(), [guard, region1, region2, (FLASH.rbar(3), FLASH.rasr())]);
+}
+
+#[test]
+#[should_panic]
+fn fiber_region_guard() {
+    let mut fiber = fib::new_proc_unprivileged::<Sv, (), (), (), _>(0x200, |(), _| {});
+    fiber.set_mpu_region(0, &RAM_WINDOW);
+}
+
+#[test]
+#[should_panic]
+fn fiber_region_unchecked() {
+    let mut fiber =
+        unsafe { fib::new_proc_unprivileged_unchecked::<Sv, (), (), (), _>(0x200, |(), _| {}) };
+    fiber.set_mpu_region(1, &RAM_WINDOW);
+}
@@ -0,0 +1,74 @@
+#![cfg(feature = "std")]
+#![feature(const_fn_fn_ptr_basics)]
+#![feature(prelude_import)]
+
+#[prelude_import]
+#[allow(unused_imports)]
+use drone_core::prelude::*;
+
+use drone_core::token::Token;
+use drone_cortexm::{
+    sim::Sim,
+    thr,
+    thr::{
+        prelude::*,
+        priority::{self, PriorityGrouping, Resource, SysException},
+    },
+};
+
+thr! {
+    thread => pub Thr {};
+    local => pub ThrLocal {};
+    vtable => pub Vtable;
+    index => pub Thrs;
+    init => pub ThrsInit;
+    threads => {
+        interrupts => {
+            37: pub usart1;
+            38: pub usart2;
+        };
+    };
+}
+
+const SCB_AIRCR: usize = 0xE000_ED0C;
+const SCB_SHPR2: usize = 0xE000_ED1C;
+const SCB_SHPR3: usize = 0xE000_ED20;
+
+#[test]
+fn priority_management() {
+    let sim = Sim::lock();
+    let thr = unsafe { Thrs::take() };
+    let grouping = PriorityGrouping::Group3Sub5;
+    priority::set_priority_grouping(grouping);
+    assert_eq!(sim.read::<u32>(SCB_AIRCR) & 0xFFFF_0700, 0x05FA_0400);
+    sim.write::<u32>(SCB_AIRCR, 0xFA05_0400);
+    assert_eq!(priority::priority_grouping(), grouping);
+    assert_eq!(grouping.priority(0b101, 0b1_0000), 0b1011_0000);
+    assert_eq!(grouping.group(0b1011_0000), 0b101);
+    priority::set_sys_priority(SysException::SysTick, 0x40);
+    priority::set_sys_priority(SysException::PendSv, 0xF0);
+    priority::set_sys_priority(SysException::SvCall, 0x10);
+    assert_eq!(sim.read::<u32>(SCB_SHPR3), 0x40F0_0000);
+    assert_eq!(sim.read::<u32>(SCB_SHPR2), 0x1000_0000);
+    assert_eq!(priority::sys_priority(SysException::PendSv), 0xF0);
+    thr.usart1.set_priority(0x80);
+    thr.usart2.set_priority(0x20);
+    let resource = Resource::new(0_u32).user(thr.usart1).user(thr.usart2);
+    assert_eq!(resource.ceiling(), 0x20);
+    assert_eq!(priority::critical_section(resource.ceiling(), || 1), 1);
+    resource.lock(|value| *value += 1);
+    assert_eq!(resource.into_inner(), 1);
+}
+
+#[test]
+#[should_panic]
+fn priority_resource_nested_lock() {
+    let resource = Resource::with_ceiling(0_u32, 0x20);
+    resource.lock(|_| resource.lock(|_| {}));
+}
+
+#[test]
+#[should_panic]
+fn priority_out_of_range() {
+    PriorityGrouping::Group3Sub5.priority(0b1000, 0);
+}
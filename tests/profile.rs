@@ -0,0 +1,79 @@
+#![cfg(feature = "std")]
+#![feature(const_fn_fn_ptr_basics)]
+#![feature(prelude_import)]
+
+#[prelude_import]
+#[allow(unused_imports)]
+use drone_core::prelude::*;
+
+use drone_core::token::Token;
+use drone_cortexm::{
+    sim::Sim,
+    thr,
+    thr::{
+        prelude::*,
+        profile::{self, ThrProfiler, ThrStats},
+    },
+};
+
+thr! {
+    thread => pub Thr {};
+    local => pub ThrLocal {};
+    vtable => pub Vtable;
+    index => pub Thrs;
+    init => pub ThrsInit;
+    profiler => pub Profiler;
+    threads => {
+        interrupts => {
+            37: pub usart1;
+            38: pub usart2;
+        };
+    };
+}
+
+const DWT_CYCCNT: usize = 0xE000_1004;
+const ITM_TER: usize = 0xE000_0E00;
+const ITM_TCR: usize = 0xE000_0E80;
+
+#[test]
+fn profiler_stats() {
+    let sim = Sim::lock();
+    let thr = unsafe { Thrs::take() };
+    let profiler = unsafe { Profiler::take() };
+    let counters = profiler.counters();
+    sim.write::<u32>(DWT_CYCCNT, 100);
+    profile::run(counters, Usart1::THR_IDX, || {
+        sim.write::<u32>(DWT_CYCCNT, 150);
+        profile::run(counters, Usart2::THR_IDX, || sim.write::<u32>(DWT_CYCCNT, 170));
+        sim.write::<u32>(DWT_CYCCNT, 200);
+    });
+    sim.write::<u32>(DWT_CYCCNT, 300);
+    profile::run(counters, Usart2::THR_IDX, || sim.write::<u32>(DWT_CYCCNT, 340));
+    assert_eq!(profiler.stats(thr.usart1), ThrStats {
+        calls: 1,
+        preemptions: 1,
+        min_cycles: 80,
+        max_cycles: 80,
+        total_cycles: 80,
+    });
+    let stats = profiler.stats(thr.usart2);
+    assert_eq!(stats, ThrStats {
+        calls: 2,
+        preemptions: 0,
+        min_cycles: 20,
+        max_cycles: 40,
+        total_cycles: 60,
+    });
+    assert_eq!(stats.average_cycles(), 30);
+    sim.write::<u32>(ITM_TCR, 1);
+    sim.write::<u32>(ITM_TER, 1 << 2);
+    profiler.stream(2);
+    let words = sim
+        .itm_output(2)
+        .chunks(4)
+        .map(|word| u32::from_le_bytes([word[0], word[1], word[2], word[3]]))
+        .collect::<Vec<_>>();
+    assert_eq!(words, [2, 1, 1, 80, 80, 80, 0, 3, 2, 0, 20, 40, 60, 0]);
+    profiler.reset();
+    assert_eq!(profiler.stats(thr.usart1), ThrStats::default());
+}
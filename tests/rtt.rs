@@ -0,0 +1,103 @@
+#![cfg(feature = "std")]
+#![feature(const_fn_fn_ptr_basics)]
+#![feature(prelude_import)]
+
+#[prelude_import]
+#[allow(unused_imports)]
+use drone_core::prelude::*;
+
+use core::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+use drone_core::token::Token;
+use drone_cortexm::{
+    rtt::{self, Mode},
+    sim::Sim,
+    thr,
+    thr::prelude::*,
+};
+use futures::{stream::Stream, task::noop_waker_ref};
+
+thr! {
+    thread => pub Thr {};
+    local => pub ThrLocal {};
+    vtable => pub Vtable;
+    index => pub Thrs;
+    init => pub ThrsInit;
+    threads => {
+        interrupts => {
+            37: pub usart1;
+        };
+    };
+}
+
+rtt::control_block! {
+    static RTT;
+    up => {
+        "Terminal", 8;
+    };
+    down => {
+        "Terminal", 8;
+    };
+}
+
+#[test]
+fn rtt_channels() {
+    assert_eq!(unsafe { *(&RTT as *const _ as *const [u8; 16]) }, *b"SEGGER RTT\0\0\0\0\0\0");
+    assert_eq!(RTT.up().len(), 1);
+    assert_eq!(RTT.down().len(), 1);
+    let up = &RTT.up()[0];
+    assert_eq!(up.mode(), Mode::NoBlockSkip);
+    assert_eq!(up.write(b"hello"), 5);
+    assert_eq!(up.write(b"world"), 0);
+    up.set_mode(Mode::NoBlockTrim);
+    assert_eq!(up.write(b"world"), 2);
+    assert_eq!(up.len(), 7);
+    let mut buffer = [0; 8];
+    assert_eq!(up.read(&mut buffer), 7);
+    assert_eq!(&buffer[..7], b"hellowo");
+    assert!(up.is_empty());
+    assert_eq!(up.write(b"rld"), 3);
+    assert_eq!(up.read(&mut buffer), 3);
+    assert_eq!(&buffer[..3], b"rld");
+}
+
+#[test]
+fn rtt_write_port() {
+    rtt::control_block! {
+        static BLOCK;
+        up => {
+            "Terminal", 8;
+        };
+        down => {};
+    }
+    BLOCK.write_port(0, b"log");
+    BLOCK.write_port(1, b"lost");
+    let mut buffer = [0; 8];
+    assert_eq!(BLOCK.up()[0].read(&mut buffer), 3);
+    assert_eq!(&buffer[..3], b"log");
+}
+
+#[test]
+#[should_panic]
+fn rtt_channel_name_without_nul() {
+    static STORAGE: rtt::Storage<[u8; 8]> = rtt::Storage::new([0; 8]);
+    let _ = rtt::Channel::new("Terminal", &STORAGE);
+}
+
+#[test]
+fn rtt_down_stream() {
+    let _sim = Sim::lock();
+    let thr = unsafe { Thrs::take() };
+    let down = &RTT.down()[0];
+    let mut stream = down.stream(thr.usart1);
+    let mut cx = Context::from_waker(noop_waker_ref());
+    assert!(Pin::new(&mut stream).poll_next(&mut cx).is_pending());
+    // The host writes to the down channel.
+    down.write(b"ls\n");
+    unsafe { thr::thread_resume::<Thr>(Usart1::THR_IDX) };
+    assert_eq!(Pin::new(&mut stream).poll_next(&mut cx), Poll::Ready(Some(b"ls\n".to_vec())));
+    assert!(down.is_empty());
+    assert!(Pin::new(&mut stream).poll_next(&mut cx).is_pending());
+}
@@ -0,0 +1,141 @@
+#![cfg(feature = "std")]
+#![feature(prelude_import)]
+
+#[prelude_import]
+#[allow(unused_imports)]
+use drone_core::prelude::*;
+
+use drone_cortexm::{
+    semihosting::{
+        self, File, OpenMode, MAX_PATH_LEN, SYS_CLOSE, SYS_ERRNO, SYS_FLEN, SYS_OPEN, SYS_READ,
+        SYS_WRITE,
+    },
+    sim::Sim,
+};
+use std::{
+    cell::{Cell, RefCell},
+    env,
+    process::Command,
+    slice,
+};
+
+const EXIT_CHILD: &str = "SEMIHOSTING_EXIT_CHILD";
+
+thread_local! {
+    static CALLS: RefCell<Vec<(u32, Vec<usize>, Vec<u8>)>> = RefCell::new(Vec::new());
+    static RESULT: Cell<usize> = Cell::new(0);
+}
+
+/// Records the calls, and returns `3 + mode` as the handle for `SYS_OPEN`, `2`
+/// for `SYS_ERRNO`, and `RESULT` for other operations.
+fn host(op: u32, param: usize) -> usize {
+    let params =
+        |count: usize| unsafe { slice::from_raw_parts(param as *const usize, count) }.to_vec();
+    let bytes =
+        |ptr: usize, len: usize| unsafe { slice::from_raw_parts(ptr as *const u8, len) }.to_vec();
+    let (params, data, result) = match op {
+        SYS_OPEN => {
+            let params = params(3);
+            let (name, handle) = (bytes(params[0], params[2] + 1), 3 + params[1]);
+            (params, name, handle)
+        }
+        SYS_WRITE => {
+            let params = params(3);
+            let data = bytes(params[1], params[2]);
+            (params, data, RESULT.with(Cell::get))
+        }
+        SYS_READ => (params(3), Vec::new(), RESULT.with(Cell::get)),
+        SYS_CLOSE | SYS_FLEN => (params(1), Vec::new(), RESULT.with(Cell::get)),
+        SYS_ERRNO => (Vec::new(), Vec::new(), 2),
+        _ => (Vec::new(), Vec::new(), usize::MAX),
+    };
+    CALLS.with(|calls| calls.borrow_mut().push((op, params, data)));
+    result
+}
+
+fn take_calls() -> Vec<(u32, Vec<usize>, Vec<u8>)> {
+    CALLS.with(|calls| calls.borrow_mut().drain(..).collect())
+}
+
+#[test]
+fn no_handler() {
+    let _sim = Sim::lock();
+    assert_eq!(semihosting::clock().unwrap_err().errno(), u32::MAX);
+    semihosting::write_port(0, b"lost");
+}
+
+#[test]
+fn write_port() {
+    let sim = Sim::lock();
+    sim.set_semihosting(host);
+    take_calls();
+    semihosting::write_port(0, b"out");
+    semihosting::write_port(1, b"err");
+    semihosting::write_port(2, b"ignored");
+    semihosting::write_port(0, b"!");
+    let calls = take_calls();
+    let ops = calls.iter().map(|(op, _, _)| *op).collect::<Vec<_>>();
+    assert_eq!(ops, [SYS_OPEN, SYS_WRITE, SYS_OPEN, SYS_WRITE, SYS_WRITE]);
+    assert_eq!(calls[0].1[1..], [OpenMode::Write as usize, 3]);
+    assert_eq!(calls[0].2, b":tt\0");
+    assert_eq!(calls[2].1[1..], [OpenMode::Append as usize, 3]);
+    assert_eq!((calls[1].1[0], calls[1].2.as_slice()), (7, &b"out"[..]));
+    assert_eq!((calls[3].1[0], calls[3].2.as_slice()), (11, &b"err"[..]));
+    assert_eq!((calls[4].1[0], calls[4].2.as_slice()), (7, &b"!"[..]));
+}
+
+#[test]
+fn file_open() {
+    let sim = Sim::lock();
+    sim.set_semihosting(host);
+    take_calls();
+    let file = File::open("output.bin", OpenMode::WriteBinary).unwrap();
+    let calls = take_calls();
+    assert_eq!(calls[0].1[1..], [OpenMode::WriteBinary as usize, 10]);
+    assert_eq!(calls[0].2, b"output.bin\0");
+    RESULT.with(|result| result.set(0));
+    file.close().unwrap();
+    assert_eq!(take_calls()[0].1, [8]);
+    let path = "a".repeat(MAX_PATH_LEN + 1);
+    assert_eq!(File::open(&path, OpenMode::Read).unwrap_err().errno(), 36);
+    assert!(take_calls().is_empty());
+    assert!(File::open(&path[1..], OpenMode::Read).is_ok());
+}
+
+#[test]
+fn result_decoding() {
+    let sim = Sim::lock();
+    sim.set_semihosting(host);
+    let file = File::open("data", OpenMode::ReadWrite).unwrap();
+    RESULT.with(|result| result.set(1));
+    assert_eq!(file.write(b"abcd"), Ok(3));
+    RESULT.with(|result| result.set(0));
+    assert_eq!(file.write(b""), Ok(0));
+    RESULT.with(|result| result.set(4));
+    assert_eq!(file.write(b"abcd").unwrap_err().errno(), 2);
+    RESULT.with(|result| result.set(5));
+    assert_eq!(file.write(b"abcd").unwrap_err().errno(), 2);
+    let mut buffer = [0; 8];
+    RESULT.with(|result| result.set(8));
+    assert_eq!(file.read(&mut buffer), Ok(0));
+    RESULT.with(|result| result.set(9));
+    assert_eq!(file.read(&mut buffer).unwrap_err().errno(), 2);
+    RESULT.with(|result| result.set(42));
+    assert_eq!(file.len(), Ok(42));
+    RESULT.with(|result| result.set(usize::MAX));
+    assert_eq!(file.len().unwrap_err().errno(), 2);
+    assert_eq!(file.close().unwrap_err().errno(), 2);
+}
+
+#[test]
+fn exit() {
+    if env::var_os(EXIT_CHILD).is_some() {
+        semihosting::exit(3);
+    }
+    let status = Command::new(env::current_exe().unwrap())
+        .args(&["--exact", "exit", "--test-threads=1"])
+        .env(EXIT_CHILD, "1")
+        .status()
+        .unwrap();
+    assert_eq!(status.code(), Some(3));
+}
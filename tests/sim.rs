@@ -0,0 +1,96 @@
+#![cfg(feature = "std")]
+#![feature(const_fn_fn_ptr_basics)]
+#![feature(prelude_import)]
+
+#[prelude_import]
+#[allow(unused_imports)]
+use drone_core::prelude::*;
+
+use core::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use drone_core::token::Token;
+use drone_cortexm::{
+    cortexm_reg_tokens,
+    drv::{sys_tick::SysTick, timer::Timer},
+    map::periph::sys_tick::periph_sys_tick,
+    sim::Sim,
+    swo, thr,
+    thr::prelude::*,
+};
+use futures::task::noop_waker_ref;
+
+cortexm_reg_tokens! {
+    index => Regs;
+    exclude => {
+        scb_ccr,
+        mpu_type, mpu_ctrl, mpu_rnr, mpu_rbar, mpu_rasr,
+    }
+}
+
+thr! {
+    thread => pub Thr {};
+    local => pub ThrLocal {};
+    vtable => pub Vtable;
+    index => pub Thrs;
+    init => pub ThrsInit;
+    threads => {
+        interrupts => {
+            37: pub usart1;
+        };
+    };
+}
+
+const ITM_TER: usize = 0xE000_0E00;
+const ITM_TCR: usize = 0xE000_0E80;
+const NVIC_ISER1: usize = 0xE000_E104;
+const NVIC_IPR: usize = 0xE000_E400;
+const NVIC_STIR: usize = 0xE000_EF00;
+const STK_CTRL: usize = 0xE000_E010;
+const STK_LOAD: usize = 0xE000_E014;
+
+#[test]
+fn nvic() {
+    let sim = Sim::lock();
+    let thr = unsafe { Thrs::take() };
+    thr.usart1.enable_int();
+    assert_eq!(sim.read::<u32>(NVIC_ISER1), 1 << 5);
+    thr.usart1.set_priority(0xA0);
+    assert_eq!(sim.read::<u8>(NVIC_IPR + 37), 0xA0);
+    assert_eq!(thr.usart1.priority(), 0xA0);
+    thr.usart1.trigger();
+    assert_eq!(sim.read::<u32>(NVIC_STIR), 37);
+}
+
+#[test]
+fn swo_port() {
+    let sim = Sim::lock();
+    assert!(!swo::is_enabled());
+    sim.write::<u32>(ITM_TCR, 1);
+    sim.write::<u32>(ITM_TER, 1 << 2);
+    assert!(swo::is_enabled());
+    assert!(swo::is_port_enabled(2));
+    assert!(!swo::is_port_enabled(3));
+    swo::Port::new(2).write(0x1234_u16).write_bytes(b"ok");
+    assert_eq!(sim.itm_output(2), [0x34, 0x12, b'o', b'k']);
+    assert!(sim.itm_output(3).is_empty());
+}
+
+#[test]
+fn sys_tick_sleep() {
+    let sim = Sim::lock();
+    let reg = unsafe { Regs::take() };
+    let thr = unsafe { Thrs::take() };
+    let mut timer = SysTick::new(periph_sys_tick!(reg), thr.usart1);
+    let mut sleep = timer.sleep(1000);
+    assert_eq!(sim.read::<u32>(STK_LOAD), 1000);
+    assert_eq!(sim.read::<u32>(STK_CTRL) & 0b11, 0b11);
+    assert!(poll(&mut sleep).is_pending());
+    // The counter has reached zero.
+    sim.write::<u32>(STK_CTRL, 1 << 16 | 0b11);
+    unsafe { thr::thread_resume::<Thr>(Usart1::THR_IDX) };
+    assert!(poll(&mut sleep).is_ready());
+    assert_eq!(sim.read::<u32>(STK_CTRL) & 0b11, 0);
+}
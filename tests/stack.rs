@@ -0,0 +1,20 @@
+#![cfg(feature = "std")]
+#![feature(prelude_import)]
+
+#[prelude_import]
+#[allow(unused_imports)]
+use drone_core::prelude::*;
+
+use drone_cortexm::{sim::Sim, thr};
+
+#[test]
+fn main_stack_high_water_mark() {
+    let _sim = Sim::lock();
+    let mut stack = [0_u8; 256];
+    let bottom = stack.as_mut_ptr();
+    let main_stack = unsafe { thr::guard_main_stack(bottom, bottom.add(stack.len())) };
+    assert!(!main_stack.is_guarded());
+    assert_eq!(main_stack.high_water_mark(), 0);
+    unsafe { bottom.add(200).write_volatile(0) };
+    assert_eq!(main_stack.high_water_mark(), 56);
+}
@@ -0,0 +1,177 @@
+#![cfg(feature = "std")]
+#![feature(prelude_import)]
+
+#[prelude_import]
+#[allow(unused_imports)]
+use drone_core::prelude::*;
+
+use drone_cortexm::swo::decode::{
+    DataTraceKind, Decoder, Demux, ExceptionFunction, FormatTable, MessageDecoder, MessageError,
+    Packet, TimestampRelation,
+};
+
+/// ITM output of `Port::new(0).write_bytes(b"Hello")` and
+/// `Port::new(1).write(0x1234_5678_u32)`, with a local timestamp after each
+/// packet, preceded by a synchronization packet.
+const PORTS_FIXTURE: &[u8] = &[
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x80, // sync
+    0x03, b'H', b'e', b'l', b'l', // port 0, 4 bytes
+    0x30, // local timestamp, 3 cycles
+    0x01, b'o', // port 0, 1 byte
+    0xC0, 0x85, 0x01, // local timestamp, 133 cycles
+    0x0B, 0x78, 0x56, 0x34, 0x12, // port 1, 4 bytes
+    0xD0, 0x07, // local timestamp, 7 cycles, delayed
+    0x70, // overflow
+];
+
+/// DWT output with exception tracing, PC sampling, and a data trace
+/// comparator.
+const DWT_FIXTURE: &[u8] = &[
+    0x0E, 0x0F, 0x10, // SysTick entered
+    0x0E, 0x0F, 0x20, // SysTick exited
+    0x0E, 0x00, 0x30, // Thread mode returned
+    0x17, 0x23, 0x01, 0x00, 0x08, // PC sample
+    0x15, 0x00, // PC sample, sleeping
+    0x05, 0x01, // event counter, CPI overflow
+    0x9E, 0x55, 0xAA, // comparator 1, data written
+    0x1C, // extension
+    0x04, // reserved
+];
+
+/// Global timestamps of the first and second formats.
+const GLOBAL_FIXTURE: &[u8] = &[
+    0x94, 0x81, 0x82, 0x83, 0x64, // full low-order bits, wrap, clock change
+    0xB4, 0x81, 0x80, 0x80, 0x01, // high-order bits
+    0x94, 0x05, // changed 7 low-order bits
+];
+
+/// Contents of the `.drone_log` section loaded at `0x1000`.
+const FORMATS_FIXTURE: &[u8] = b"tick {}\0{:?} {{{:x}}} {:b}\0{} {} {}\0";
+
+/// Port stream of `log!` messages with the formats from `FORMATS_FIXTURE`.
+const MESSAGES_FIXTURE: &[u8] = &[
+    0x00, 0x10, 0x00, 0x00, // "tick {}"
+    0x03, 0x40, 0xE2, 0x01, 0x00, // 123456_u32
+    0x08, 0x10, 0x00, 0x00, // "{:?} {{{:x}}} {:b}"
+    0x08, 0x41, 0x00, 0x00, 0x00, // 'A'
+    0x05, 0xFE, 0xFF, // -2_i16
+    0x01, 0x05, // 5_u8
+    0x1B, 0x10, 0x00, 0x00, // "{} {} {}"
+    0x04, 0xFD, // -3_i8
+    0x09, 0x00, 0x00, 0xC0, 0x3F, // 1.5_f32
+    0x0A, 0x02, 0x00, b'o', b'k', // "ok"
+];
+
+#[test]
+fn decode_messages() {
+    let table = FormatTable::from_section(0x1000, FORMATS_FIXTURE);
+    assert_eq!(table.get(0x1008), Some("{:?} {{{:x}}} {:b}"));
+    let mut decoder = MessageDecoder::new(table);
+    assert_eq!(decoder.feed(MESSAGES_FIXTURE), [
+        Ok("tick 123456".into()),
+        Ok("'A' {fffe} 101".into()),
+        Ok("-3 1.5 ok".into()),
+    ]);
+}
+
+#[test]
+fn decode_messages_split() {
+    let mut decoder = MessageDecoder::new(FormatTable::from_section(0x1000, FORMATS_FIXTURE));
+    let mut messages = Vec::new();
+    for &byte in MESSAGES_FIXTURE {
+        messages.extend(decoder.feed(&[byte]));
+    }
+    assert_eq!(decoder.pending(), 0);
+    assert_eq!(messages.len(), 3);
+    assert!(decoder.feed(&[0x00, 0x10, 0x00, 0x00, 0x03, 0x01]).is_empty());
+    assert_eq!(decoder.pending(), 6);
+}
+
+#[test]
+fn decode_messages_invalid() {
+    let mut decoder = MessageDecoder::new(FormatTable::from_section(0x1000, FORMATS_FIXTURE));
+    assert_eq!(decoder.feed(&[0x04, 0x10, 0x00, 0x00, 0x03]), [Err(MessageError::UnknownFormat(
+        0x1004
+    ))]);
+    assert_eq!(decoder.pending(), 0);
+    assert_eq!(decoder.feed(&[0x00, 0x10, 0x00, 0x00, 0x0B, 0x00]), [Err(
+        MessageError::InvalidTag(0x0B)
+    )]);
+    assert_eq!(decoder.feed(&[0x1B, 0x10, 0x00, 0x00, 0x07, 0x02]), [Err(
+        MessageError::InvalidArgument
+    )]);
+}
+
+#[test]
+fn decode_ports() {
+    assert_eq!(Decoder::new().feed(PORTS_FIXTURE), [
+        Packet::Sync,
+        Packet::Instrumentation { port: 0, size: 4, value: 0x6C6C_6548 },
+        Packet::LocalTimestamp { delta: 3, relation: TimestampRelation::Sync },
+        Packet::Instrumentation { port: 0, size: 1, value: u32::from(b'o') },
+        Packet::LocalTimestamp { delta: 133, relation: TimestampRelation::Sync },
+        Packet::Instrumentation { port: 1, size: 4, value: 0x1234_5678 },
+        Packet::LocalTimestamp { delta: 7, relation: TimestampRelation::TimestampDelayed },
+        Packet::Overflow,
+    ]);
+}
+
+#[test]
+fn decode_dwt() {
+    assert_eq!(Decoder::new().feed(DWT_FIXTURE), [
+        Packet::ExceptionTrace { number: 15, function: ExceptionFunction::Entered },
+        Packet::ExceptionTrace { number: 15, function: ExceptionFunction::Exited },
+        Packet::ExceptionTrace { number: 0, function: ExceptionFunction::Returned },
+        Packet::PcSample(Some(0x0800_0123)),
+        Packet::PcSample(None),
+        Packet::EventCounter(0x01),
+        Packet::DataTrace { comparator: 1, kind: DataTraceKind::Write, size: 2, value: 0xAA55 },
+        Packet::Extension { hardware: true, info: 1 },
+        Packet::Unknown(0x04),
+    ]);
+}
+
+#[test]
+fn decode_global_timestamps() {
+    let low = 1 | 2 << 7 | 3 << 14 | 4 << 21;
+    let high = (1 | 1 << 21) << 26;
+    assert_eq!(Decoder::new().feed(GLOBAL_FIXTURE), [
+        Packet::GlobalTimestamp { timestamp: low, wrap: true, clock_change: true },
+        Packet::GlobalTimestamp {
+            timestamp: high | low & !0x7F | 5,
+            wrap: false,
+            clock_change: false
+        },
+    ]);
+}
+
+#[test]
+fn decode_split() {
+    let mut decoder = Decoder::new();
+    let mut packets = Vec::new();
+    for &byte in PORTS_FIXTURE {
+        packets.extend(decoder.feed(&[byte]));
+    }
+    assert_eq!(decoder.pending(), 0);
+    assert_eq!(packets, Decoder::new().feed(PORTS_FIXTURE));
+    assert!(decoder.feed(&[0x03, b'a', b'b']).is_empty());
+    assert_eq!(decoder.pending(), 3);
+}
+
+#[test]
+fn demux_ports() {
+    let mut demux = Demux::new();
+    assert_eq!(demux.feed(&PORTS_FIXTURE[..20]), [
+        Packet::Sync,
+        Packet::LocalTimestamp { delta: 3, relation: TimestampRelation::Sync },
+        Packet::LocalTimestamp { delta: 133, relation: TimestampRelation::Sync },
+    ]);
+    assert_eq!(demux.feed(&PORTS_FIXTURE[20..]), [
+        Packet::LocalTimestamp { delta: 7, relation: TimestampRelation::TimestampDelayed },
+        Packet::Overflow,
+    ]);
+    assert_eq!(demux.port(0), b"Hello");
+    assert_eq!(demux.take_port(1), [0x78, 0x56, 0x34, 0x12]);
+    assert!(demux.port(1).is_empty());
+    assert!(demux.port(31).is_empty());
+}
@@ -0,0 +1,82 @@
+#![cfg(feature = "std")]
+#![feature(const_fn_fn_ptr_basics)]
+#![feature(prelude_import)]
+
+#[prelude_import]
+#[allow(unused_imports)]
+use drone_core::prelude::*;
+
+use drone_cortexm::{
+    sim::Sim,
+    swo::buffer::{Buffer, DrainThread, DropPolicy},
+    thr,
+    thr::prelude::*,
+};
+
+thr! {
+    thread => pub Thr {};
+    local => pub ThrLocal {};
+    vtable => pub Vtable;
+    index => pub Thrs;
+    init => pub ThrsInit;
+    threads => {
+        interrupts => {
+            38: pub usart2;
+        };
+    };
+}
+
+const ITM_TER: usize = 0xE000_0E00;
+const ITM_TCR: usize = 0xE000_0E80;
+const NVIC_STIR: usize = 0xE000_EF00;
+const SCB_ICSR: usize = 0xE000_ED04;
+
+#[test]
+fn swo_buffer() {
+    static BUFFER: Buffer<[u8; 16]> = Buffer::new([0; 16]).drain_thread(DrainThread::PEND_SV);
+    let sim = Sim::lock();
+    sim.write::<u32>(ITM_TCR, 1);
+    sim.write::<u32>(ITM_TER, 1 << 2);
+    assert!(BUFFER.is_empty());
+    assert!(BUFFER.write(2, b"hello"));
+    assert!(BUFFER.write(3, b"off"));
+    assert_eq!(sim.read::<u32>(SCB_ICSR), 1 << 28);
+    assert!(!BUFFER.write(2, b"overflow"));
+    assert_eq!(BUFFER.dropped(), 1);
+    assert!(sim.itm_output(2).is_empty());
+    assert!(BUFFER.drain());
+    assert!(BUFFER.is_empty());
+    assert_eq!(sim.itm_output(2), b"hello");
+    assert!(sim.itm_output(3).is_empty());
+    assert!(BUFFER.write(2, b", world"));
+    sim.write::<u32>(SCB_ICSR, 0);
+    BUFFER.trigger();
+    assert_eq!(sim.read::<u32>(SCB_ICSR), 1 << 28);
+    assert_eq!(sim.itm_output(2), b"hello");
+    assert!(BUFFER.drain());
+    assert_eq!(sim.itm_output(2), b"hello, world");
+}
+
+#[test]
+fn swo_buffer_drain_int() {
+    static BUFFER: Buffer<[u8; 16]> =
+        Buffer::new([0; 16]).drain_thread(DrainThread::int::<Usart2>());
+    let sim = Sim::lock();
+    sim.write::<u32>(NVIC_STIR, 0);
+    assert!(BUFFER.write(2, b"hi"));
+    assert_eq!(sim.read::<u32>(NVIC_STIR), 38);
+}
+
+#[test]
+fn swo_buffer_write_through() {
+    static BUFFER: Buffer<[u8; 8]> = Buffer::new([0; 8]).policy(DropPolicy::WriteThrough);
+    let sim = Sim::lock();
+    sim.write::<u32>(ITM_TCR, 1);
+    sim.write::<u32>(ITM_TER, 1 << 2);
+    assert!(BUFFER.write(2, b"ab"));
+    assert!(!BUFFER.write(2, b"cd"));
+    assert_eq!(BUFFER.dropped(), 0);
+    assert!(BUFFER.is_empty());
+    assert_eq!(sim.itm_output(2), b"abcd");
+    assert_eq!(sim.read::<u32>(SCB_ICSR), 0);
+}
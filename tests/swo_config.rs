@@ -0,0 +1,58 @@
+#![cfg(feature = "std")]
+#![feature(prelude_import)]
+
+#[prelude_import]
+#[allow(unused_imports)]
+use drone_core::prelude::*;
+
+use drone_cortexm::{
+    sim::Sim,
+    swo::{Config, ConfigError, GlobalTimestamp, SyncPeriod, TimestampPrescaler},
+};
+
+const DWT_CTRL: usize = 0xE000_1000;
+const ITM_LAR: usize = 0xE000_0FB0;
+const ITM_TCR: usize = 0xE000_0E80;
+const SCB_DEMCR: usize = 0xE000_EDFC;
+
+#[test]
+fn swo_config() {
+    let sim = Sim::lock();
+    sim.write::<u32>(ITM_TCR, 1);
+    sim.write::<u32>(DWT_CTRL, 4 << 28 | 1);
+    Config::new()
+        .local_timestamps(TimestampPrescaler::Div16)
+        .global_timestamps(GlobalTimestamp::Cycles8192)
+        .sync_packets(SyncPeriod::Cycles64M)
+        .exception_trace()
+        .pc_sampling(2048)
+        .apply()
+        .unwrap();
+    assert_eq!(sim.read::<u32>(SCB_DEMCR), 1 << 24);
+    assert_eq!(sim.read::<u32>(ITM_LAR), 0xC5AC_CE55);
+    assert_eq!(sim.read::<u32>(ITM_TCR), 1 << 16 | 2 << 10 | 2 << 8 | 1 << 3 | 1 << 2 | 1 << 1 | 1);
+    assert_eq!(
+        sim.read::<u32>(DWT_CTRL),
+        4 << 28 | 1 << 16 | 1 << 12 | 2 << 10 | 1 << 9 | 1 << 1 | 1
+    );
+    Config::new().pc_sampling(128).apply().unwrap();
+    assert_eq!(sim.read::<u32>(ITM_TCR), 1 << 16 | 1 << 3 | 1);
+    assert_eq!(sim.read::<u32>(DWT_CTRL), 4 << 28 | 1 << 12 | 1 << 1 | 1);
+    Config::new().apply().unwrap();
+    assert_eq!(sim.read::<u32>(ITM_TCR), 1 << 16 | 1);
+    assert_eq!(sim.read::<u32>(DWT_CTRL), 4 << 28 | 1);
+    sim.write::<u32>(DWT_CTRL, 1 << 27);
+    assert_eq!(Config::new().exception_trace().apply(), Err(ConfigError::NoTracePackets));
+    sim.write::<u32>(DWT_CTRL, 1 << 25);
+    assert_eq!(
+        Config::new().sync_packets(SyncPeriod::Cycles16M).apply(),
+        Err(ConfigError::NoCycleCounter)
+    );
+    assert_eq!(Config::new().local_timestamps(TimestampPrescaler::Div1).apply(), Ok(()));
+}
+
+#[test]
+#[should_panic]
+fn swo_config_pc_sampling_interval() {
+    Config::new().pc_sampling(1536);
+}
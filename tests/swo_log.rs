@@ -0,0 +1,52 @@
+#![cfg(feature = "std")]
+#![feature(prelude_import)]
+
+#[prelude_import]
+#[allow(unused_imports)]
+use drone_core::prelude::*;
+
+use drone_cortexm::{
+    sim::Sim,
+    swo,
+    swo::{
+        decode::{FormatTable, MessageDecoder},
+        deferred,
+    },
+};
+
+const ITM_TER: usize = 0xE000_0E00;
+const ITM_TCR: usize = 0xE000_0E80;
+
+#[test]
+fn swo_log() {
+    let sim = Sim::lock();
+    swo::log!(2, "x = {}", 5_u8);
+    assert!(sim.itm_output(2).is_empty());
+    sim.write::<u32>(ITM_TCR, 1);
+    sim.write::<u32>(ITM_TER, 1 << 2);
+    let name = "adc";
+    swo::log!(2, "{}: x = {:X}, ok = {}", name, 0xBEEF_u16, true);
+    let output = sim.itm_output(2);
+    let (address, args) = output.split_at(4);
+    assert_eq!(args, [10, 3, 0, b'a', b'd', b'c', 2, 0xEF, 0xBE, 7, 1]);
+    let mut table = FormatTable::new();
+    table.insert(
+        u32::from_le_bytes([address[0], address[1], address[2], address[3]]),
+        "{}: x = {:X}, ok = {}",
+    );
+    let mut decoder = MessageDecoder::new(table);
+    assert_eq!(decoder.feed(&output), [Ok("adc: x = BEEF, ok = true".into())]);
+}
+
+#[test]
+fn swo_log_long_str() {
+    let sim = Sim::lock();
+    sim.write::<u32>(ITM_TCR, 1);
+    sim.write::<u32>(ITM_TER, 1 << 2);
+    let name = format!("x{}", "é".repeat(deferred::MAX_STR_LEN));
+    swo::log!(2, "{}", name.as_str());
+    let output = sim.itm_output(2);
+    let len = deferred::MAX_STR_LEN - 1;
+    assert_eq!(output[4..7], [deferred::TAG_STR, 63, 0]);
+    assert_eq!(output[7..], name.as_bytes()[..len]);
+}
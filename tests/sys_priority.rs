@@ -0,0 +1,48 @@
+#![cfg(feature = "std")]
+#![feature(const_fn_fn_ptr_basics)]
+#![feature(prelude_import)]
+
+#[prelude_import]
+#[allow(unused_imports)]
+use drone_core::prelude::*;
+
+use drone_core::token::Token;
+use drone_cortexm::{
+    sim::Sim,
+    thr,
+    thr::{prelude::*, priority::Resource},
+};
+
+thr! {
+    thread => pub Thr {};
+    local => pub ThrLocal {};
+    vtable => pub Vtable;
+    index => pub Thrs;
+    init => pub ThrsInit;
+    threads => {
+        exceptions => {
+            pub debug priority = 0x60;
+        };
+        interrupts => {
+            37: pub usart1;
+            39: pub usart3 priority = 0xC0;
+        };
+    };
+}
+
+const NVIC_IPR: usize = 0xE000_E400;
+const SCB_SHPR3: usize = 0xE000_ED20;
+
+#[test]
+fn priority_init() {
+    let sim = Sim::lock();
+    let thr = thr::init(unsafe { ThrsInit::take() });
+    assert_eq!(sim.read::<u32>(SCB_SHPR3), 0x0000_0060);
+    assert_eq!(sim.read::<u8>(NVIC_IPR + 37), 0);
+    assert_eq!(sim.read::<u8>(NVIC_IPR + 39), 0xC0);
+    assert_eq!(thr.debug.priority(), 0x60);
+    thr.debug.set_priority(0x30);
+    assert_eq!(sim.read::<u8>(SCB_SHPR3), 0x30);
+    let resource = Resource::new(()).user(thr.usart3).sys_user(thr.debug);
+    assert_eq!(resource.ceiling(), 0x30);
+}
@@ -0,0 +1,65 @@
+#![cfg(feature = "std")]
+#![feature(const_fn_fn_ptr_basics)]
+#![feature(prelude_import)]
+
+#[prelude_import]
+#[allow(unused_imports)]
+use drone_core::prelude::*;
+
+use drone_core::token::Token;
+use drone_cortexm::{
+    sim::Sim,
+    sv::{syscall, IntEnableService, LogService, SvService, TimeService, TriggerService},
+    thr,
+    thr::prelude::*,
+};
+
+thr! {
+    thread => pub Thr {};
+    local => pub ThrLocal {};
+    vtable => pub Vtable;
+    index => pub Thrs;
+    init => pub ThrsInit;
+    threads => {
+        interrupts => {
+            38: pub usart2;
+        };
+    };
+}
+
+const DWT_CYCCNT: usize = 0xE000_1004;
+const ITM_TER: usize = 0xE000_0E00;
+const ITM_TCR: usize = 0xE000_0E80;
+const NVIC_ISER1: usize = 0xE000_E104;
+const NVIC_STIR: usize = 0xE000_EF00;
+
+fn sv_handle<T: SvService>(frame: &mut [u32; 8]) -> u32 {
+    unsafe { T::handler(&mut *(frame.as_mut_ptr() as *mut T)) };
+    frame[0]
+}
+
+#[test]
+fn syscall_services() {
+    let sim = Sim::lock();
+    let thr = unsafe { Thrs::take() };
+    assert_eq!(sv_handle::<TriggerService>(&mut [38, 0, 0, 0, 0, 0, 0, 0]), 1);
+    assert_eq!(sim.read::<u32>(NVIC_STIR), 0);
+    syscall::grant_int(thr.usart2);
+    assert_eq!(sv_handle::<TriggerService>(&mut [38, 0, 0, 0, 0, 0, 0, 0]), 0);
+    assert_eq!(sim.read::<u32>(NVIC_STIR), 38);
+    assert_eq!(sv_handle::<IntEnableService>(&mut [38, 0, 0, 0, 0, 0, 0, 0]), 0);
+    assert_eq!(sim.read::<u32>(NVIC_ISER1), 1 << 6);
+    assert_eq!(sv_handle::<IntEnableService>(&mut [37, 0, 0, 0, 0, 0, 0, 0]), 1);
+    assert_eq!(sv_handle::<IntEnableService>(&mut [0xFFFF, 0, 0, 0, 0, 0, 0, 0]), 1);
+    syscall::revoke_int(thr.usart2);
+    assert_eq!(sv_handle::<TriggerService>(&mut [38, 0, 0, 0, 0, 0, 0, 0]), 1);
+    sim.write::<u32>(DWT_CYCCNT, 1234);
+    assert_eq!(sv_handle::<TimeService>(&mut [0; 8]), 1234);
+    sim.write::<u32>(ITM_TCR, 1);
+    sim.write::<u32>(ITM_TER, 1 << 2);
+    let word = u32::from_le_bytes(*b"ok!\0");
+    assert_eq!(sv_handle::<LogService>(&mut [2, 3, word, 0, 0, 0, 0, 0]), 0);
+    assert_eq!(sim.itm_output(2), b"ok!");
+    assert_eq!(sv_handle::<LogService>(&mut [32, 3, word, 0, 0, 0, 0, 0]), 1);
+    assert_eq!(sv_handle::<LogService>(&mut [2, 9, word, 0, 0, 0, 0, 0]), 1);
+}
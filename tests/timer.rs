@@ -0,0 +1,155 @@
+#![feature(prelude_import)]
+
+#[prelude_import]
+#[allow(unused_imports)]
+use drone_core::prelude::*;
+
+use core::{
+    future::Future,
+    num::NonZeroUsize,
+    pin::Pin,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    task::{Context, Poll},
+};
+use drone_cortexm::drv::timer::{
+    Elapsed, MissedTickPolicy, Timer, TimerInterval, TimerOverflow, TimerSleep, TimerStop,
+};
+use futures::{future, stream, stream::Stream, task::noop_waker_ref, StreamExt};
+use std::sync::Arc;
+
+#[derive(Default)]
+struct MockTimer {
+    fired: Arc<AtomicBool>,
+    stops: Arc<AtomicUsize>,
+    restarts: Arc<AtomicUsize>,
+    pulses: Vec<usize>,
+}
+
+impl Timer for MockTimer {
+    type Stop = Self;
+
+    fn sleep(&mut self, _duration: u32) -> TimerSleep<'_, Self> {
+        let fired = Arc::clone(&self.fired);
+        TimerSleep::new(
+            self,
+            Box::pin(future::poll_fn(move |_| {
+                if fired.load(Ordering::SeqCst) { Poll::Ready(()) } else { Poll::Pending }
+            })),
+        )
+    }
+
+    fn interval(
+        &mut self,
+        _duration: u32,
+    ) -> TimerInterval<'_, Self, Result<NonZeroUsize, TimerOverflow>> {
+        let pulses = self.pulses();
+        TimerInterval::new(self, Box::pin(stream::iter(pulses).map(Ok)))
+    }
+
+    fn interval_skip(&mut self, _duration: u32) -> TimerInterval<'_, Self, NonZeroUsize> {
+        let pulses = self.pulses();
+        TimerInterval::new(self, Box::pin(stream::iter(pulses)))
+    }
+}
+
+impl TimerStop for MockTimer {
+    fn stop(&mut self) {
+        self.stops.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn restart(&mut self) {
+        self.restarts.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+impl MockTimer {
+    fn with_pulses(pulses: &[usize]) -> Self {
+        Self { pulses: pulses.to_vec(), ..Self::default() }
+    }
+
+    fn pulses(&self) -> Vec<NonZeroUsize> {
+        self.pulses.iter().map(|&pulse| NonZeroUsize::new(pulse).unwrap()).collect()
+    }
+}
+
+fn poll<F: Future + Unpin>(fut: &mut F) -> Poll<F::Output> {
+    Pin::new(fut).poll(&mut Context::from_waker(noop_waker_ref()))
+}
+
+fn collect<S: Stream + Unpin>(mut stream: S) -> Vec<S::Item> {
+    let mut items = Vec::new();
+    while let Poll::Ready(Some(item)) =
+        Pin::new(&mut stream).poll_next(&mut Context::from_waker(noop_waker_ref()))
+    {
+        items.push(item);
+    }
+    items
+}
+
+fn ticks(stream: impl Stream<Item = NonZeroUsize> + Unpin) -> Vec<usize> {
+    collect(stream).into_iter().map(NonZeroUsize::get).collect()
+}
+
+#[test]
+fn timeout_ready() {
+    let mut timer = MockTimer::default();
+    let stops = Arc::clone(&timer.stops);
+    let mut timeout = timer.timeout(100, future::ready(5));
+    assert!(matches!(poll(&mut timeout), Poll::Ready(Ok(5))));
+    assert_eq!(stops.load(Ordering::SeqCst), 1);
+    drop(timeout);
+    assert_eq!(stops.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn timeout_elapsed() {
+    let mut timer = MockTimer::default();
+    let fired = Arc::clone(&timer.fired);
+    let stops = Arc::clone(&timer.stops);
+    let mut timeout = timer.timeout(100, future::pending::<()>());
+    assert!(poll(&mut timeout).is_pending());
+    assert_eq!(stops.load(Ordering::SeqCst), 0);
+    fired.store(true, Ordering::SeqCst);
+    assert!(matches!(poll(&mut timeout), Poll::Ready(Err(Elapsed))));
+    drop(timeout);
+    assert_eq!(stops.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn timeout_drop() {
+    let mut timer = MockTimer::default();
+    let stops = Arc::clone(&timer.stops);
+    let mut timeout = timer.timeout(100, future::pending::<()>());
+    assert!(poll(&mut timeout).is_pending());
+    drop(timeout);
+    assert_eq!(stops.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn missed_tick_policy() {
+    let mut timer = MockTimer::with_pulses(&[1, 3, 2]);
+    assert_eq!(ticks(timer.interval_skip(10)), [1, 3, 2]);
+    assert_eq!(
+        ticks(timer.interval_skip(10).with_missed_tick_policy(MissedTickPolicy::Skip)),
+        [1, 3, 2]
+    );
+    assert_eq!(
+        ticks(timer.interval_skip(10).with_missed_tick_policy(MissedTickPolicy::Burst)),
+        [1, 1, 1, 1, 1, 1]
+    );
+    assert_eq!(timer.restarts.load(Ordering::SeqCst), 0);
+    assert_eq!(
+        ticks(timer.interval_skip(10).with_missed_tick_policy(MissedTickPolicy::Delay)),
+        [1, 1, 1]
+    );
+    assert_eq!(timer.restarts.load(Ordering::SeqCst), 2);
+    assert_eq!(timer.stops.load(Ordering::SeqCst), 4);
+}
+
+#[test]
+fn missed_tick_policy_overflow() {
+    let mut timer = MockTimer::with_pulses(&[2, 1]);
+    let items = collect(timer.interval(10).with_missed_tick_policy(MissedTickPolicy::Burst));
+    let items = items.into_iter().map(|item| item.unwrap().get()).collect::<Vec<_>>();
+    assert_eq!(items, [1, 1, 1]);
+}
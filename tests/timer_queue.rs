@@ -0,0 +1,79 @@
+#![cfg(feature = "std")]
+#![feature(const_fn_fn_ptr_basics)]
+#![feature(prelude_import)]
+
+#[prelude_import]
+#[allow(unused_imports)]
+use drone_core::prelude::*;
+
+use core::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use drone_core::token::Token;
+use drone_cortexm::{
+    cortexm_reg_tokens,
+    drv::{
+        mono::{Duration, Instant},
+        sys_tick::SysTick,
+        timer::{Elapsed, TimerQueue},
+    },
+    map::periph::sys_tick::periph_sys_tick,
+    sim::Sim,
+    thr,
+    thr::prelude::*,
+};
+use futures::{future, task::noop_waker_ref};
+
+cortexm_reg_tokens! {
+    index => Regs;
+    exclude => {
+        scb_ccr,
+        mpu_type, mpu_ctrl, mpu_rnr, mpu_rbar, mpu_rasr,
+    }
+}
+
+thr! {
+    thread => pub Thr {};
+    local => pub ThrLocal {};
+    vtable => pub Vtable;
+    index => pub Thrs;
+    init => pub ThrsInit;
+    threads => {
+        interrupts => {
+            39: pub usart3;
+        };
+    };
+}
+
+const STK_CTRL: usize = 0xE000_E010;
+
+#[test]
+fn timer_queue() {
+    let sim = Sim::lock();
+    let reg = unsafe { Regs::take() };
+    let thr = unsafe { Thrs::take() };
+    let queue = TimerQueue::new(SysTick::new(periph_sys_tick!(reg), thr.usart3), 100, thr.usart3);
+    let mut sleep = queue.clone().sleep(Duration::from_ticks(2));
+    let mut timeout = queue.timeout(Duration::from_ticks(3), future::pending::<()>());
+    let mut ready = queue.timeout(Duration::from_ticks(3), future::ready(5));
+    let mut deadline = queue.deadline(Instant::from_ticks(2), future::pending::<()>());
+    let cancelled = queue.sleep(Duration::from_ticks(1));
+    assert!(poll(&mut sleep).is_pending());
+    assert!(poll(&mut timeout).is_pending());
+    assert!(poll(&mut deadline).is_pending());
+    assert!(matches!(poll(&mut ready), Poll::Ready(Ok(5))));
+    drop(cancelled);
+    // The counter reaches zero on each thread run.
+    sim.write::<u32>(STK_CTRL, 1 << 16 | 0b11);
+    while queue.now() < Instant::from_ticks(2) {
+        unsafe { thr::thread_resume::<Thr>(Usart3::THR_IDX) };
+    }
+    assert!(poll(&mut sleep).is_ready());
+    assert!(matches!(poll(&mut deadline), Poll::Ready(Err(Elapsed))));
+    while queue.now() < Instant::from_ticks(3) {
+        unsafe { thr::thread_resume::<Thr>(Usart3::THR_IDX) };
+    }
+    assert!(matches!(poll(&mut timeout), Poll::Ready(Err(Elapsed))));
+}
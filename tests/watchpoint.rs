@@ -0,0 +1,96 @@
+#![cfg(feature = "std")]
+#![feature(const_fn_fn_ptr_basics)]
+#![feature(prelude_import)]
+
+#[prelude_import]
+#[allow(unused_imports)]
+use drone_core::prelude::*;
+
+use core::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+use drone_core::token::Token;
+use drone_cortexm::{
+    cortexm_reg_tokens,
+    drv::dwt::{WatchAccess, WatchpointError, Watchpoints},
+    map::periph::dwt::periph_dwt_watch,
+    sim::Sim,
+    thr,
+    thr::prelude::*,
+};
+use futures::{stream::Stream, task::noop_waker_ref};
+
+cortexm_reg_tokens! {
+    index => Regs;
+    exclude => {
+        scb_ccr,
+        mpu_type, mpu_ctrl, mpu_rnr, mpu_rbar, mpu_rasr,
+    }
+}
+
+thr! {
+    thread => pub Thr {};
+    local => pub ThrLocal {};
+    vtable => pub Vtable;
+    index => pub Thrs;
+    init => pub ThrsInit;
+    threads => {
+        exceptions => {
+            pub debug;
+        };
+    };
+}
+
+const DWT_CTRL: usize = 0xE000_1000;
+const DWT_COMP1: usize = 0xE000_1030;
+const DWT_MASK1: usize = 0xE000_1034;
+const DWT_FUNCTION1: usize = 0xE000_1038;
+const SCB_DEMCR: usize = 0xE000_EDFC;
+const SCB_DFSR: usize = 0xE000_ED30;
+
+#[test]
+fn dwt_watchpoint() {
+    static mut SHARED: u64 = 0;
+    let sim = Sim::lock();
+    let reg = unsafe { Regs::take() };
+    let thr = unsafe { Thrs::take() };
+    // Two comparators are implemented.
+    sim.write::<u32>(DWT_CTRL, 2 << 28);
+    let watchpoints = Watchpoints::new(periph_dwt_watch!(reg), thr.debug);
+    assert_eq!(sim.read::<u32>(SCB_DEMCR), 1 << 16);
+    assert_eq!(watchpoints.count(), 2);
+    let first = watchpoints.watch(0x2000_0000, 0x100, WatchAccess::Read).unwrap();
+    let second = watchpoints.watch_var(unsafe { &SHARED }, WatchAccess::Write).unwrap();
+    assert_eq!(first.comparator(), 0);
+    assert_eq!(second.comparator(), 1);
+    assert_eq!(sim.read::<u32>(DWT_COMP1), unsafe { &SHARED as *const u64 } as u32);
+    assert_eq!(sim.read::<u32>(DWT_MASK1), 3);
+    assert_eq!(sim.read::<u32>(DWT_FUNCTION1), 0b0110);
+    assert!(matches!(
+        watchpoints.watch(0x2000_0000, 4, WatchAccess::Write),
+        Err(WatchpointError::NoComparator)
+    ));
+    let mut hits = watchpoints.hits();
+    let mut cx = Context::from_waker(noop_waker_ref());
+    assert!(Pin::new(&mut hits).poll_next(&mut cx).is_pending());
+    // The second comparator has matched.
+    sim.write::<u32>(DWT_FUNCTION1, 1 << 24 | 0b0110);
+    sim.write::<u32>(SCB_DFSR, 1 << 2);
+    unsafe { thr::thread_resume::<Thr>(Debug::THR_IDX) };
+    match Pin::new(&mut hits).poll_next(&mut cx) {
+        Poll::Ready(Some(hit)) => {
+            assert_eq!(hit.comparator, 1);
+            assert_eq!(hit.address, unsafe { &SHARED as *const u64 } as usize);
+        }
+        _ => panic!("watchpoint hit is not reported"),
+    }
+    assert!(Pin::new(&mut hits).poll_next(&mut cx).is_pending());
+    drop(second);
+    assert_eq!(sim.read::<u32>(DWT_FUNCTION1), 0);
+    assert!(matches!(
+        watchpoints.watch(0x2000_0001, 2, WatchAccess::Write),
+        Err(WatchpointError::Unaligned)
+    ));
+    drop(first);
+}